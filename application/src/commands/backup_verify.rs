@@ -0,0 +1,112 @@
+use crate::server::backup::adapters::BackupAdapter;
+use clap::ArgMatches;
+use colored::Colorize;
+use std::{
+    io::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+pub async fn backup_verify(
+    matches: &ArgMatches,
+    config: Option<&Arc<crate::config::Config>>,
+) -> i32 {
+    let uuid = *matches.get_one::<uuid::Uuid>("backup").unwrap();
+    let checksum = matches.get_one::<String>("checksum");
+    let adapter = match matches.get_one::<String>("adapter") {
+        Some(adapter) => {
+            match serde_json::from_value::<BackupAdapter>(serde_json::Value::String(
+                adapter.clone(),
+            )) {
+                Ok(adapter) => Some(adapter),
+                Err(_) => {
+                    eprintln!("{} {adapter}", "unknown backup adapter".red());
+                    return 1;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let config = match config {
+        Some(config) => config,
+        None => {
+            eprintln!("{}", "no config found".red());
+            return 1;
+        }
+    };
+
+    let backup = match adapter {
+        Some(adapter) => adapter.find(config, uuid).await,
+        None => BackupAdapter::find_all(config, uuid)
+            .await
+            .map(|backup| backup.map(|(_, backup)| backup)),
+    };
+    let backup = match backup {
+        Ok(Some(backup)) => backup,
+        Ok(None) => {
+            eprintln!("{}", "backup not found".red());
+            return 1;
+        }
+        Err(err) => {
+            eprintln!("{}: {err:#?}", "failed to find backup".red());
+            return 1;
+        }
+    };
+
+    println!(
+        "verifying {} backup {}",
+        serde_json::to_value(backup.adapter())
+            .unwrap()
+            .as_str()
+            .unwrap(),
+        uuid.to_string().cyan()
+    );
+
+    let progress = Arc::new(AtomicU64::new(0));
+    let total = Arc::new(AtomicU64::new(1));
+
+    let progress_task = tokio::spawn({
+        let progress = Arc::clone(&progress);
+        let total = Arc::clone(&total);
+
+        async move {
+            loop {
+                let progress = progress.load(Ordering::SeqCst);
+                let total = total.load(Ordering::SeqCst).max(1);
+
+                print!(
+                    "\r{:.2}%",
+                    (progress.min(total) as f64 / total as f64) * 100.0
+                );
+                std::io::stdout().flush().ok();
+
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            }
+        }
+    });
+
+    let result = backup
+        .verify(config, progress, total, checksum.map(|c| c.as_str()), None)
+        .await;
+    progress_task.abort();
+    println!();
+
+    match result {
+        Ok(checksum) => {
+            println!("{}", "backup is intact".green());
+            if let Some(checksum) = checksum {
+                println!("checksum: {checksum}");
+            }
+
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: {err:#}", "backup verification failed".red());
+
+            1
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod backup_verify;
 pub mod configure;
 pub mod diagnostics;
 pub mod service_install;
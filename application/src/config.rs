@@ -54,6 +54,12 @@ fn api_file_compression_threads() -> usize {
 fn api_upload_limit() -> usize {
     100
 }
+fn api_upload_session_timeout() -> u64 {
+    60 * 60
+}
+fn api_download_range_token_uses() -> u8 {
+    32
+}
 
 fn system_root_directory() -> String {
     "/var/lib/pterodactyl".to_string()
@@ -73,6 +79,9 @@ fn system_backup_directory() -> String {
 fn system_tmp_directory() -> String {
     "/tmp/pterodactyl".to_string()
 }
+fn system_trash_directory() -> String {
+    "/var/lib/pterodactyl/trash".to_string()
+}
 fn system_username() -> String {
     "pterodactyl".to_string()
 }
@@ -113,6 +122,16 @@ fn system_check_permissions_on_boot_threads() -> usize {
 fn system_websocket_log_count() -> usize {
     150
 }
+fn system_websocket_watch_limit() -> usize {
+    256
+}
+
+fn system_resource_history_enabled() -> bool {
+    true
+}
+fn system_resource_history_directory() -> String {
+    "/var/lib/pterodactyl/stats".to_string()
+}
 
 fn system_sftp_bind_address() -> std::net::IpAddr {
     std::net::IpAddr::from([0, 0, 0, 0])
@@ -138,6 +157,14 @@ fn system_sftp_shell_cli_name() -> String {
     ".wings".to_string()
 }
 
+fn system_sftp_shell_container_command() -> Vec<String> {
+    vec!["/bin/sh".to_string()]
+}
+
+fn system_sftp_lockout_duration() -> u64 {
+    300
+}
+
 fn system_crash_detection_enabled() -> bool {
     true
 }
@@ -161,6 +188,9 @@ fn system_backup_wings_create_threads() -> usize {
 fn system_backup_wings_restore_threads() -> usize {
     4
 }
+fn system_backup_wings_incremental_max_chain_length() -> usize {
+    7
+}
 
 fn system_backup_s3_create_threads() -> usize {
     4
@@ -185,6 +215,9 @@ fn system_backup_restic_password_file() -> String {
 fn system_backup_restic_retry_lock_seconds() -> u64 {
     60
 }
+fn system_backup_restic_verify_read_data_subset() -> String {
+    "5%".to_string()
+}
 
 fn system_backup_btrfs_restore_threads() -> usize {
     4
@@ -197,6 +230,13 @@ fn system_backup_zfs_restore_threads() -> usize {
     4
 }
 
+fn system_transfers_chunk_size() -> u64 {
+    256
+}
+fn system_transfers_retry_limit() -> u64 {
+    10
+}
+
 fn docker_socket() -> String {
     "/var/run/docker.sock".to_string()
 }
@@ -282,6 +322,10 @@ fn throttles_line_reset_interval() -> u64 {
     100
 }
 
+fn metrics_sample_interval() -> u64 {
+    5
+}
+
 fn remote_query_timeout() -> u64 {
     30
 }
@@ -347,6 +391,12 @@ nestify::nest! {
             #[serde(default = "api_upload_limit")]
             /// MB
             pub upload_limit: usize,
+            #[serde(default = "api_upload_session_timeout")]
+            /// seconds until an abandoned resumable upload is discarded
+            pub upload_session_timeout: u64,
+            #[serde(default = "api_download_range_token_uses")]
+            /// how often a download token can be reused to resume the download it started (`If-Range` with its `ETag`)
+            pub download_range_token_uses: u8,
             #[serde(default)]
             pub trusted_proxies: Vec<std::net::IpAddr>,
         },
@@ -364,6 +414,8 @@ nestify::nest! {
             pub backup_directory: String,
             #[serde(default = "system_tmp_directory")]
             pub tmp_directory: String,
+            #[serde(default = "system_trash_directory")]
+            pub trash_directory: String,
 
             #[serde(default = "system_username")]
             pub username: String,
@@ -418,6 +470,16 @@ nestify::nest! {
             pub check_permissions_on_boot_threads: usize,
             #[serde(default = "system_websocket_log_count")]
             pub websocket_log_count: usize,
+            #[serde(default = "system_websocket_watch_limit")]
+            pub websocket_watch_limit: usize,
+
+            #[serde(default)]
+            pub resource_history: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemResourceHistory {
+                #[serde(default = "system_resource_history_enabled")]
+                pub enabled: bool,
+                #[serde(default = "system_resource_history_directory")]
+                pub directory: String,
+            },
 
             #[serde(default)]
             pub sftp: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftp {
@@ -447,6 +509,54 @@ nestify::nest! {
                         #[serde(default = "system_sftp_shell_cli_name")]
                         pub name: String,
                     },
+
+                    #[serde(default)]
+                    pub container: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftpShellContainer {
+                        #[serde(default)]
+                        pub enabled: bool,
+                        #[serde(default = "system_sftp_shell_container_command")]
+                        pub command: Vec<String>,
+                    },
+                },
+
+                #[serde(default)]
+                pub port_forwarding: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftpPortForwarding {
+                    #[serde(default)]
+                    pub enabled: bool,
+                },
+
+                #[serde(default)]
+                pub limits: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftpLimits {
+                    #[serde(default)]
+                    /// MiB/s
+                    pub server_read_limit: u64,
+                    #[serde(default)]
+                    /// MiB/s
+                    pub server_write_limit: u64,
+                    #[serde(default)]
+                    /// MiB/s
+                    pub user_read_limit: u64,
+                    #[serde(default)]
+                    /// MiB/s
+                    pub user_write_limit: u64,
+                    #[serde(default)]
+                    pub user_sessions: usize,
+                },
+
+                #[serde(default)]
+                pub lockout: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftpLockout {
+                    #[serde(default)]
+                    pub max_attempts: u32,
+                    #[serde(default = "system_sftp_lockout_duration")]
+                    /// seconds
+                    pub duration: u64,
+                },
+
+                #[serde(default)]
+                pub certificates: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemSftpCertificates {
+                    /// OpenSSH public keys of the certificate authorities trusted to sign user certificates
+                    #[serde(default)]
+                    pub trusted_user_ca_keys: Vec<String>,
                 },
             },
 
@@ -471,6 +581,15 @@ nestify::nest! {
                 #[serde(default)]
                 pub compression_level: crate::io::compression::CompressionLevel,
 
+                #[serde(default)]
+                pub encryption: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemBackupsEncryption {
+                    #[serde(default)]
+                    pub enabled: bool,
+                    /// Secret the backup keys are derived from, backups can not be read without it
+                    #[serde(default)]
+                    pub key: String,
+                },
+
                 #[serde(default)]
                 pub mounting: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemBackupsMounting {
                     #[serde(default = "system_backup_mounting_enabled")]
@@ -487,13 +606,22 @@ nestify::nest! {
                     pub restore_threads: usize,
 
                     #[serde(default)]
-                    pub archive_format: #[derive(Clone, Copy, Deserialize, Serialize, Default)] #[serde(rename_all = "snake_case")] pub enum SystemBackupsWingsArchiveFormat {
+                    pub archive_format: #[derive(Clone, Copy, Deserialize, Serialize, Default, PartialEq)] #[serde(rename_all = "snake_case")] pub enum SystemBackupsWingsArchiveFormat {
                         Tar,
                         #[default]
                         TarGz,
                         TarZstd,
                         Zip,
                     },
+
+                    #[serde(default)]
+                    pub incremental: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemBackupsWingsIncremental {
+                        #[serde(default)]
+                        pub enabled: bool,
+                        /// Amount of backups in a chain, including the full backup it starts with
+                        #[serde(default = "system_backup_wings_incremental_max_chain_length")]
+                        pub max_chain_length: usize,
+                    },
                 },
                 #[serde(default)]
                 pub s3: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct SystemBackupsS3 {
@@ -527,6 +655,9 @@ nestify::nest! {
 
                     #[serde(default = "system_backup_restic_retry_lock_seconds")]
                     pub retry_lock_seconds: u64,
+                    /// Passed to `restic check --read-data-subset` when verifying a backup
+                    #[serde(default = "system_backup_restic_verify_read_data_subset")]
+                    pub verify_read_data_subset: String,
                     #[serde(default)]
                     pub environment: BTreeMap<String, String>,
                 },
@@ -550,6 +681,12 @@ nestify::nest! {
                 #[serde(default)]
                 /// MiB/s
                 pub download_limit: u64,
+
+                #[serde(default = "system_transfers_chunk_size")]
+                /// MiB, only used by chunked transfers
+                pub chunk_size: u64,
+                #[serde(default = "system_transfers_retry_limit")]
+                pub retry_limit: u64,
             },
         },
         #[serde(default)]
@@ -664,6 +801,16 @@ nestify::nest! {
             pub line_reset_interval: u64,
         },
 
+        #[serde(default)]
+        pub metrics: #[derive(Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct Metrics {
+            #[serde(default)]
+            pub enabled: bool,
+            #[serde(default)]
+            pub token: String,
+            #[serde(default = "metrics_sample_interval")]
+            pub sample_interval: u64,
+        },
+
         pub remote: String,
         #[serde(default)]
         pub remote_query: #[derive(Clone, Copy, Deserialize, Serialize, DefaultFromSerde)] #[serde(default)] pub struct RemoteQuery {
@@ -877,6 +1024,7 @@ impl Config {
             &self.system.archive_directory,
             &self.system.backup_directory,
             &self.system.tmp_directory,
+            &self.system.trash_directory,
         ];
 
         for dir in directories {
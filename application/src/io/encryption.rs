@@ -0,0 +1,388 @@
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::AeadInPlace};
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    os::unix::fs::FileExt,
+    sync::Arc,
+};
+
+pub const MAGIC: &[u8; 8] = b"WINGSENC";
+const VERSION: u8 = 1;
+
+/// magic, version, key id, salt and nonce prefix
+pub const HEADER_SIZE: usize = 8 + 1 + 16 + 32 + 7;
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+const ENCRYPTED_SEGMENT_SIZE: usize = SEGMENT_SIZE + TAG_SIZE;
+
+#[derive(Clone, Copy)]
+pub struct EncryptionHeader {
+    /// Identifies the master key the stream was encrypted with, nil for the node key
+    pub key_id: uuid::Uuid,
+    salt: [u8; 32],
+    nonce_prefix: [u8; 7],
+}
+
+impl EncryptionHeader {
+    pub fn new(key_id: uuid::Uuid) -> Self {
+        let mut salt = [0; 32];
+        let mut nonce_prefix = [0; 7];
+        rand::fill(&mut salt);
+        rand::fill(&mut nonce_prefix);
+
+        Self {
+            key_id,
+            salt,
+            nonce_prefix,
+        }
+    }
+
+    /// Returns `None` if the data does not start with an encryption header
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE || &data[0..8] != MAGIC || data[8] != VERSION {
+            return None;
+        }
+
+        Some(Self {
+            key_id: uuid::Uuid::from_slice(&data[9..25]).ok()?,
+            salt: data[25..57].try_into().ok()?,
+            nonce_prefix: data[57..64].try_into().ok()?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut data = [0; HEADER_SIZE];
+        data[0..8].copy_from_slice(MAGIC);
+        data[8] = VERSION;
+        data[9..25].copy_from_slice(self.key_id.as_bytes());
+        data[25..57].copy_from_slice(&self.salt);
+        data[57..64].copy_from_slice(&self.nonce_prefix);
+
+        data
+    }
+
+    fn cipher(&self, master_key: &[u8]) -> Aes256Gcm {
+        let mut key = [0; 32];
+        hkdf::Hkdf::<sha2::Sha256>::new(Some(&self.salt), master_key)
+            .expand(b"wings backup encryption", &mut key)
+            .expect("32 bytes is a valid hkdf output length");
+
+        Aes256Gcm::new(&key.into())
+    }
+
+    fn nonce(&self, segment: u64, last: bool) -> std::io::Result<[u8; 12]> {
+        let segment: u32 = segment
+            .try_into()
+            .map_err(|_| std::io::Error::other("encrypted stream has too many segments"))?;
+
+        let mut nonce = [0; 12];
+        nonce[0..7].copy_from_slice(&self.nonce_prefix);
+        nonce[7..11].copy_from_slice(&segment.to_be_bytes());
+        nonce[11] = last as u8;
+
+        Ok(nonce)
+    }
+}
+
+/// Reads until the buffer is full or the reader is exhausted
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(filled)
+}
+
+fn decrypt_segment(
+    header: &EncryptionHeader,
+    cipher: &Aes256Gcm,
+    segment: u64,
+    last: bool,
+    buffer: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    let nonce = header.nonce(segment, last)?;
+
+    cipher
+        .decrypt_in_place(Nonce::from_slice(&nonce), b"", buffer)
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "failed to authenticate encrypted data, wrong key or damaged file",
+            )
+        })
+}
+
+pub struct EncryptionWriter<W: Write> {
+    inner: W,
+    header: EncryptionHeader,
+    cipher: Aes256Gcm,
+
+    segment: u64,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptionWriter<W> {
+    pub fn new(mut inner: W, key_id: uuid::Uuid, master_key: &[u8]) -> std::io::Result<Self> {
+        let header = EncryptionHeader::new(key_id);
+        inner.write_all(&header.to_bytes())?;
+
+        Ok(Self {
+            inner,
+            cipher: header.cipher(master_key),
+            header,
+            segment: 0,
+            buffer: Vec::with_capacity(ENCRYPTED_SEGMENT_SIZE),
+        })
+    }
+
+    fn write_segment(&mut self, last: bool) -> std::io::Result<()> {
+        let nonce = self.header.nonce(self.segment, last)?;
+
+        self.cipher
+            .encrypt_in_place(Nonce::from_slice(&nonce), b"", &mut self.buffer)
+            .map_err(|_| std::io::Error::other("failed to encrypt segment"))?;
+        self.inner.write_all(&self.buffer)?;
+
+        self.buffer.clear();
+        self.segment += 1;
+
+        Ok(())
+    }
+
+    /// Writes the final segment, a stream that is not finished fails to decrypt
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if self.buffer.len() == SEGMENT_SIZE {
+            self.write_segment(false)?;
+        }
+        self.write_segment(true)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptionWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.buffer.len() == SEGMENT_SIZE {
+            self.write_segment(false)?;
+        }
+
+        let len = buf.len().min(SEGMENT_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct DecryptionReader<R: Read> {
+    inner: R,
+    header: EncryptionHeader,
+    cipher: Aes256Gcm,
+
+    segment: u64,
+    finished: bool,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptionReader<R> {
+    /// The header has to be consumed from `inner` already
+    pub fn new(inner: R, header: EncryptionHeader, master_key: &[u8]) -> Self {
+        Self {
+            inner,
+            cipher: header.cipher(master_key),
+            header,
+            segment: 0,
+            finished: false,
+            buffer: Vec::with_capacity(ENCRYPTED_SEGMENT_SIZE),
+            position: 0,
+        }
+    }
+
+    fn read_segment(&mut self) -> std::io::Result<()> {
+        self.buffer.resize(ENCRYPTED_SEGMENT_SIZE, 0);
+        let bytes_read = read_full(&mut self.inner, &mut self.buffer)?;
+        self.buffer.truncate(bytes_read);
+        self.position = 0;
+
+        if bytes_read < TAG_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "encrypted stream ended early",
+            ));
+        }
+
+        let last = bytes_read < ENCRYPTED_SEGMENT_SIZE;
+        decrypt_segment(
+            &self.header,
+            &self.cipher,
+            self.segment,
+            last,
+            &mut self.buffer,
+        )?;
+
+        self.segment += 1;
+        self.finished = last;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.read_segment()?;
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// Random access to an encrypted file, clones share the file but not the position
+#[derive(Clone)]
+pub struct EncryptedFile {
+    file: Arc<std::fs::File>,
+    header: EncryptionHeader,
+    cipher: Aes256Gcm,
+
+    segments: u64,
+    len: u64,
+    position: u64,
+    cache: Option<(u64, Vec<u8>)>,
+}
+
+impl EncryptedFile {
+    pub fn new(
+        file: Arc<std::fs::File>,
+        header: EncryptionHeader,
+        master_key: &[u8],
+    ) -> std::io::Result<Self> {
+        let encrypted_len = file
+            .metadata()?
+            .len()
+            .checked_sub(HEADER_SIZE as u64)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+
+        let full_segments = encrypted_len / ENCRYPTED_SEGMENT_SIZE as u64;
+        let remainder = encrypted_len % ENCRYPTED_SEGMENT_SIZE as u64;
+        if remainder < TAG_SIZE as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "encrypted file is truncated",
+            ));
+        }
+
+        Ok(Self {
+            file,
+            cipher: header.cipher(master_key),
+            header,
+            segments: full_segments + 1,
+            len: full_segments * SEGMENT_SIZE as u64 + remainder - TAG_SIZE as u64,
+            position: 0,
+            cache: None,
+        })
+    }
+
+    /// Size of the decrypted contents
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn load_segment(&mut self, segment: u64) -> std::io::Result<&[u8]> {
+        if !matches!(&self.cache, Some((cached, _)) if *cached == segment) {
+            let last = segment + 1 == self.segments;
+            let mut buffer = self
+                .cache
+                .take()
+                .map(|(_, buffer)| buffer)
+                .unwrap_or_default();
+            buffer.resize(ENCRYPTED_SEGMENT_SIZE, 0);
+
+            let offset = HEADER_SIZE as u64 + segment * ENCRYPTED_SEGMENT_SIZE as u64;
+            let mut filled = 0;
+            while filled < buffer.len() {
+                match self
+                    .file
+                    .read_at(&mut buffer[filled..], offset + filled as u64)?
+                {
+                    0 => break,
+                    n => filled += n,
+                }
+            }
+            buffer.truncate(filled);
+
+            decrypt_segment(&self.header, &self.cipher, segment, last, &mut buffer)?;
+            self.cache = Some((segment, buffer));
+        }
+
+        Ok(self
+            .cache
+            .as_ref()
+            .map(|(_, buffer)| buffer.as_slice())
+            .unwrap())
+    }
+}
+
+impl Read for EncryptedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let segment = self.position / SEGMENT_SIZE as u64;
+        let offset = (self.position % SEGMENT_SIZE as u64) as usize;
+
+        let data = self.load_segment(segment)?;
+        let len = buf.len().min(data.len().saturating_sub(offset));
+        buf[..len].copy_from_slice(&data[offset..offset + len]);
+
+        self.position += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl Seek for EncryptedFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+
+                Ok(position)
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
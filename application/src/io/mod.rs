@@ -5,6 +5,7 @@ pub mod compression;
 
 pub mod counting_reader;
 pub mod counting_writer;
+pub mod encryption;
 pub mod fixed_reader;
 pub mod limited_reader;
 pub mod limited_writer;
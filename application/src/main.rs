@@ -145,9 +145,41 @@ fn cli() -> Command {
                 )
                 .arg_required_else_help(false),
         )
+        .subcommand(
+            Command::new("backup-verify")
+                .about("Re-reads a stored backup and checks that it is intact.")
+                .arg(
+                    Arg::new("backup")
+                        .help("the uuid of the backup to verify")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(uuid::Uuid))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("adapter")
+                        .help("the adapter the backup was created with, all adapters are searched if omitted")
+                        .num_args(1)
+                        .short('a')
+                        .long("adapter")
+                        .value_parser(["wings", "s3", "ddup-bak", "btrfs", "zfs", "restic"])
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("checksum")
+                        .help("the checksum recorded by the panel, verification fails if it differs")
+                        .num_args(1)
+                        .long("checksum")
+                        .required(false),
+                )
+                .arg_required_else_help(true),
+        )
 }
 
-async fn handle_request(req: Request<Body>, next: Next) -> Result<Response<Body>, StatusCode> {
+async fn handle_request(
+    state: wings_rs::routes::GetState,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response<Body>, StatusCode> {
     tracing::info!(
         "http {} {}{}",
         req.method().to_string().to_lowercase(),
@@ -160,7 +192,16 @@ async fn handle_request(req: Request<Body>, next: Next) -> Result<Response<Body>
         .bright_cyan()
     );
 
-    Ok(next.run(req).await)
+    let method = req.method().clone();
+    let start = Instant::now();
+    let response = next.run(req).await;
+
+    state
+        .metrics
+        .record_http_request(&method, response.status(), start.elapsed())
+        .await;
+
+    Ok(response)
 }
 
 async fn handle_cors(
@@ -262,6 +303,13 @@ async fn main() {
             )
             .await,
         ),
+        Some(("backup-verify", sub_matches)) => std::process::exit(
+            wings_rs::commands::backup_verify::backup_verify(
+                sub_matches,
+                config.as_ref().ok().map(|c| &c.0),
+            )
+            .await,
+        ),
         None => {
             tracing::info!(" __      ___ _ __   __ _ ___        ");
             tracing::info!(" \\ \\ /\\ / / | '_ \\ / _` / __|       ");
@@ -345,9 +393,14 @@ async fn main() {
         backup_manager: Arc::new(wings_rs::server::backup::manager::BackupManager::new(
             Arc::clone(&config),
         )),
+        upload_manager: Arc::new(wings_rs::server::upload::UploadManager::new(Arc::clone(
+            &config,
+        ))),
+        metrics: Arc::new(wings_rs::metrics::Metrics::new()),
     });
 
     state.server_manager.boot(&state, servers).await;
+    state.metrics.boot(&state);
 
     let app = OpenApiRouter::new()
         .merge(wings_rs::routes::router(&state))
@@ -361,7 +414,10 @@ async fn main() {
             state.clone(),
             handle_cors,
         ))
-        .layer(axum::middleware::from_fn(handle_request))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            handle_request,
+        ))
         .with_state(state.clone());
 
     let (mut router, mut openapi) = app.split_for_parts();
@@ -392,9 +448,7 @@ async fn main() {
         let state = Arc::clone(&state);
 
         async move {
-            let mut server = wings_rs::ssh::Server {
-                state: Arc::clone(&state),
-            };
+            let mut server = wings_rs::ssh::Server::new(Arc::clone(&state));
 
             let key_file = Path::new(&state.config.system.data_directory)
                 .join(".sftp")
@@ -0,0 +1,555 @@
+use crate::server::{resources::ResourceUsage, state::ServerState};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use sysinfo::{Disks, Networks, System};
+use tokio::sync::{Mutex, RwLock};
+
+const HTTP_DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+type ServerGauge = (&'static str, &'static str, fn(&ResourceUsage) -> f64);
+
+#[derive(Default, Clone)]
+pub struct NodeStats {
+    pub cpu_used: f32,
+    pub cpu_threads: usize,
+    pub cpu_model: String,
+
+    pub memory_used: u64,
+    pub memory_total: u64,
+
+    pub disk_used: u64,
+    pub disk_total: u64,
+    pub disk_read: u64,
+    pub disk_reading_rate: f64,
+    pub disk_written: u64,
+    pub disk_writing_rate: f64,
+
+    pub network_received: u64,
+    pub network_receiving_rate: f64,
+    pub network_sent: u64,
+    pub network_sending_rate: f64,
+}
+
+struct ServerSample {
+    uuid: uuid::Uuid,
+    usage: ResourceUsage,
+    operations: usize,
+}
+
+#[derive(Default)]
+struct ServerSamples {
+    servers: Vec<ServerSample>,
+    outgoing_transfers: usize,
+    incoming_transfers: usize,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; HTTP_DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+pub struct Metrics {
+    node: RwLock<NodeStats>,
+    servers: RwLock<ServerSamples>,
+    http_requests: Mutex<BTreeMap<(String, u16), Histogram>>,
+
+    pub sftp_sessions: AtomicU64,
+    pub backups_running: AtomicU64,
+    pub backups_successful: AtomicU64,
+    pub backups_failed: AtomicU64,
+    pub backup_restores_running: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            node: RwLock::new(NodeStats::default()),
+            servers: RwLock::new(ServerSamples::default()),
+            http_requests: Mutex::new(BTreeMap::new()),
+
+            sftp_sessions: AtomicU64::new(0),
+            backups_running: AtomicU64::new(0),
+            backups_successful: AtomicU64::new(0),
+            backups_failed: AtomicU64::new(0),
+            backup_restores_running: AtomicU64::new(0),
+        }
+    }
+
+    /// Starts the background sampler feeding both `/metrics` and `/api/stats`
+    pub fn boot(&self, state: &crate::routes::State) {
+        tokio::spawn({
+            let state = std::sync::Arc::clone(state);
+
+            async move {
+                let mut sys = System::new_all();
+                let mut disks = Disks::new_with_refreshed_list();
+                let mut networks = Networks::new_with_refreshed_list();
+                let mut last_refresh = std::time::Instant::now();
+
+                tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+
+                loop {
+                    sys.refresh_all();
+                    disks.refresh(true);
+                    networks.refresh(true);
+
+                    let elapsed = last_refresh.elapsed().as_secs_f64();
+                    last_refresh = std::time::Instant::now();
+
+                    *state.metrics.node.write().await =
+                        Self::sample_node(&sys, &disks, &networks, elapsed);
+
+                    let mut samples = ServerSamples::default();
+                    let servers = state.server_manager.get_servers().await.clone();
+                    for server in servers {
+                        if server.outgoing_transfer.read().await.is_some() {
+                            samples.outgoing_transfers += 1;
+                        }
+                        if server.incoming_transfer.read().await.is_some() {
+                            samples.incoming_transfers += 1;
+                        }
+
+                        samples.servers.push(ServerSample {
+                            uuid: server.uuid,
+                            usage: server.resource_usage().await,
+                            operations: server.filesystem.operations.operations().await.len(),
+                        });
+                    }
+
+                    *state.metrics.servers.write().await = samples;
+
+                    tokio::time::sleep(std::time::Duration::from_secs(
+                        state.config.metrics.sample_interval.max(1),
+                    ))
+                    .await;
+                }
+            }
+        });
+    }
+
+    fn sample_node(sys: &System, disks: &Disks, networks: &Networks, elapsed: f64) -> NodeStats {
+        let mut stats = NodeStats {
+            cpu_used: sys.global_cpu_usage(),
+            cpu_threads: sys.cpus().len(),
+            cpu_model: sys
+                .cpus()
+                .first()
+                .map_or_else(|| "unknown".to_string(), |cpu| cpu.brand().to_string()),
+            memory_used: sys.used_memory(),
+            memory_total: sys.total_memory(),
+            ..Default::default()
+        };
+
+        if let Some(disk) = disks
+            .iter()
+            .find(|d| d.mount_point() == Path::new("/"))
+            .or_else(|| disks.iter().next())
+        {
+            stats.disk_total = disk.total_space();
+            stats.disk_used = disk.total_space() - disk.available_space();
+            stats.disk_read = disk.usage().total_read_bytes;
+            stats.disk_reading_rate = disk.usage().read_bytes as f64 / elapsed;
+            stats.disk_written = disk.usage().total_written_bytes;
+            stats.disk_writing_rate = disk.usage().written_bytes as f64 / elapsed;
+        }
+
+        for (_, network) in networks.iter() {
+            stats.network_received += network.total_received();
+            stats.network_receiving_rate += network.received() as f64 / elapsed;
+            stats.network_sent += network.total_transmitted();
+            stats.network_sending_rate += network.transmitted() as f64 / elapsed;
+        }
+
+        stats
+    }
+
+    #[inline]
+    pub async fn node(&self) -> NodeStats {
+        self.node.read().await.clone()
+    }
+
+    pub async fn record_http_request(
+        &self,
+        method: &axum::http::Method,
+        status: axum::http::StatusCode,
+        duration: std::time::Duration,
+    ) {
+        let seconds = duration.as_secs_f64();
+
+        let mut http_requests = self.http_requests.lock().await;
+        let histogram = http_requests
+            .entry((method.to_string(), status.as_u16()))
+            .or_default();
+
+        for (i, bound) in HTTP_DURATION_BUCKETS.iter().enumerate() {
+            if seconds <= *bound {
+                histogram.buckets[i] += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// Renders all metrics in the OpenMetrics text format
+    pub async fn render(&self, state: &crate::routes::AppState) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "wings_build",
+            "info",
+            "Information about this wings instance",
+        );
+        writeln!(
+            out,
+            "wings_build_info{{version=\"{}\"}} 1",
+            escape(&state.version)
+        )
+        .ok();
+        header(
+            &mut out,
+            "wings_uptime_seconds",
+            "gauge",
+            "Seconds since wings started",
+        );
+        writeln!(
+            out,
+            "wings_uptime_seconds {}",
+            state.start_time.elapsed().as_secs()
+        )
+        .ok();
+
+        let node = self.node.read().await;
+        for (name, kind, help, value) in [
+            (
+                "wings_node_cpu_usage_percent",
+                "gauge",
+                "Total cpu usage of the node",
+                node.cpu_used as f64,
+            ),
+            (
+                "wings_node_cpu_threads",
+                "gauge",
+                "Amount of cpu threads of the node",
+                node.cpu_threads as f64,
+            ),
+            (
+                "wings_node_memory_used_bytes",
+                "gauge",
+                "Used memory of the node",
+                node.memory_used as f64,
+            ),
+            (
+                "wings_node_memory_total_bytes",
+                "gauge",
+                "Total memory of the node",
+                node.memory_total as f64,
+            ),
+            (
+                "wings_node_disk_used_bytes",
+                "gauge",
+                "Used disk space of the root filesystem",
+                node.disk_used as f64,
+            ),
+            (
+                "wings_node_disk_total_bytes",
+                "gauge",
+                "Total disk space of the root filesystem",
+                node.disk_total as f64,
+            ),
+            (
+                "wings_node_disk_read_bytes",
+                "counter",
+                "Bytes read from the root filesystem",
+                node.disk_read as f64,
+            ),
+            (
+                "wings_node_disk_written_bytes",
+                "counter",
+                "Bytes written to the root filesystem",
+                node.disk_written as f64,
+            ),
+            (
+                "wings_node_network_received_bytes",
+                "counter",
+                "Bytes received over all interfaces",
+                node.network_received as f64,
+            ),
+            (
+                "wings_node_network_transmitted_bytes",
+                "counter",
+                "Bytes transmitted over all interfaces",
+                node.network_sent as f64,
+            ),
+        ] {
+            header(&mut out, name, kind, help);
+            if kind == "counter" {
+                writeln!(out, "{name}_total {value}").ok();
+            } else {
+                writeln!(out, "{name} {value}").ok();
+            }
+        }
+        drop(node);
+
+        let samples = self.servers.read().await;
+        header(
+            &mut out,
+            "wings_servers",
+            "gauge",
+            "Amount of servers on this node",
+        );
+        writeln!(out, "wings_servers {}", samples.servers.len()).ok();
+
+        let server_gauges: [ServerGauge; 7] = [
+            (
+                "wings_server_memory_bytes",
+                "Memory used by the server container",
+                |usage| usage.memory_bytes as f64,
+            ),
+            (
+                "wings_server_memory_limit_bytes",
+                "Memory limit of the server container",
+                |usage| usage.memory_limit_bytes as f64,
+            ),
+            (
+                "wings_server_disk_bytes",
+                "Disk space used by the server",
+                |usage| usage.disk_bytes as f64,
+            ),
+            (
+                "wings_server_cpu_absolute_percent",
+                "Cpu usage of the server container, 100 per thread",
+                |usage| usage.cpu_absolute,
+            ),
+            (
+                "wings_server_network_received_bytes",
+                "Bytes received by the server container since it started",
+                |usage| usage.network.rx_bytes as f64,
+            ),
+            (
+                "wings_server_network_transmitted_bytes",
+                "Bytes transmitted by the server container since it started",
+                |usage| usage.network.tx_bytes as f64,
+            ),
+            (
+                "wings_server_uptime_seconds",
+                "Seconds since the server container started",
+                |usage| usage.uptime as f64 / 1000.0,
+            ),
+        ];
+
+        for (name, help, value) in server_gauges {
+            header(&mut out, name, "gauge", help);
+            for server in samples.servers.iter() {
+                writeln!(
+                    out,
+                    "{name}{{server=\"{}\"}} {}",
+                    server.uuid,
+                    value(&server.usage)
+                )
+                .ok();
+            }
+        }
+
+        header(
+            &mut out,
+            "wings_server_state",
+            "stateset",
+            "Current state of the server",
+        );
+        for server in samples.servers.iter() {
+            for state in [
+                ServerState::Offline,
+                ServerState::Starting,
+                ServerState::Stopping,
+                ServerState::Running,
+            ] {
+                writeln!(
+                    out,
+                    "wings_server_state{{server=\"{}\",wings_server_state=\"{}\"}} {}",
+                    server.uuid,
+                    state.to_str(),
+                    u8::from(server.usage.state == state)
+                )
+                .ok();
+            }
+        }
+
+        header(
+            &mut out,
+            "wings_server_operations",
+            "gauge",
+            "Running filesystem operations of the server",
+        );
+        for server in samples.servers.iter() {
+            writeln!(
+                out,
+                "wings_server_operations{{server=\"{}\"}} {}",
+                server.uuid, server.operations
+            )
+            .ok();
+        }
+
+        header(
+            &mut out,
+            "wings_transfers",
+            "gauge",
+            "Running server transfers",
+        );
+        writeln!(
+            out,
+            "wings_transfers{{direction=\"outgoing\"}} {}",
+            samples.outgoing_transfers
+        )
+        .ok();
+        writeln!(
+            out,
+            "wings_transfers{{direction=\"incoming\"}} {}",
+            samples.incoming_transfers
+        )
+        .ok();
+        drop(samples);
+
+        header(
+            &mut out,
+            "wings_backups_running",
+            "gauge",
+            "Backups currently being created",
+        );
+        writeln!(
+            out,
+            "wings_backups_running {}",
+            self.backups_running.load(Ordering::Relaxed)
+        )
+        .ok();
+        header(
+            &mut out,
+            "wings_backups",
+            "counter",
+            "Finished backups since wings started",
+        );
+        writeln!(
+            out,
+            "wings_backups_total{{result=\"successful\"}} {}",
+            self.backups_successful.load(Ordering::Relaxed)
+        )
+        .ok();
+        writeln!(
+            out,
+            "wings_backups_total{{result=\"failed\"}} {}",
+            self.backups_failed.load(Ordering::Relaxed)
+        )
+        .ok();
+        header(
+            &mut out,
+            "wings_backup_restores_running",
+            "gauge",
+            "Backups currently being restored",
+        );
+        writeln!(
+            out,
+            "wings_backup_restores_running {}",
+            self.backup_restores_running.load(Ordering::Relaxed)
+        )
+        .ok();
+
+        header(
+            &mut out,
+            "wings_sftp_sessions",
+            "gauge",
+            "Open sftp sessions",
+        );
+        writeln!(
+            out,
+            "wings_sftp_sessions {}",
+            self.sftp_sessions.load(Ordering::Relaxed)
+        )
+        .ok();
+
+        header(
+            &mut out,
+            "wings_http_request_duration_seconds",
+            "histogram",
+            "Time until the response headers of http requests were sent",
+        );
+        for ((method, status), histogram) in self.http_requests.lock().await.iter() {
+            let method = escape(method);
+
+            for (bound, count) in HTTP_DURATION_BUCKETS.iter().zip(histogram.buckets) {
+                writeln!(
+                    out,
+                    "wings_http_request_duration_seconds_bucket{{method=\"{method}\",status=\"{status}\",le=\"{bound:?}\"}} {count}"
+                )
+                .ok();
+            }
+            writeln!(
+                out,
+                "wings_http_request_duration_seconds_bucket{{method=\"{method}\",status=\"{status}\",le=\"+Inf\"}} {}",
+                histogram.count
+            )
+            .ok();
+            writeln!(
+                out,
+                "wings_http_request_duration_seconds_sum{{method=\"{method}\",status=\"{status}\"}} {}",
+                histogram.sum
+            )
+            .ok();
+            writeln!(
+                out,
+                "wings_http_request_duration_seconds_count{{method=\"{method}\",status=\"{status}\"}} {}",
+                histogram.count
+            )
+            .ok();
+        }
+
+        out.push_str("# EOF\n");
+
+        out
+    }
+}
+
+#[inline]
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# TYPE {name} {kind}").ok();
+    writeln!(out, "# HELP {name} {help}").ok();
+}
+
+#[inline]
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Increments a gauge for as long as the guard is alive
+pub struct GaugeGuard<'a>(&'a AtomicU64);
+
+impl<'a> GaugeGuard<'a> {
+    #[inline]
+    pub fn new(gauge: &'a AtomicU64) -> Self {
+        gauge.fetch_add(1, Ordering::Relaxed);
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -56,6 +56,17 @@ pub async fn set_backup_status(
     Ok(())
 }
 
+pub async fn delete_backup(client: &Client, uuid: uuid::Uuid) -> Result<(), anyhow::Error> {
+    client
+        .client
+        .delete(format!("{}/backups/{}", client.url, uuid))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
 pub async fn set_backup_restore_status(
     client: &Client,
     uuid: uuid::Uuid,
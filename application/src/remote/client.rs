@@ -113,6 +113,22 @@ impl Client {
         .await
     }
 
+    #[tracing::instrument(skip(self))]
+    pub async fn get_sftp_certificate_auth(
+        &self,
+        username: &str,
+        key_id: &str,
+        fingerprint: &str,
+    ) -> Result<(uuid::Uuid, uuid::Uuid, Permissions, Vec<String>), anyhow::Error> {
+        tracing::debug!("getting sftp certificate auth");
+
+        self.retry(
+            || super::get_sftp_certificate_auth(self, username, key_id, fingerprint),
+            Self::skip_client_errors,
+        )
+        .await
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn send_activity(&self, activity: Vec<ApiActivity>) -> Result<(), anyhow::Error> {
         tracing::debug!("sending {} activity to remote", activity.len());
@@ -272,6 +288,17 @@ impl Client {
         .await
     }
 
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_backup(&self, uuid: uuid::Uuid) -> Result<(), anyhow::Error> {
+        tracing::info!("reporting backup deletion");
+
+        self.retry(
+            || super::backups::delete_backup(self, uuid),
+            Self::skip_client_errors,
+        )
+        .await
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn set_backup_restore_status(
         &self,
@@ -60,6 +60,7 @@ impl BasePayload {
 }
 
 type CountingMap = HashMap<String, (u8, chrono::DateTime<chrono::Utc>)>;
+type DownloadSessionMap = HashMap<String, (String, u8, chrono::DateTime<chrono::Utc>)>;
 
 pub struct JwtClient {
     pub key: hmac::Hmac<sha2::Sha256>,
@@ -67,16 +68,19 @@ pub struct JwtClient {
 
     pub denied_jtokens: Arc<RwLock<HashMap<String, chrono::DateTime<chrono::Utc>>>>,
     pub seen_jtoken_ids: Arc<RwLock<CountingMap>>,
+    pub download_sessions: Arc<RwLock<DownloadSessionMap>>,
 }
 
 impl JwtClient {
     pub fn new(key: &str) -> Self {
         let denied_jtokens = Arc::new(RwLock::new(HashMap::new()));
         let seen_jtoken_ids = Arc::new(RwLock::new(HashMap::new()));
+        let download_sessions = Arc::new(RwLock::new(HashMap::new()));
 
         tokio::spawn({
             let denied_jtokens = Arc::clone(&denied_jtokens);
             let seen_jtoken_ids = Arc::clone(&seen_jtoken_ids);
+            let download_sessions = Arc::clone(&download_sessions);
 
             async move {
                 loop {
@@ -93,6 +97,12 @@ impl JwtClient {
                         expiration > chrono::Utc::now() - chrono::Duration::hours(1)
                     });
                     drop(seen);
+
+                    let mut sessions = download_sessions.write().await;
+                    sessions.retain(|_, &mut (_, _, expiration)| {
+                        expiration > chrono::Utc::now() - chrono::Duration::hours(1)
+                    });
+                    drop(sessions);
                 }
             }
         });
@@ -103,6 +113,7 @@ impl JwtClient {
 
             denied_jtokens,
             seen_jtoken_ids,
+            download_sessions,
         }
     }
 
@@ -135,6 +146,40 @@ impl JwtClient {
         true
     }
 
+    /// Uses a one-time download token, requests that resume the download the token
+    /// started (same `ETag`) can reuse it up to `resume_uses` times
+    pub async fn download_session_id(
+        &self,
+        id: &str,
+        etag: &str,
+        resume: bool,
+        resume_uses: u8,
+    ) -> bool {
+        if resume {
+            let mut sessions = self.download_sessions.write().await;
+            if let Some((session_etag, count, _)) = sessions.get_mut(id)
+                && session_etag == etag
+                && *count < resume_uses
+            {
+                *count += 1;
+
+                return true;
+            }
+        }
+
+        if !self.one_time_id(id).await {
+            return false;
+        }
+
+        self.download_sessions
+            .write()
+            .await
+            .entry(id.to_string())
+            .or_insert_with(|| (etag.to_string(), 0, chrono::Utc::now()));
+
+        true
+    }
+
     pub async fn deny(&self, id: impl Into<String>) {
         let mut denied = self.denied_jtokens.write().await;
         denied.insert(id.into(), chrono::Utc::now());
@@ -35,13 +35,30 @@ pub enum AuthenticationType {
     PublicKey,
 }
 
+#[derive(Deserialize)]
+struct SftpAuthResponse {
+    user: uuid::Uuid,
+    server: uuid::Uuid,
+
+    permissions: Permissions,
+    #[serde(default)]
+    ignored_files: Vec<String>,
+}
+
+impl SftpAuthResponse {
+    #[inline]
+    fn into_parts(self) -> (uuid::Uuid, uuid::Uuid, Permissions, Vec<String>) {
+        (self.user, self.server, self.permissions, self.ignored_files)
+    }
+}
+
 pub async fn get_sftp_auth(
     client: &Client,
     r#type: AuthenticationType,
     username: &str,
     password: &str,
 ) -> Result<(uuid::Uuid, uuid::Uuid, Permissions, Vec<String>), anyhow::Error> {
-    let response: Response = into_json(
+    let response: SftpAuthResponse = into_json(
         client
             .client
             .post(format!("{}/sftp/auth", client.url))
@@ -57,22 +74,34 @@ pub async fn get_sftp_auth(
             .await?,
     )?;
 
-    #[derive(Deserialize)]
-    pub struct Response {
-        user: uuid::Uuid,
-        server: uuid::Uuid,
+    Ok(response.into_parts())
+}
 
-        permissions: Permissions,
-        #[serde(default)]
-        ignored_files: Vec<String>,
-    }
+/// Resolves the user, server and permissions of a certificate login, the certificate
+/// was already validated against the trusted certificate authorities of this node
+pub async fn get_sftp_certificate_auth(
+    client: &Client,
+    username: &str,
+    key_id: &str,
+    fingerprint: &str,
+) -> Result<(uuid::Uuid, uuid::Uuid, Permissions, Vec<String>), anyhow::Error> {
+    let response: SftpAuthResponse = into_json(
+        client
+            .client
+            .post(format!("{}/sftp/auth/certificate", client.url))
+            .json(&json!({
+                "username": username,
+                "key_id": key_id,
+                "fingerprint": fingerprint,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?,
+    )?;
 
-    Ok((
-        response.user,
-        response.server,
-        response.permissions,
-        response.ignored_files,
-    ))
+    Ok(response.into_parts())
 }
 
 pub async fn send_activity(
@@ -2,6 +2,7 @@ use super::State;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 mod restore;
+mod verify;
 
 mod delete {
     use crate::{
@@ -45,7 +46,7 @@ mod delete {
         };
 
         tokio::spawn(async move {
-            if let Err(err) = backup.delete(&state.config).await {
+            if let Err(err) = state.backup_manager.delete(&backup, &server).await {
                 tracing::error!(
                     server = %server.uuid,
                     backup = %backup.uuid(),
@@ -65,6 +66,7 @@ mod delete {
 pub fn router(state: &State) -> OpenApiRouter<State> {
     OpenApiRouter::new()
         .nest("/restore", restore::router(state))
+        .nest("/verify", verify::router(state))
         .routes(routes!(delete::route))
         .with_state(state.clone())
 }
@@ -0,0 +1,100 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::backup::adapters::BackupAdapter,
+    };
+    use axum::{extract::Path, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        #[serde(default)]
+        adapter: Option<BackupAdapter>,
+        #[serde(default)]
+        checksum: Option<String>,
+        #[serde(default)]
+        download_url: Option<String>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = ACCEPTED, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+        (status = BAD_REQUEST, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "backup" = uuid::Uuid,
+            description = "The backup uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        state: GetState,
+        server: GetServer,
+        Path((_server, backup_id)): Path<(uuid::Uuid, uuid::Uuid)>,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let backup = match data.adapter {
+            Some(adapter) => {
+                state
+                    .backup_manager
+                    .find_adapter(adapter, backup_id)
+                    .await?
+            }
+            None => state.backup_manager.find(backup_id).await?,
+        };
+
+        let backup = match backup {
+            Some(backup) => backup,
+            None => {
+                return ApiResponse::error("backup not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        if backup.adapter() == BackupAdapter::S3 && data.download_url.is_none() {
+            return ApiResponse::error("unable to verify s3 backup without download_url")
+                .with_status(StatusCode::BAD_REQUEST)
+                .ok();
+        }
+
+        tokio::spawn(async move {
+            if let Err(err) = state
+                .backup_manager
+                .verify(&backup, &server, data.checksum, data.download_url)
+                .await
+            {
+                tracing::error!(
+                    server = %server.uuid,
+                    backup = %backup.uuid(),
+                    adapter = ?backup.adapter(),
+                    "failed to verify backup: {:#?}",
+                    err
+                );
+            }
+        });
+
+        ApiResponse::json(Response {})
+            .with_status(StatusCode::ACCEPTED)
+            .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
@@ -47,7 +47,7 @@ mod post {
         tokio::spawn(async move {
             if let Err(err) = state
                 .backup_manager
-                .create(data.adapter, &server, data.uuid, data.ignore)
+                .create(data.adapter, &server, data.uuid, data.ignore, None)
                 .await
             {
                 tracing::error!(
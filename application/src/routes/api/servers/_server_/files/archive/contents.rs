@@ -0,0 +1,152 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+        server::filesystem::archive::Archive,
+    };
+    use axum::{
+        body::Body,
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+    };
+    use serde::Deserialize;
+    use std::path::Path;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        file: String,
+        entry: String,
+
+        #[schema(default = "false")]
+        #[serde(default)]
+        download: bool,
+        max_size: Option<u64>,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = String),
+        (status = NOT_FOUND, body = ApiError),
+        (status = PAYLOAD_TOO_LARGE, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "file" = String, Query,
+            description = "The archive to read the entry from",
+        ),
+        (
+            "entry" = String, Query,
+            description = "The path of the file inside of the archive",
+        ),
+        (
+            "download" = bool, Query,
+            description = "Whether to add 'download headers' to the file",
+        ),
+        (
+            "max_size" = Option<u64>, Query,
+            description = "The maximum size of the entry to return. If the entry is larger than this, an error will be returned.",
+        ),
+    ))]
+    pub async fn route(server: GetServer, Query(data): Query<Params>) -> ApiResponseResult {
+        let path = match server.filesystem.async_canonicalize(&data.file).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("file not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = server.filesystem.async_metadata(&path).await;
+        if !metadata.map(|m| m.is_file()).unwrap_or(false)
+            || server.filesystem.is_ignored(&path, false).await
+        {
+            return ApiResponse::error("file not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let archive = match Archive::open(server.0.clone(), path.clone()).await {
+            Some(archive) => archive,
+            None => {
+                return ApiResponse::error("failed to open archive")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        let (size, reader) = match archive.read_entry(Path::new(&data.entry)).await {
+            Ok(entry) => entry,
+            Err(err) => {
+                if err
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound)
+                {
+                    return ApiResponse::error("entry not found")
+                        .with_status(StatusCode::NOT_FOUND)
+                        .ok();
+                }
+
+                tracing::error!(
+                    server = %server.uuid,
+                    path = %path.display(),
+                    "failed to read archive entry: {:#?}",
+                    err,
+                );
+
+                return ApiResponse::error(&format!("failed to read archive entry: {err}"))
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        if let Some(max_size) = data.max_size
+            && size.is_none_or(|size| size > max_size)
+        {
+            return ApiResponse::error("entry size exceeds maximum allowed size")
+                .with_status(StatusCode::PAYLOAD_TOO_LARGE)
+                .ok();
+        }
+
+        let mut headers = HeaderMap::new();
+        if let Some(size) = size {
+            headers.insert("Content-Length", size.into());
+        }
+        if data.download {
+            let file_name = Path::new(&data.entry)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            headers.insert(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename={}",
+                    serde_json::Value::String(file_name)
+                )
+                .parse()?,
+            );
+            headers.insert("Content-Type", "application/octet-stream".parse()?);
+        }
+
+        ApiResponse::new(Body::from_stream(
+            tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+        ))
+        .with_headers(headers)
+        .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
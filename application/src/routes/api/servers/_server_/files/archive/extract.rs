@@ -0,0 +1,179 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+        server::filesystem::archive::{Archive, entries::ArchiveSelection},
+    };
+    use axum::http::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, atomic::AtomicU64};
+    use utoipa::ToSchema;
+
+    fn foreground() -> bool {
+        true
+    }
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        #[serde(default)]
+        root: String,
+        file: String,
+        /// Paths inside of the archive, directories are extracted with everything inside of them
+        entries: Vec<String>,
+
+        #[serde(default = "foreground")]
+        foreground: bool,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[derive(ToSchema, Serialize)]
+    struct ResponseAccepted {
+        identifier: uuid::Uuid,
+    }
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = ACCEPTED, body = inline(ResponseAccepted)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        server: GetServer,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let selection = ArchiveSelection::new(&data.entries);
+        if selection.is_empty() {
+            return ApiResponse::error("no valid entries to extract")
+                .with_status(StatusCode::BAD_REQUEST)
+                .ok();
+        }
+
+        let root = match server.filesystem.async_canonicalize(data.root).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("root not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = server.filesystem.async_metadata(&root).await;
+        if !metadata.map(|m| m.is_dir()).unwrap_or(true) {
+            return ApiResponse::error("root is not a directory")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        let source = root.join(data.file);
+
+        if server
+            .filesystem
+            .is_ignored(
+                &source,
+                server
+                    .filesystem
+                    .async_metadata(&source)
+                    .await
+                    .is_ok_and(|m| m.is_dir()),
+            )
+            .await
+        {
+            return ApiResponse::error("file not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let archive = match Archive::open(server.0.clone(), source.clone()).await {
+            Some(archive) => archive.with_selection(selection),
+            None => {
+                return ApiResponse::error("failed to open archive")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        let progress = Arc::new(AtomicU64::new(0));
+        let total = Arc::new(AtomicU64::new(0));
+
+        let (identifier, task) = server
+            .filesystem
+            .operations
+            .add_operation(
+                crate::server::filesystem::operations::FilesystemOperation::Decompress {
+                    path: source,
+                    destination: root.clone(),
+                    progress: progress.clone(),
+                    total: total.clone(),
+                },
+                {
+                    let root = root.clone();
+
+                    async move { archive.extract(root, Some(progress), Some(total)).await }
+                },
+            )
+            .await;
+
+        if data.foreground {
+            match task.await {
+                Ok(Some(Ok(()))) => {}
+                Ok(None) => {
+                    return ApiResponse::error("archive extraction aborted by another source")
+                        .with_status(StatusCode::EXPECTATION_FAILED)
+                        .ok();
+                }
+                Ok(Some(Err(err))) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        root = %root.display(),
+                        "failed to extract archive entries: {:#?}",
+                        err,
+                    );
+
+                    return ApiResponse::error(&format!(
+                        "failed to extract archive entries: {err}"
+                    ))
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+                }
+                Err(err) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        root = %root.display(),
+                        "failed to extract archive entries: {:#?}",
+                        err,
+                    );
+
+                    return ApiResponse::error("failed to extract archive entries")
+                        .with_status(StatusCode::EXPECTATION_FAILED)
+                        .ok();
+                }
+            }
+
+            server.filesystem.chown_path(&root).await?;
+
+            ApiResponse::json(Response {}).ok()
+        } else {
+            ApiResponse::json(ResponseAccepted { identifier })
+                .with_status(StatusCode::ACCEPTED)
+                .ok()
+        }
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
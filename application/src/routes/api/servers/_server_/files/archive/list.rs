@@ -0,0 +1,128 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::filesystem::archive::{Archive, entries::ArchiveEntry},
+    };
+    use axum::http::StatusCode;
+    use axum_extra::extract::Query;
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        file: String,
+
+        per_page: Option<usize>,
+        page: Option<usize>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        total: usize,
+        entries: Vec<ArchiveEntry>,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "file" = String, Query,
+            description = "The archive to list the entries of",
+        ),
+        (
+            "per_page" = usize, Query,
+            description = "The number of entries to return per page",
+        ),
+        (
+            "page" = usize, Query,
+            description = "The page number to return",
+        ),
+    ))]
+    pub async fn route(
+        state: GetState,
+        server: GetServer,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
+        let per_page = match data.per_page {
+            Some(per_page) => Some(per_page),
+            None => match state.config.api.directory_entry_limit {
+                0 => None,
+                limit => Some(limit),
+            },
+        };
+        let page = data.page.unwrap_or(1).max(1);
+
+        let path = match server.filesystem.async_canonicalize(&data.file).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("file not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = server.filesystem.async_metadata(&path).await;
+        if !metadata.map(|m| m.is_file()).unwrap_or(false)
+            || server.filesystem.is_ignored(&path, false).await
+        {
+            return ApiResponse::error("file not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let archive = match Archive::open(server.0.clone(), path.clone()).await {
+            Some(archive) => archive,
+            None => {
+                return ApiResponse::error("failed to open archive")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        let mut entries = match archive.entries().await {
+            Ok(entries) => entries,
+            Err(err) => {
+                tracing::error!(
+                    server = %server.uuid,
+                    path = %path.display(),
+                    "failed to list archive entries: {:#?}",
+                    err,
+                );
+
+                return ApiResponse::error(&format!("failed to list archive entries: {err}"))
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        entries.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        let total = entries.len();
+        if let Some(per_page) = per_page {
+            entries = entries
+                .into_iter()
+                .skip((page - 1) * per_page)
+                .take(per_page)
+                .collect();
+        }
+
+        ApiResponse::json(Response { total, entries }).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
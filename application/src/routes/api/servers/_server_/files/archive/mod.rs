@@ -0,0 +1,14 @@
+use super::State;
+use utoipa_axum::router::OpenApiRouter;
+
+mod contents;
+mod extract;
+mod list;
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .nest("/list", list::router(state))
+        .nest("/extract", extract::router(state))
+        .nest("/contents", contents::router(state))
+        .with_state(state.clone())
+}
@@ -76,7 +76,12 @@ mod post {
                 continue;
             }
 
-            if server.filesystem.truncate_path(&destination).await.is_ok() {
+            if server
+                .trash
+                .remove_path(&server, &destination)
+                .await
+                .is_ok()
+            {
                 deleted_count += 1;
             }
         }
@@ -0,0 +1,201 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::{
+            Server,
+            backup::{BrowseBackup, manager::BackupManager},
+            filesystem::diff,
+        },
+    };
+    use axum::{extract::Query, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use std::path::{Path, PathBuf};
+    use tokio::io::AsyncReadExt;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        file: String,
+        against: Option<String>,
+        backup: Option<uuid::Uuid>,
+        context: Option<usize>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        old_hash: String,
+        new_hash: String,
+        diff: String,
+    }
+
+    async fn read_backup_file(backup: &BrowseBackup, path: PathBuf) -> Result<String, ApiResponse> {
+        let (size, reader) = match backup.read_file(path).await {
+            Ok(file) => file,
+            Err(_) => {
+                return Err(ApiResponse::error("file not found in backup")
+                    .with_status(StatusCode::NOT_FOUND));
+            }
+        };
+
+        if size > diff::MAX_FILE_SIZE {
+            return Err(ApiResponse::error("file is too large to diff")
+                .with_status(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+
+        let mut content = Vec::with_capacity(size as usize);
+        reader
+            .take(diff::MAX_FILE_SIZE)
+            .read_to_end(&mut content)
+            .await?;
+
+        String::from_utf8(content).map_err(|_| {
+            ApiResponse::error("file is not a text file")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+        })
+    }
+
+    async fn read_file(
+        server: &Server,
+        backup_manager: &BackupManager,
+        path: &str,
+    ) -> Result<String, ApiResponse> {
+        let path = match server.filesystem.async_canonicalize(path).await {
+            Ok(path) => path,
+            Err(_) => PathBuf::from(path),
+        };
+
+        if let Some((backup, path)) = server
+            .filesystem
+            .backup_fs(server, backup_manager, &path)
+            .await
+        {
+            return read_backup_file(&backup, path).await;
+        }
+
+        match server.filesystem.async_metadata(&path).await {
+            Ok(metadata) => {
+                if !metadata.is_file() || server.filesystem.is_ignored(&path, false).await {
+                    return Err(
+                        ApiResponse::error("file not found").with_status(StatusCode::NOT_FOUND)
+                    );
+                }
+
+                if metadata.len() > diff::MAX_FILE_SIZE {
+                    return Err(ApiResponse::error("file is too large to diff")
+                        .with_status(StatusCode::PAYLOAD_TOO_LARGE));
+                }
+            }
+            Err(_) => {
+                return Err(ApiResponse::error("file not found").with_status(StatusCode::NOT_FOUND));
+            }
+        }
+
+        server
+            .filesystem
+            .async_read_to_string(&path)
+            .await
+            .map_err(|_| {
+                ApiResponse::error("file is not a text file")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+            })
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = PAYLOAD_TOO_LARGE, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "file" = String, Query,
+            description = "The file to compare, the new side of the diff",
+        ),
+        (
+            "against" = Option<String>, Query,
+            description = "The file to compare against, the old side of the diff",
+        ),
+        (
+            "backup" = Option<uuid::Uuid>, Query,
+            description = "Compare against the same file inside this backup instead",
+        ),
+        (
+            "context" = Option<usize>, Query,
+            description = "The amount of unchanged lines around each change, defaults to 3 (max 100)",
+        ),
+    ))]
+    pub async fn route(
+        state: GetState,
+        server: GetServer,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
+        let new = read_file(&server, &state.backup_manager, &data.file).await?;
+
+        let (old, old_name) = match (data.backup, data.against) {
+            (Some(backup_id), None) => {
+                if !server
+                    .configuration
+                    .read()
+                    .await
+                    .backups
+                    .contains(&backup_id)
+                {
+                    return ApiResponse::error("backup not found")
+                        .with_status(StatusCode::NOT_FOUND)
+                        .ok();
+                }
+
+                let backup = match state.backup_manager.browse(&server, backup_id).await? {
+                    Some(backup) => backup,
+                    None => {
+                        return ApiResponse::error("backup cannot be browsed")
+                            .with_status(StatusCode::EXPECTATION_FAILED)
+                            .ok();
+                    }
+                };
+
+                let path = server.filesystem.relative_path(Path::new(&data.file));
+                let old = read_backup_file(&backup, path).await?;
+
+                (old, format!("{backup_id}/{}", data.file))
+            }
+            (None, Some(against)) => (
+                read_file(&server, &state.backup_manager, &against).await?,
+                against,
+            ),
+            _ => {
+                return ApiResponse::error("either against or backup is required")
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        ApiResponse::json(Response {
+            old_hash: diff::hash(&old),
+            new_hash: diff::hash(&new),
+            diff: diff::unified_diff(
+                &old,
+                &new,
+                &old_name,
+                &data.file,
+                data.context.unwrap_or(3).min(100),
+            ),
+        })
+        .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
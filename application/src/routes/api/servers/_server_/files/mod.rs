@@ -1,6 +1,7 @@
 use super::State;
 use utoipa_axum::router::OpenApiRouter;
 
+mod archive;
 mod chmod;
 mod compress;
 mod contents;
@@ -8,13 +9,18 @@ mod copy;
 mod create_directory;
 mod decompress;
 mod delete;
+mod diff;
 mod fingerprints;
 mod list;
 mod list_directory;
 mod operations;
+mod patch;
 mod pull;
 mod rename;
+mod replace;
 mod search;
+mod trash;
+mod usage;
 mod write;
 
 pub fn router(state: &State) -> OpenApiRouter<State> {
@@ -25,14 +31,20 @@ pub fn router(state: &State) -> OpenApiRouter<State> {
         .nest("/rename", rename::router(state))
         .nest("/copy", copy::router(state))
         .nest("/write", write::router(state))
+        .nest("/patch", patch::router(state))
+        .nest("/diff", diff::router(state))
         .nest("/create-directory", create_directory::router(state))
         .nest("/delete", delete::router(state))
         .nest("/chmod", chmod::router(state))
         .nest("/search", search::router(state))
+        .nest("/replace", replace::router(state))
         .nest("/fingerprints", fingerprints::router(state))
         .nest("/pull", pull::router(state))
         .nest("/compress", compress::router(state))
         .nest("/decompress", decompress::router(state))
+        .nest("/archive", archive::router(state))
         .nest("/operations", operations::router(state))
+        .nest("/trash", trash::router(state))
+        .nest("/usage", usage::router(state))
         .with_state(state.clone())
 }
@@ -0,0 +1,145 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+        server::filesystem::diff::{self, EditOperation, PatchError},
+    };
+    use axum::http::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        file: String,
+        /// sha256 of the file the patch was made against
+        base_hash: String,
+
+        /// A unified diff, mutually exclusive with `operations`
+        #[serde(default)]
+        patch: Option<String>,
+        #[serde(default)]
+        operations: Option<Vec<EditOperation>>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        /// sha256 of the patched file
+        hash: String,
+    }
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+        (status = PAYLOAD_TOO_LARGE, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        server: GetServer,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let path = match server.filesystem.async_canonicalize(&data.file).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("file not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = match server.filesystem.async_metadata(&path).await {
+            Ok(metadata) => {
+                if !metadata.is_file() || server.filesystem.is_ignored(&path, false).await {
+                    return ApiResponse::error("file not found")
+                        .with_status(StatusCode::NOT_FOUND)
+                        .ok();
+                }
+
+                metadata
+            }
+            Err(_) => {
+                return ApiResponse::error("file not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        if metadata.len() > diff::MAX_FILE_SIZE {
+            return ApiResponse::error("file is too large to patch")
+                .with_status(StatusCode::PAYLOAD_TOO_LARGE)
+                .ok();
+        }
+
+        let _guard = server.filesystem.patch_lock.lock().await;
+
+        let base = match server.filesystem.async_read_to_string(&path).await {
+            Ok(base) => base,
+            Err(_) => {
+                return ApiResponse::error("file is not a text file")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        };
+
+        if !diff::hash(&base).eq_ignore_ascii_case(&data.base_hash) {
+            return ApiResponse::error("file has been modified since the patch was created")
+                .with_status(StatusCode::CONFLICT)
+                .ok();
+        }
+
+        let patched = match (data.patch, data.operations) {
+            (Some(patch), None) => diff::apply_unified_diff(&base, &patch),
+            (None, Some(operations)) => diff::apply_edits(&base, &operations),
+            _ => {
+                return ApiResponse::error("either patch or operations is required")
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        let patched = match patched {
+            Ok(patched) => patched,
+            Err(err @ PatchError::Invalid(_)) => {
+                return ApiResponse::error(&err.to_string())
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+            Err(err @ PatchError::Conflict(_)) => {
+                return ApiResponse::error(&err.to_string())
+                    .with_status(StatusCode::CONFLICT)
+                    .ok();
+            }
+        };
+
+        let hash = diff::hash(&patched);
+        match crate::server::filesystem::replace_file_contents(&server, &path, patched.into_bytes())
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                return ApiResponse::error("failed to allocate space")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        ApiResponse::json(Response { hash }).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
@@ -0,0 +1,255 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::filesystem::{
+            build_gitignore,
+            replace::{FileChange, ReplaceOptions},
+        },
+    };
+    use axum::http::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use std::{
+        path::Path,
+        sync::{Arc, atomic::AtomicU64},
+    };
+    use utoipa::ToSchema;
+
+    fn foreground() -> bool {
+        true
+    }
+
+    fn case_sensitive() -> bool {
+        true
+    }
+
+    #[derive(ToSchema, Deserialize, Default, Clone, Copy)]
+    #[serde(rename_all = "snake_case")]
+    #[schema(rename_all = "snake_case")]
+    pub enum ReplaceMode {
+        #[default]
+        Literal,
+        Regex,
+    }
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        #[serde(default)]
+        root: String,
+        pattern: String,
+        /// In regex mode `$1` and `${name}` refer to capture groups
+        replacement: String,
+        #[serde(default)]
+        mode: ReplaceMode,
+        #[serde(default = "case_sensitive")]
+        case_sensitive: bool,
+
+        /// Gitignore style patterns relative to `root`, only matching files are changed
+        #[serde(default)]
+        include: Vec<String>,
+        /// Gitignore style patterns relative to `root`, matching files and directories are skipped
+        #[serde(default)]
+        exclude: Vec<String>,
+
+        max_size: Option<u64>,
+        #[serde(default)]
+        dry_run: bool,
+
+        #[serde(default = "foreground")]
+        foreground: bool,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        dry_run: bool,
+        changes: Vec<FileChange>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct ResponseAccepted {
+        identifier: uuid::Uuid,
+    }
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = ACCEPTED, body = inline(ResponseAccepted)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        state: GetState,
+        server: GetServer,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        if data.pattern.is_empty() {
+            return ApiResponse::error("pattern cannot be empty")
+                .with_status(StatusCode::BAD_REQUEST)
+                .ok();
+        }
+
+        let root = match server.filesystem.async_canonicalize(data.root).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("root not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = server.filesystem.async_symlink_metadata(&root).await;
+        if !metadata.map(|m| m.is_dir()).unwrap_or(true)
+            || server.filesystem.is_ignored(&root, true).await
+        {
+            return ApiResponse::error("root is not a directory")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        let regex = match regex::RegexBuilder::new(&match data.mode {
+            ReplaceMode::Literal => regex::escape(&data.pattern),
+            ReplaceMode::Regex => data.pattern,
+        })
+        .case_insensitive(!data.case_sensitive)
+        .size_limit(1024 * 1024)
+        .build()
+        {
+            Ok(regex) => regex,
+            Err(err) => {
+                return ApiResponse::error(&format!("invalid pattern: {err}"))
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        let include = if data.include.is_empty() {
+            None
+        } else {
+            match build_gitignore(Path::new(""), &data.include) {
+                Ok(include) => Some(include),
+                Err(err) => {
+                    return ApiResponse::error(&format!("invalid include pattern: {err}"))
+                        .with_status(StatusCode::BAD_REQUEST)
+                        .ok();
+                }
+            }
+        };
+
+        let exclude = match build_gitignore(&root, &data.exclude) {
+            Ok(exclude) => exclude,
+            Err(err) => {
+                return ApiResponse::error(&format!("invalid exclude pattern: {err}"))
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        let options = ReplaceOptions {
+            regex,
+            replacement: data.replacement,
+            expand: matches!(data.mode, ReplaceMode::Regex),
+            include,
+            max_size: data
+                .max_size
+                .unwrap_or(crate::server::filesystem::diff::MAX_FILE_SIZE)
+                .min(crate::server::filesystem::diff::MAX_FILE_SIZE),
+            dry_run: data.dry_run,
+            threads: state.config.api.file_search_threads,
+        };
+
+        let progress = Arc::new(AtomicU64::new(0));
+        let total = Arc::new(AtomicU64::new(
+            server
+                .filesystem
+                .disk_usage
+                .read()
+                .await
+                .get_size(&root)
+                .unwrap_or(0),
+        ));
+
+        let (identifier, task) = server
+            .filesystem
+            .operations
+            .add_operation_with_result(
+                crate::server::filesystem::operations::FilesystemOperation::Replace {
+                    path: root.clone(),
+                    progress: progress.clone(),
+                    total,
+                },
+                {
+                    let root = root.clone();
+                    let server = server.0.clone();
+                    let dry_run = options.dry_run;
+
+                    async move {
+                        let ignored = [server.filesystem.get_ignored().await, exclude];
+
+                        let changes = crate::server::filesystem::replace::replace(
+                            &server, &root, &ignored, options, progress,
+                        )
+                        .await?;
+
+                        Ok(Response { dry_run, changes })
+                    }
+                },
+            )
+            .await;
+
+        if data.foreground {
+            let response = match task.await {
+                Ok(Some(Ok(response))) => response,
+                Ok(None) => {
+                    return ApiResponse::error("replace aborted by another source")
+                        .with_status(StatusCode::EXPECTATION_FAILED)
+                        .ok();
+                }
+                Ok(Some(Err(err))) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        root = %root.display(),
+                        "failed to replace in files: {:#?}",
+                        err,
+                    );
+
+                    return ApiResponse::error(&format!("failed to replace in files: {err}"))
+                        .with_status(StatusCode::EXPECTATION_FAILED)
+                        .ok();
+                }
+                Err(err) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        root = %root.display(),
+                        "failed to replace in files: {:#?}",
+                        err,
+                    );
+
+                    return ApiResponse::error("failed to replace in files")
+                        .with_status(StatusCode::EXPECTATION_FAILED)
+                        .ok();
+                }
+            };
+
+            ApiResponse::json(response).ok()
+        } else {
+            ApiResponse::json(ResponseAccepted { identifier })
+                .with_status(StatusCode::ACCEPTED)
+                .ok()
+        }
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
@@ -3,34 +3,162 @@ use utoipa_axum::{router::OpenApiRouter, routes};
 
 mod post {
     use crate::{
+        models::DirectoryEntry,
         response::{ApiResponse, ApiResponseResult},
         routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::{Server, filesystem::build_gitignore},
+    };
+    use axum::{
+        body::Body,
+        http::{HeaderMap, StatusCode},
     };
-    use axum::http::StatusCode;
     use serde::{Deserialize, Serialize};
-    use std::{path::PathBuf, sync::Arc};
-    use tokio::{io::AsyncReadExt, sync::RwLock};
+    use std::{
+        path::{Path, PathBuf},
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+    use tokio::io::AsyncReadExt;
     use utoipa::ToSchema;
 
+    /// Matched lines longer than this are cut off in the results
+    const MAX_LINE_LENGTH: usize = 1024;
+
+    #[derive(ToSchema, Deserialize, Default, Clone, Copy)]
+    #[serde(rename_all = "snake_case")]
+    #[schema(rename_all = "snake_case")]
+    pub enum SearchMode {
+        #[default]
+        Literal,
+        Regex,
+    }
+
+    fn default_case_sensitive() -> bool {
+        true
+    }
+
     #[derive(ToSchema, Deserialize)]
     pub struct Payload {
         #[serde(default)]
         root: String,
         query: String,
+        #[serde(default)]
+        mode: SearchMode,
+        #[serde(default = "default_case_sensitive")]
+        case_sensitive: bool,
+
+        /// Gitignore style patterns relative to `root`, only matching files are searched
+        #[serde(default)]
+        include: Vec<String>,
+        /// Gitignore style patterns relative to `root`, matching files and directories are skipped
+        #[serde(default)]
+        exclude: Vec<String>,
+        /// File types to search, for example `json`, `yaml` or `java`
+        #[serde(default)]
+        types: Vec<String>,
+
         #[serde(default)]
         include_content: bool,
+        /// The amount of lines before and after each content match
+        #[serde(default)]
+        context: usize,
 
         limit: Option<usize>,
         max_size: Option<u64>,
+        max_matches: Option<usize>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    pub struct SearchMatch {
+        /// The matched line, 1 based
+        line: usize,
+        content: String,
+        before: Vec<String>,
+        after: Vec<String>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    pub struct SearchResult {
+        #[serde(flatten)]
+        entry: DirectoryEntry,
+        /// The matched lines, empty if only the path matched
+        matches: Vec<SearchMatch>,
     }
 
     #[derive(ToSchema, Serialize)]
-    struct Response<'a> {
-        results: &'a [crate::models::DirectoryEntry],
+    struct Response {
+        results: Vec<SearchResult>,
+    }
+
+    fn truncate_line(line: &str) -> String {
+        if line.len() <= MAX_LINE_LENGTH {
+            return line.to_string();
+        }
+
+        let mut end = MAX_LINE_LENGTH;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        line[..end].to_string()
+    }
+
+    async fn search_content(
+        server: &Server,
+        path: &Path,
+        regex: &regex::Regex,
+        max_size: u64,
+        context: usize,
+        max_matches: usize,
+    ) -> Option<Vec<SearchMatch>> {
+        let file = server.filesystem.async_open(path).await.ok()?;
+
+        let mut content = Vec::new();
+        file.take(max_size).read_to_end(&mut content).await.ok()?;
+
+        if !crate::is_valid_utf8_slice(&content[..content.len().min(128)]) {
+            return None;
+        }
+
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut matches = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+
+            matches.push(SearchMatch {
+                line: index + 1,
+                content: truncate_line(line),
+                before: lines[index.saturating_sub(context)..index]
+                    .iter()
+                    .map(|line| truncate_line(line))
+                    .collect(),
+                after: lines[index + 1..(index + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|line| truncate_line(line))
+                    .collect(),
+            });
+
+            if matches.len() >= max_matches {
+                break;
+            }
+        }
+
+        if matches.is_empty() {
+            None
+        } else {
+            Some(matches)
+        }
     }
 
     #[utoipa::path(post, path = "/", responses(
         (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
         (status = NOT_FOUND, body = ApiError),
     ), params(
         (
@@ -38,14 +166,22 @@ mod post {
             description = "The server uuid",
             example = "123e4567-e89b-12d3-a456-426614174000",
         ),
+        (
+            "Accept" = Option<String>, Header,
+            description = "Use `application/x-ndjson` to receive each result as its own line once it is found",
+        ),
     ), request_body = inline(Payload))]
     pub async fn route(
         state: GetState,
         server: GetServer,
+        headers: HeaderMap,
         axum::Json(data): axum::Json<Payload>,
     ) -> ApiResponseResult {
         let limit = data.limit.unwrap_or(100).min(500);
         let max_size = data.max_size.unwrap_or(512 * 1024);
+        let max_matches = data.max_matches.unwrap_or(10).min(100);
+        let context = data.context.min(10);
+        let include_content = data.include_content;
 
         let root = match server
             .filesystem
@@ -67,125 +203,212 @@ mod post {
                 .ok();
         }
 
-        let results = Arc::new(RwLock::new(Vec::new()));
+        let regex = match regex::RegexBuilder::new(&match data.mode {
+            SearchMode::Literal => regex::escape(&data.query),
+            SearchMode::Regex => data.query,
+        })
+        .case_insensitive(!data.case_sensitive)
+        .size_limit(1024 * 1024)
+        .build()
+        {
+            Ok(regex) => regex,
+            Err(err) => {
+                return ApiResponse::error(&format!("invalid query: {err}"))
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
 
-        let ignored = &[server.filesystem.get_ignored().await];
-        let mut walker = server
-            .filesystem
-            .async_walk_dir(&root)
-            .await?
-            .with_ignored(ignored);
-
-        walker
-            .run_multithreaded(
-                state.config.api.file_search_threads,
-                Arc::new({
-                    let server = server.clone();
-                    let results = Arc::clone(&results);
-                    let query = Arc::new(data.query.clone());
-                    let root = Arc::new(root.clone());
-
-                    move |is_dir, path: PathBuf| {
-                        let server = server.clone();
-                        let results = Arc::clone(&results);
-                        let query = Arc::clone(&query);
-                        let root = Arc::clone(&root);
-                        let mut buffer = vec![0; crate::BUFFER_SIZE];
-
-                        async move {
-                            if is_dir || results.read().await.len() >= limit {
-                                return Ok(());
-                            }
-
-                            let metadata =
-                                match server.filesystem.async_symlink_metadata(&path).await {
-                                    Ok(metadata) => metadata,
-                                    Err(_) => return Ok(()),
-                                };
-
-                            if !metadata.is_file() {
-                                return Ok(());
-                            }
-
-                            if path.to_string_lossy().contains(query.as_ref()) {
-                                let mut entry = server
-                                    .filesystem
-                                    .to_api_entry(path.to_path_buf(), metadata)
-                                    .await;
-                                entry.name = match path.strip_prefix(root.as_ref()) {
-                                    Ok(path) => path.to_string_lossy().to_string(),
-                                    Err(_) => return Ok(()),
-                                };
-
-                                results.write().await.push(entry);
-                                return Ok(());
-                            }
-
-                            if data.include_content && metadata.len() <= max_size {
-                                let mut file = match server.filesystem.async_open(&path).await {
-                                    Ok(file) => file,
-                                    Err(_) => return Ok(()),
-                                };
-                                let mut bytes_read = match file.read(&mut buffer).await {
-                                    Ok(bytes_read) => bytes_read,
-                                    Err(_) => return Ok(()),
-                                };
-
-                                if !crate::is_valid_utf8_slice(&buffer[..bytes_read.min(128)]) {
-                                    return Ok(());
-                                }
-
-                                let mut last_content = String::with_capacity(8192 * 2);
-                                loop {
-                                    let content = String::from_utf8_lossy(&buffer[..bytes_read]);
-                                    last_content.push_str(&content);
-
-                                    if last_content.contains(query.as_ref()) {
-                                        let mut entry = server
-                                            .filesystem
-                                            .to_api_entry_buffer(
-                                                path.to_path_buf(),
-                                                &metadata,
-                                                false,
-                                                Some(&buffer[..bytes_read]),
-                                                None,
-                                                None,
-                                            )
-                                            .await;
-                                        entry.name = match path.strip_prefix(root.as_ref()) {
-                                            Ok(path) => path.to_string_lossy().to_string(),
-                                            Err(_) => return Ok(()),
-                                        };
-
-                                        results.write().await.push(entry);
-                                        break;
-                                    }
-
-                                    last_content.clear();
-                                    last_content.push_str(&content);
-
-                                    bytes_read = match file.read(&mut buffer).await {
-                                        Ok(bytes_read) => bytes_read,
-                                        Err(_) => break,
-                                    };
-
-                                    if bytes_read == 0 {
-                                        break;
-                                    }
-                                }
-                            }
-
-                            Ok(())
-                        }
+        let include = if data.include.is_empty() {
+            None
+        } else {
+            match build_gitignore(Path::new(""), &data.include) {
+                Ok(include) => Some(include),
+                Err(err) => {
+                    return ApiResponse::error(&format!("invalid include pattern: {err}"))
+                        .with_status(StatusCode::BAD_REQUEST)
+                        .ok();
+                }
+            }
+        };
+
+        let exclude = match build_gitignore(&root, &data.exclude) {
+            Ok(exclude) => exclude,
+            Err(err) => {
+                return ApiResponse::error(&format!("invalid exclude pattern: {err}"))
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        let types = if data.types.is_empty() {
+            None
+        } else {
+            let mut builder = ignore::types::TypesBuilder::new();
+            builder.add_defaults();
+            for file_type in &data.types {
+                builder.select(file_type);
+            }
+
+            match builder.build() {
+                Ok(types) => Some(types),
+                Err(err) => {
+                    return ApiResponse::error(&format!("invalid file type: {err}"))
+                        .with_status(StatusCode::BAD_REQUEST)
+                        .ok();
+                }
+            }
+        };
+
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(64);
+        let found = Arc::new(AtomicUsize::new(0));
+
+        let func = Arc::new({
+            let server = server.clone();
+            let root = Arc::new(root.clone());
+            let include = Arc::new(include);
+            let types = Arc::new(types);
+
+            move |is_dir, path: PathBuf| {
+                let server = server.clone();
+                let sender = sender.clone();
+                let found = Arc::clone(&found);
+                let regex = regex.clone();
+                let root = Arc::clone(&root);
+                let include = Arc::clone(&include);
+                let types = Arc::clone(&types);
+
+                async move {
+                    if is_dir || found.load(Ordering::Relaxed) >= limit {
+                        return Ok(());
                     }
-                }),
-            )
-            .await?;
 
-        ApiResponse::json(Response {
-            results: &results.read().await,
-        })
-        .ok()
+                    let relative = match path.strip_prefix(root.as_ref()) {
+                        Ok(relative) => relative.to_path_buf(),
+                        Err(_) => return Ok(()),
+                    };
+
+                    if let Some(include) = include.as_ref()
+                        && !include
+                            .matched_path_or_any_parents(&relative, false)
+                            .is_ignore()
+                    {
+                        return Ok(());
+                    }
+
+                    if let Some(types) = types.as_ref()
+                        && !types.matched(&relative, false).is_whitelist()
+                    {
+                        return Ok(());
+                    }
+
+                    let metadata = match server.filesystem.async_symlink_metadata(&path).await {
+                        Ok(metadata) => metadata,
+                        Err(_) => return Ok(()),
+                    };
+
+                    if !metadata.is_file() {
+                        return Ok(());
+                    }
+
+                    let content_matches = if include_content && metadata.len() <= max_size {
+                        search_content(&server, &path, &regex, max_size, context, max_matches).await
+                    } else {
+                        None
+                    };
+
+                    let matches = match content_matches {
+                        Some(matches) => matches,
+                        None if regex.is_match(&path.to_string_lossy()) => Vec::new(),
+                        None => return Ok(()),
+                    };
+
+                    if found.fetch_add(1, Ordering::Relaxed) >= limit {
+                        return Ok(());
+                    }
+
+                    let mut entry = server.filesystem.to_api_entry(path, metadata).await;
+                    entry.name = relative.to_string_lossy().to_string();
+
+                    if sender.send(SearchResult { entry, matches }).await.is_err() {
+                        return Err(anyhow::anyhow!("search results receiver closed"));
+                    }
+
+                    Ok(())
+                }
+            }
+        });
+
+        let threads = state.config.api.file_search_threads;
+        let task = tokio::spawn({
+            let server = server.clone();
+
+            async move {
+                let ignored = [server.filesystem.get_ignored().await, exclude];
+                let mut walker = server
+                    .filesystem
+                    .async_walk_dir(&root)
+                    .await?
+                    .with_ignored(&ignored);
+
+                walker.run_multithreaded(threads, func).await
+            }
+        });
+
+        let ndjson = headers
+            .get("Accept")
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("application/x-ndjson"));
+
+        if ndjson {
+            let server_uuid = server.uuid;
+            let stream = futures::stream::unfold(
+                (receiver, Some(task)),
+                move |(mut receiver, task)| async move {
+                    if let Some(result) = receiver.recv().await {
+                        let line = serde_json::to_vec(&result)
+                            .map(|mut line| {
+                                line.push(b'\n');
+                                line
+                            })
+                            .map_err(std::io::Error::other);
+
+                        return Some((line, (receiver, task)));
+                    }
+
+                    // every sender is dropped once the walker is done, its error is sent as the last line
+                    let err = match task?.await {
+                        Ok(Ok(())) => return None,
+                        Ok(Err(err)) => err,
+                        Err(err) => err.into(),
+                    };
+
+                    tracing::error!(
+                        server = %server_uuid,
+                        "failed to search files: {:#?}",
+                        err
+                    );
+
+                    let mut line = ApiError::new(&err.to_string()).to_json().to_string();
+                    line.push('\n');
+
+                    Some((Ok(line.into_bytes()), (receiver, None)))
+                },
+            );
+
+            return ApiResponse::new(Body::from_stream(stream))
+                .with_header("Content-Type", "application/x-ndjson")
+                .ok();
+        }
+
+        let mut results = Vec::new();
+        while let Some(result) = receiver.recv().await {
+            results.push(result);
+        }
+
+        task.await??;
+
+        ApiResponse::json(Response { results }).ok()
     }
 }
 
@@ -0,0 +1,52 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod restore;
+
+mod delete {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+    };
+    use axum::{extract::Path, http::StatusCode};
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(delete, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "entry" = uuid::Uuid,
+            description = "The trash entry uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ))]
+    pub async fn route(
+        server: GetServer,
+        Path((_server, entry)): Path<(uuid::Uuid, uuid::Uuid)>,
+    ) -> ApiResponseResult {
+        if server.trash.purge(Some(entry)).await? == 0 {
+            return ApiResponse::error("trash entry not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        ApiResponse::json(Response {}).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .nest("/restore", restore::router(state))
+        .routes(routes!(delete::route))
+        .with_state(state.clone())
+}
@@ -0,0 +1,101 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+    };
+    use axum::{extract::Path, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        /// Defaults to the original path of the entry
+        #[serde(default)]
+        destination: Option<String>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "entry" = uuid::Uuid,
+            description = "The trash entry uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        server: GetServer,
+        Path((_server, entry)): Path<(uuid::Uuid, uuid::Uuid)>,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let entry = match server.trash.entry(entry).await {
+            Some(entry) => entry,
+            None => {
+                return ApiResponse::error("trash entry not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let destination = server.filesystem.relative_path(std::path::Path::new(
+            data.destination.as_ref().unwrap_or(&entry.path),
+        ));
+
+        if server
+            .filesystem
+            .is_ignored(&destination, entry.directory)
+            .await
+        {
+            return ApiResponse::error("destination not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let replaced_size = match server.filesystem.async_symlink_metadata(&destination).await {
+            Ok(metadata) if metadata.is_file() && !entry.directory => metadata.len(),
+            Ok(_) => {
+                return ApiResponse::error("destination already exists")
+                    .with_status(StatusCode::CONFLICT)
+                    .ok();
+            }
+            Err(_) => 0,
+        };
+
+        if server.filesystem.disk_limit() != 0
+            && server.filesystem.limiter_usage().await as i64 + entry.size as i64
+                - replaced_size as i64
+                > server.filesystem.disk_limit()
+        {
+            return ApiResponse::error("not enough disk space to restore this entry")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        server
+            .trash
+            .restore(&server, entry.uuid, &destination)
+            .await?;
+
+        ApiResponse::json(Response {}).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
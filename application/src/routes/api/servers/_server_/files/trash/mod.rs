@@ -0,0 +1,79 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod _entry_;
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::api::servers::_server_::GetServer,
+        server::filesystem::trash::{TrashEntry, TrashPolicy},
+    };
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        policy: Option<TrashPolicy>,
+        total_size: u64,
+        entries: Vec<TrashEntry>,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ))]
+    pub async fn route(server: GetServer) -> ApiResponseResult {
+        let policy = server.configuration.read().await.trash.clone();
+        let entries = server.trash.entries().await;
+
+        ApiResponse::json(Response {
+            policy,
+            total_size: entries.iter().map(|entry| entry.size).sum(),
+            entries,
+        })
+        .ok()
+    }
+}
+
+mod delete {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::api::servers::_server_::GetServer,
+    };
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        purged: usize,
+    }
+
+    #[utoipa::path(delete, path = "/", responses(
+        (status = OK, body = inline(Response)),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ))]
+    pub async fn route(server: GetServer) -> ApiResponseResult {
+        let purged = server.trash.purge(None).await?;
+
+        ApiResponse::json(Response { purged }).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .routes(routes!(delete::route))
+        .nest("/{entry}", _entry_::router(state))
+        .with_state(state.clone())
+}
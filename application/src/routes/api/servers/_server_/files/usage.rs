@@ -0,0 +1,194 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, api::servers::_server_::GetServer},
+    };
+    use axum::{extract::Query, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use std::{
+        cmp::Reverse,
+        collections::{BinaryHeap, HashMap, VecDeque},
+        path::{Path, PathBuf},
+    };
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        #[serde(default)]
+        root: String,
+        depth: Option<usize>,
+        limit: Option<usize>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct UsageEntry {
+        path: String,
+        size: u64,
+    }
+
+    #[derive(ToSchema, Serialize, Default)]
+    struct ExtensionUsage {
+        extension: String,
+        count: u64,
+        size: u64,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        size: u64,
+        directories: Vec<UsageEntry>,
+        files: Vec<UsageEntry>,
+        extensions: Vec<ExtensionUsage>,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "root" = String, Query,
+            description = "The directory to break down",
+        ),
+        (
+            "depth" = Option<usize>, Query,
+            description = "How many directory levels below the root to break down, defaults to 3, files and extensions always cover the whole root",
+        ),
+        (
+            "limit" = Option<usize>, Query,
+            description = "The amount of directories, files and extensions to return, defaults to 20",
+        ),
+    ))]
+    pub async fn route(server: GetServer, Query(data): Query<Params>) -> ApiResponseResult {
+        let depth = data.depth.unwrap_or(3).clamp(1, 16);
+        let limit = data.limit.unwrap_or(20).clamp(1, 500);
+
+        let root = match server.filesystem.async_canonicalize(&data.root).await {
+            Ok(path) => path,
+            Err(_) => {
+                return ApiResponse::error("root not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let metadata = server.filesystem.async_symlink_metadata(&root).await;
+        if !metadata.map(|m| m.is_dir()).unwrap_or(true)
+            || server.filesystem.is_ignored(&root, true).await
+        {
+            return ApiResponse::error("root not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let ignored = server.filesystem.get_ignored().await;
+
+        let (size, mut directories) = {
+            let disk_usage = server.filesystem.disk_usage.read().await;
+
+            match disk_usage.get_entry(&root) {
+                Some(usage) => {
+                    let mut directories = Vec::new();
+                    usage.collect_directories(Path::new(""), depth, &mut directories);
+
+                    (usage.size, directories)
+                }
+                None => (0, Vec::new()),
+            }
+        };
+
+        directories.retain(|(path, _)| !ignored.matched(root.join(path), true).is_ignore());
+        directories.sort_unstable_by_key(|(_, size)| Reverse(*size));
+        directories.truncate(limit);
+
+        let mut files = BinaryHeap::new();
+        let mut extensions: HashMap<String, ExtensionUsage> = HashMap::new();
+
+        let mut queue = VecDeque::from([PathBuf::new()]);
+        while let Some(directory) = queue.pop_front() {
+            let mut read_dir = match server
+                .filesystem
+                .async_read_dir(root.join(&directory))
+                .await
+            {
+                Ok(read_dir) => read_dir,
+                Err(_) => continue,
+            };
+
+            while let Some(Ok((is_dir, name))) = read_dir.next_entry().await {
+                let path = directory.join(&name);
+                if ignored.matched(root.join(&path), is_dir).is_ignore() {
+                    continue;
+                }
+
+                if is_dir {
+                    queue.push_back(path);
+                    continue;
+                }
+
+                let metadata = match server
+                    .filesystem
+                    .async_symlink_metadata(root.join(&path))
+                    .await
+                {
+                    Ok(metadata) if metadata.is_file() => metadata,
+                    _ => continue,
+                };
+
+                let extension = Path::new(&name)
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let usage = extensions.entry(extension).or_default();
+                usage.count += 1;
+                usage.size += metadata.len();
+
+                files.push(Reverse((metadata.len(), path)));
+                if files.len() > limit {
+                    files.pop();
+                }
+            }
+        }
+
+        let mut extensions: Vec<ExtensionUsage> = extensions
+            .into_iter()
+            .map(|(extension, usage)| ExtensionUsage { extension, ..usage })
+            .collect();
+        extensions.sort_unstable_by_key(|usage| Reverse(usage.size));
+        extensions.truncate(limit);
+
+        ApiResponse::json(Response {
+            size,
+            directories: directories
+                .into_iter()
+                .map(|(path, size)| UsageEntry {
+                    path: path.to_string_lossy().to_string(),
+                    size,
+                })
+                .collect(),
+            files: files
+                .into_sorted_vec()
+                .into_iter()
+                .map(|Reverse((size, path))| UsageEntry {
+                    path: path.to_string_lossy().to_string(),
+                    size,
+                })
+                .collect(),
+            extensions,
+        })
+        .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
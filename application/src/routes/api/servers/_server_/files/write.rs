@@ -110,6 +110,17 @@ mod post {
                 .ok();
         }
 
+        if old_content_size > 0
+            && let Err(err) = server.trash.preserve_file(&server, &path).await
+        {
+            tracing::warn!(
+                server = %server.uuid,
+                path = %path.display(),
+                "failed to preserve overwritten file in trash: {:#?}",
+                err
+            );
+        }
+
         let mut file = server.filesystem.async_create(&path).await?;
         let mut stream = body.into_data_stream();
 
@@ -17,6 +17,7 @@ mod power;
 mod reinstall;
 mod schedules;
 mod script;
+mod stats;
 mod sync;
 mod transfer;
 mod version;
@@ -128,6 +129,7 @@ pub fn router(state: &State) -> OpenApiRouter<State> {
         .nest("/files", files::router(state))
         .nest("/backup", backup::router(state))
         .nest("/schedules", schedules::router(state))
+        .nest("/stats", stats::router(state))
         .routes(routes!(get::route))
         .routes(routes!(delete::route))
         .route_layer(axum::middleware::from_fn_with_state(state.clone(), auth))
@@ -0,0 +1,86 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, api::servers::_server_::GetServer},
+        server::resources::history::{ResourceHistoryPoint, ResourceHistoryResolution},
+    };
+    use axum::{extract::Query, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        from: Option<chrono::DateTime<chrono::Utc>>,
+        to: Option<chrono::DateTime<chrono::Utc>>,
+        resolution: Option<ResourceHistoryResolution>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        resolution: ResourceHistoryResolution,
+        data: Vec<ResourceHistoryPoint>,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
+    ), params(
+        (
+            "server" = uuid::Uuid,
+            description = "The server uuid",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "from" = Option<chrono::DateTime<chrono::Utc>>, Query,
+            description = "The start of the range, defaults to 1 hour ago",
+        ),
+        (
+            "to" = Option<chrono::DateTime<chrono::Utc>>, Query,
+            description = "The end of the range, defaults to now",
+        ),
+        (
+            "resolution" = Option<ResourceHistoryResolution>, Query,
+            description = "The bucket size, defaults to the finest resolution covering the range",
+        ),
+    ))]
+    pub async fn route(
+        state: GetState,
+        server: GetServer,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
+        if !state.config.system.resource_history.enabled {
+            return ApiResponse::error("resource history is not enabled on this node")
+                .with_status(StatusCode::BAD_REQUEST)
+                .ok();
+        }
+
+        let now = chrono::Utc::now();
+        let to = data.to.unwrap_or(now).timestamp();
+        let from = data
+            .from
+            .unwrap_or(now - chrono::Duration::hours(1))
+            .timestamp();
+
+        if from > to {
+            return ApiResponse::error("from must be before to")
+                .with_status(StatusCode::BAD_REQUEST)
+                .ok();
+        }
+
+        let resolution = data
+            .resolution
+            .unwrap_or_else(|| ResourceHistoryResolution::for_range(from, now.timestamp()));
+        let data = server.resource_history.points(resolution, from, to).await?;
+
+        ApiResponse::json(Response { resolution, data }).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
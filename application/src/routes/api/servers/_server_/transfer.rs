@@ -27,6 +27,8 @@ mod post {
         backups: Vec<uuid::Uuid>,
         #[serde(default)]
         delete_backups: bool,
+        #[serde(default)]
+        mode: crate::server::transfer::TransferMode,
     }
 
     #[derive(ToSchema, Serialize)]
@@ -61,6 +63,7 @@ mod post {
             data.archive_format,
             data.compression_level
                 .unwrap_or(state.config.system.backups.compression_level),
+            data.mode,
         );
 
         if transfer
@@ -2,10 +2,11 @@ use super::State;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 mod get {
-    use crate::response::{ApiResponse, ApiResponseResult};
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::GetState,
+    };
     use serde::Serialize;
-    use std::path::Path;
-    use sysinfo::{Disks, Networks, System};
     use utoipa::ToSchema;
 
     #[derive(ToSchema, Serialize)]
@@ -54,73 +55,32 @@ mod get {
     #[utoipa::path(get, path = "/", responses(
         (status = OK, body = inline(Response)),
     ))]
-    pub async fn route() -> ApiResponseResult {
-        let mut sys = System::new_all();
-
-        let mut disks = Disks::new_with_refreshed_list();
-        let mut networks = Networks::new_with_refreshed_list();
-
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-        sys.refresh_all();
-        disks.refresh(true);
-        networks.refresh(true);
-
-        let total_memory = sys.total_memory() / 1024 / 1024;
-        let used_memory = sys.used_memory() / 1024 / 1024;
-
-        let disk = disks
-            .iter()
-            .find(|d| d.mount_point() == Path::new("/"))
-            .unwrap_or(&disks[0]);
-        let total_disk_space = disk.total_space() / 1024 / 1024;
-        let used_disk_space = (disk.total_space() - disk.available_space()) / 1024 / 1024;
-        let total_disk_read = disk.usage().total_read_bytes / 1024 / 1024;
-        let disk_read_rate = disk.usage().read_bytes as f64 / 1024.0 / 1024.0;
-        let total_disk_write = disk.usage().total_written_bytes / 1024 / 1024;
-        let disk_write_rate = disk.usage().written_bytes as f64 / 1024.0 / 1024.0;
-
-        let mut total_received = 0;
-        let mut net_in_rate = 0.0;
-        let mut total_transmitted = 0;
-        let mut net_out_rate = 0.0;
-        for (_, network) in networks.into_iter() {
-            total_received += network.total_received() / 1024 / 1024;
-            net_in_rate += network.received() as f64 / 1024.0 / 1024.0;
-            total_transmitted += network.total_transmitted() / 1024 / 1024;
-            net_out_rate += network.transmitted() as f64 / 1024.0 / 1024.0;
-        }
-
-        let cpu_usage = sys.global_cpu_usage();
-        let cpu_threads = sys.cpus().len();
-        let cpu_model = sys
-            .cpus()
-            .first()
-            .map_or_else(|| "unknown".to_string(), |cpu| cpu.brand().to_string());
+    pub async fn route(state: GetState) -> ApiResponseResult {
+        let stats = state.metrics.node().await;
 
         ApiResponse::json(Response {
             cpu: CpuStats {
-                used: cpu_usage,
-                threads: cpu_threads,
-                model: cpu_model,
+                used: stats.cpu_used,
+                threads: stats.cpu_threads,
+                model: stats.cpu_model,
             },
             network: NetworkStats {
-                received: total_received,
-                receiving_rate: net_in_rate,
-                sent: total_transmitted,
-                sending_rate: net_out_rate,
+                received: stats.network_received / 1024 / 1024,
+                receiving_rate: stats.network_receiving_rate / 1024.0 / 1024.0,
+                sent: stats.network_sent / 1024 / 1024,
+                sending_rate: stats.network_sending_rate / 1024.0 / 1024.0,
             },
             memory: MemoryStats {
-                used: used_memory,
-                total: total_memory,
+                used: stats.memory_used / 1024 / 1024,
+                total: stats.memory_total / 1024 / 1024,
             },
             disk: DiskStats {
-                used: used_disk_space,
-                total: total_disk_space,
-                read: total_disk_read,
-                reading_rate: disk_read_rate,
-                written: total_disk_write,
-                writing_rate: disk_write_rate,
+                used: stats.disk_used / 1024 / 1024,
+                total: stats.disk_total / 1024 / 1024,
+                read: stats.disk_read / 1024 / 1024,
+                reading_rate: stats.disk_reading_rate / 1024.0 / 1024.0,
+                written: stats.disk_written / 1024 / 1024,
+                writing_rate: stats.disk_writing_rate / 1024.0 / 1024.0,
             },
         })
         .ok()
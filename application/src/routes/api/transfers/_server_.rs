@@ -48,6 +48,13 @@ mod delete {
             handle.abort();
         }
 
+        tokio::fs::remove_dir_all(crate::server::transfer::chunked::staging_directory(
+            &state.config,
+            server.uuid,
+        ))
+        .await
+        .ok();
+
         ApiResponse::json(Response {}).ok()
     }
 }
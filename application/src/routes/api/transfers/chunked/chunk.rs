@@ -0,0 +1,309 @@
+use super::State;
+use axum::extract::DefaultBodyLimit;
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+        server::transfer::chunked::{is_valid_chunk_name, staging_directory},
+    };
+    use axum::{
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+    };
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        name: String,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        offset: u64,
+        completed: bool,
+    }
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = UNAUTHORIZED, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+    ), params(
+        (
+            "name" = String, Query,
+            description = "The name of the chunk",
+        ),
+    ))]
+    pub async fn route(
+        state: GetState,
+        headers: HeaderMap,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
+        let subject = match super::super::super::validate_token(&state, &headers).await {
+            Ok(subject) => subject,
+            Err(response) => return response.ok(),
+        };
+
+        if !is_valid_chunk_name(&data.name) {
+            return ApiResponse::error("invalid chunk name").ok();
+        }
+
+        let staging_directory = staging_directory(&state.config, subject);
+        if tokio::fs::metadata(&staging_directory).await.is_err() {
+            return ApiResponse::error("no chunked transfer in progress for this server")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let completed = tokio::fs::metadata(staging_directory.join(format!("{}.done", data.name)))
+            .await
+            .is_ok();
+        let offset = tokio::fs::metadata(staging_directory.join(format!("{}.part", data.name)))
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+
+        ApiResponse::json(Response { offset, completed }).ok()
+    }
+}
+
+mod put {
+    use crate::{
+        io::{compression::reader::CompressionReader, limited_reader::AsyncLimitedReader},
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+        server::transfer::{
+            TransferArchiveFormat,
+            chunked::{file_checksum, is_valid_chunk_name, staging_directory},
+        },
+    };
+    use axum::{
+        body::Body,
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+    };
+    use futures::TryStreamExt;
+    use serde::{Deserialize, Serialize};
+    use std::{path::Path, str::FromStr};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        name: String,
+        file_name: String,
+        offset: u64,
+        size: u64,
+        checksum: String,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        offset: u64,
+        completed: bool,
+    }
+
+    #[utoipa::path(put, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = UNAUTHORIZED, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "name" = String, Query,
+            description = "The name of the chunk",
+        ),
+        (
+            "file_name" = String, Query,
+            description = "The file name of the chunk, used to detect the archive format or name the backup",
+        ),
+        (
+            "offset" = u64, Query,
+            description = "The offset in the chunk the body starts at",
+        ),
+        (
+            "size" = u64, Query,
+            description = "The total size of the chunk",
+        ),
+        (
+            "checksum" = String, Query,
+            description = "The sha256 checksum of the whole chunk",
+        ),
+    ), request_body = String)]
+    pub async fn route(
+        state: GetState,
+        headers: HeaderMap,
+        Query(data): Query<Params>,
+        body: Body,
+    ) -> ApiResponseResult {
+        let subject = match super::super::super::validate_token(&state, &headers).await {
+            Ok(subject) => subject,
+            Err(response) => return response.ok(),
+        };
+
+        if !is_valid_chunk_name(&data.name) {
+            return ApiResponse::error("invalid chunk name").ok();
+        }
+
+        let server = match state
+            .server_manager
+            .get_servers()
+            .await
+            .iter()
+            .find(|s| s.uuid == subject)
+            .cloned()
+        {
+            Some(server) => server,
+            None => {
+                return ApiResponse::error("server not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let staging_directory = staging_directory(&state.config, subject);
+        if tokio::fs::metadata(&staging_directory).await.is_err() {
+            return ApiResponse::error("no chunked transfer in progress for this server")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        let done_path = staging_directory.join(format!("{}.done", data.name));
+        if tokio::fs::metadata(&done_path).await.is_ok() {
+            return ApiResponse::json(Response {
+                offset: data.size,
+                completed: true,
+            })
+            .ok();
+        }
+
+        let part_path = staging_directory.join(format!("{}.part", data.name));
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&part_path)
+            .await?;
+
+        let current_offset = file.metadata().await?.len();
+        if current_offset != data.offset || data.offset > data.size {
+            return ApiResponse::error(&format!(
+                "chunk offset mismatch, expected {current_offset}"
+            ))
+            .with_status(StatusCode::CONFLICT)
+            .ok();
+        }
+
+        let reader = tokio_util::io::StreamReader::new(
+            body.into_data_stream().map_err(std::io::Error::other),
+        );
+        let reader = AsyncLimitedReader::new_with_bytes_per_second(
+            reader,
+            state.config.system.transfers.download_limit * 1024 * 1024,
+        );
+        let mut reader = reader.take(data.size - data.offset);
+
+        let copy_result = tokio::io::copy(&mut reader, &mut file).await;
+        file.flush().await?;
+        file.sync_all().await?;
+        drop(file);
+
+        let offset = tokio::fs::metadata(&part_path).await?.len();
+        if let Err(err) = copy_result {
+            tracing::debug!(
+                server = %subject,
+                "chunk {} of transfer interrupted at {} bytes: {:#?}",
+                data.name,
+                offset,
+                err
+            );
+        }
+
+        if offset < data.size {
+            return ApiResponse::json(Response {
+                offset,
+                completed: false,
+            })
+            .ok();
+        }
+
+        if !file_checksum(&part_path)
+            .await?
+            .eq_ignore_ascii_case(&data.checksum)
+        {
+            tokio::fs::remove_file(&part_path).await?;
+
+            return ApiResponse::error("chunk checksum mismatch")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        if let Some(backup) = data.name.strip_prefix("backup-") {
+            let file_name = match Path::new(&data.file_name).file_name() {
+                Some(file_name) if backup.parse::<uuid::Uuid>().is_ok() => file_name,
+                _ => {
+                    tokio::fs::remove_file(&part_path).await?;
+
+                    return ApiResponse::error("invalid backup chunk").ok();
+                }
+            };
+
+            let destination = Path::new(&state.config.system.backup_directory).join(file_name);
+            if tokio::fs::rename(&part_path, &destination).await.is_err() {
+                tokio::fs::copy(&part_path, &destination).await?;
+                tokio::fs::remove_file(&part_path).await?;
+            }
+        } else {
+            let compression_type = TransferArchiveFormat::from_str(&data.file_name)
+                .unwrap_or(TransferArchiveFormat::TarGz)
+                .compression_type();
+            let file = tokio::fs::File::open(&part_path).await?.into_std().await;
+
+            let result = tokio::task::spawn_blocking(move || {
+                let reader = CompressionReader::new(
+                    std::io::BufReader::with_capacity(crate::BUFFER_SIZE, file),
+                    compression_type,
+                );
+
+                crate::server::transfer::extract_archive(&server, reader)
+            })
+            .await?;
+            tokio::fs::remove_file(&part_path).await?;
+
+            if let Err(err) = result {
+                tracing::error!(
+                    server = %subject,
+                    "failed to extract chunk {} of transfer: {:#?}",
+                    data.name,
+                    err
+                );
+
+                return ApiResponse::error("failed to extract chunk")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        }
+
+        tokio::fs::write(&done_path, b"").await?;
+
+        ApiResponse::json(Response {
+            offset,
+            completed: true,
+        })
+        .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .routes(routes!(put::route).layer(DefaultBodyLimit::disable()))
+        .with_state(state.clone())
+}
@@ -0,0 +1,126 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+        server::transfer::chunked::staging_directory,
+    };
+    use axum::http::{HeaderMap, StatusCode};
+    use serde::{Deserialize, Serialize};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        #[serde(default)]
+        deleted: Vec<String>,
+        #[serde(default)]
+        backups: Vec<uuid::Uuid>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = UNAUTHORIZED, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        state: GetState,
+        headers: HeaderMap,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let subject = match super::super::super::validate_token(&state, &headers).await {
+            Ok(subject) => subject,
+            Err(response) => return response.ok(),
+        };
+
+        let server = match state
+            .server_manager
+            .get_servers()
+            .await
+            .iter()
+            .find(|s| s.uuid == subject)
+            .cloned()
+        {
+            Some(server) => server,
+            None => {
+                return ApiResponse::error("server not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let staging_directory = staging_directory(&state.config, subject);
+        if tokio::fs::metadata(&staging_directory).await.is_err() {
+            return ApiResponse::error("no chunked transfer in progress for this server")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        for path in data.deleted {
+            let metadata = match server.filesystem.async_symlink_metadata(&path).await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            let result = if metadata.is_dir() {
+                server.filesystem.async_remove_dir_all(&path).await
+            } else {
+                server.filesystem.async_remove_file(&path).await
+            };
+
+            if let Err(err) = result {
+                tracing::warn!(
+                    server = %subject,
+                    path = %path,
+                    "failed to remove deleted path after transfer: {:#?}",
+                    err
+                );
+            }
+        }
+
+        let mut backups = Vec::new();
+        for backup in data.backups {
+            if tokio::fs::metadata(staging_directory.join(format!("backup-{backup}.done")))
+                .await
+                .is_ok()
+            {
+                backups.push(backup);
+            }
+        }
+
+        state
+            .config
+            .client
+            .set_server_transfer(subject, true, backups)
+            .await?;
+        tokio::fs::remove_dir_all(&staging_directory).await.ok();
+
+        server
+            .transferring
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        server
+            .websocket
+            .send(crate::server::websocket::WebsocketMessage::new(
+                crate::server::websocket::WebsocketEvent::ServerTransferStatus,
+                &["completed".to_string()],
+            ))
+            .ok();
+
+        tracing::info!(
+            server = %subject,
+            "chunked server transfer completed successfully"
+        );
+
+        ApiResponse::json(Response {}).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
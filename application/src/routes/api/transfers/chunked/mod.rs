@@ -0,0 +1,92 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod chunk;
+mod finish;
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+        server::transfer::chunked::staging_directory,
+    };
+    use axum::http::{HeaderMap, StatusCode};
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        completed: Vec<String>,
+    }
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = UNAUTHORIZED, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+    ))]
+    pub async fn route(state: GetState, headers: HeaderMap) -> ApiResponseResult {
+        let subject = match super::super::validate_token(&state, &headers).await {
+            Ok(subject) => subject,
+            Err(response) => return response.ok(),
+        };
+
+        let staging_directory = staging_directory(&state.config, subject);
+        let resuming = tokio::fs::metadata(&staging_directory).await.is_ok();
+
+        if state
+            .server_manager
+            .get_servers()
+            .await
+            .iter()
+            .any(|s| s.uuid == subject)
+        {
+            if !resuming {
+                return ApiResponse::error("server with this uuid already exists")
+                    .with_status(StatusCode::CONFLICT)
+                    .ok();
+            }
+        } else {
+            let server_data = state.config.client.server(subject).await?;
+            let server = state
+                .server_manager
+                .create_server(&state, server_data, false)
+                .await;
+
+            server
+                .transferring
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        tokio::fs::create_dir_all(&staging_directory).await?;
+
+        let mut completed = Vec::new();
+        let mut directory = tokio::fs::read_dir(&staging_directory).await?;
+        while let Some(entry) = directory.next_entry().await? {
+            if let Some(name) = entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.strip_suffix(".done"))
+            {
+                completed.push(name.to_string());
+            }
+        }
+
+        if resuming {
+            tracing::info!(
+                server = %subject,
+                "resuming chunked server transfer with {} completed chunks",
+                completed.len()
+            );
+        }
+
+        ApiResponse::json(Response { completed }).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .nest("/chunk", chunk::router(state))
+        .nest("/finish", finish::router(state))
+        .with_state(state.clone())
+}
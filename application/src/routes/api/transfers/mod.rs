@@ -1,18 +1,20 @@
 use super::State;
-use axum::extract::DefaultBodyLimit;
+use crate::response::ApiResponse;
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{HeaderMap, StatusCode},
+};
 use utoipa_axum::{
     router::{OpenApiRouter, UtoipaMethodRouterExt},
     routes,
 };
 
 mod _server_;
+mod chunked;
 
 mod post {
     use crate::{
-        io::{
-            compression::{CompressionType, reader::CompressionReader},
-            limited_reader::LimitedReader,
-        },
+        io::{compression::reader::CompressionReader, limited_reader::LimitedReader},
         response::{ApiResponse, ApiResponseResult},
         routes::{ApiError, GetState},
         server::transfer::TransferArchiveFormat,
@@ -21,7 +23,6 @@ mod post {
         extract::Multipart,
         http::{HeaderMap, StatusCode},
     };
-    use cap_std::fs::{Permissions, PermissionsExt};
     use futures::TryStreamExt;
     use serde::Serialize;
     use std::{io::Write, path::Path, str::FromStr};
@@ -40,56 +41,9 @@ mod post {
         headers: HeaderMap,
         mut multipart: Multipart,
     ) -> ApiResponseResult {
-        let key = headers
-            .get("Authorization")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("")
-            .to_string();
-        let (r#type, token) = match key.split_once(' ') {
-            Some((t, tok)) => (t, tok),
-            None => {
-                return ApiResponse::error("invalid authorization header")
-                    .with_status(StatusCode::UNAUTHORIZED)
-                    .with_header("WWW-Authenticate", "Bearer")
-                    .ok();
-            }
-        };
-
-        if r#type != "Bearer" {
-            return ApiResponse::error("invalid authorization header")
-                .with_status(StatusCode::UNAUTHORIZED)
-                .ok();
-        }
-
-        let payload: crate::remote::jwt::BasePayload = match state.config.jwt.verify(token) {
-            Ok(payload) => payload,
-            Err(_) => {
-                return ApiResponse::error("invalid token")
-                    .with_status(StatusCode::UNAUTHORIZED)
-                    .ok();
-            }
-        };
-
-        if !payload.validate(&state.config.jwt).await {
-            return ApiResponse::error("invalid token")
-                .with_status(StatusCode::UNAUTHORIZED)
-                .ok();
-        }
-
-        let subject: uuid::Uuid = match payload.subject {
-            Some(subject) => match subject.parse() {
-                Ok(subject) => subject,
-                Err(_) => {
-                    return ApiResponse::error("invalid token")
-                        .with_status(StatusCode::UNAUTHORIZED)
-                        .ok();
-                }
-            },
-            None => {
-                return ApiResponse::error("invalid token")
-                    .with_status(StatusCode::UNAUTHORIZED)
-                    .ok();
-            }
+        let subject = match super::validate_token(&state, &headers).await {
+            Ok(subject) => subject,
+            Err(response) => return response.ok(),
         };
 
         if state
@@ -137,105 +91,12 @@ mod post {
                         );
                         let reader = CompressionReader::new(
                             reader,
-                            match TransferArchiveFormat::from_str(&file_name)
+                            TransferArchiveFormat::from_str(&file_name)
                                 .unwrap_or(TransferArchiveFormat::TarGz)
-                            {
-                                TransferArchiveFormat::Tar => CompressionType::None,
-                                TransferArchiveFormat::TarGz => CompressionType::Gz,
-                                TransferArchiveFormat::TarXz => CompressionType::Xz,
-                                TransferArchiveFormat::TarBz2 => CompressionType::Bz2,
-                                TransferArchiveFormat::TarLz4 => CompressionType::Lz4,
-                                TransferArchiveFormat::TarZstd => CompressionType::Zstd,
-                            },
+                                .compression_type(),
                         );
 
-                        let mut archive = tar::Archive::new(reader);
-                        let mut directory_entries = chunked_vec::ChunkedVec::new();
-                        let mut entries = archive.entries()?;
-
-                        while let Some(Ok(mut entry)) = entries.next() {
-                            let path = entry.path()?;
-
-                            if path.is_absolute() {
-                                continue;
-                            }
-
-                            let destination_path = path.as_ref();
-                            let header = entry.header();
-
-                            match header.entry_type() {
-                                tar::EntryType::Directory => {
-                                    server.filesystem.create_dir_all(destination_path)?;
-                                    if let Ok(permissions) =
-                                        header.mode().map(Permissions::from_mode)
-                                    {
-                                        server
-                                            .filesystem
-                                            .set_permissions(destination_path, permissions)?;
-                                    }
-
-                                    if let Ok(modified_time) = header.mtime() {
-                                        directory_entries
-                                            .push((destination_path.to_path_buf(), modified_time));
-                                    }
-                                }
-                                tar::EntryType::Regular => {
-                                    if let Some(parent) = destination_path.parent() {
-                                        server.filesystem.create_dir_all(parent)?;
-                                    }
-
-                                    let mut writer =
-                                        crate::server::filesystem::writer::FileSystemWriter::new(
-                                            server.clone(),
-                                            destination_path,
-                                            header.mode().map(Permissions::from_mode).ok(),
-                                            header
-                                                .mtime()
-                                                .map(|t| {
-                                                    cap_std::time::SystemTime::from_std(
-                                                        std::time::UNIX_EPOCH
-                                                            + std::time::Duration::from_secs(t),
-                                                    )
-                                                })
-                                                .ok(),
-                                        )?;
-
-                                    crate::io::copy(&mut entry, &mut writer)?;
-                                    writer.flush()?;
-                                }
-                                tar::EntryType::Symlink => {
-                                    let link =
-                                        entry.link_name().unwrap_or_default().unwrap_or_default();
-
-                                    if let Err(err) =
-                                        server.filesystem.symlink(link, destination_path)
-                                    {
-                                        tracing::debug!(
-                                            path = %destination_path.display(),
-                                            "failed to create symlink from archive: {:#?}",
-                                            err
-                                        );
-                                    } else if let Ok(permissions) =
-                                        header.mode().map(Permissions::from_mode)
-                                    {
-                                        server.filesystem.set_symlink_permissions(
-                                            destination_path,
-                                            permissions,
-                                        )?;
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-
-                        for (destination_path, modified_time) in directory_entries {
-                            server.filesystem.set_times(
-                                &destination_path,
-                                std::time::UNIX_EPOCH
-                                    + std::time::Duration::from_secs(modified_time),
-                                None,
-                            )?;
-                        }
+                        crate::server::transfer::extract_archive(&server, reader)?;
                     } else if field.name().is_some_and(|n| n.starts_with("backup-")) {
                         tracing::debug!(
                             "processing backup field: {}",
@@ -393,9 +254,59 @@ mod post {
     }
 }
 
+/// Validates the token the source node got from the panel, returning the uuid of the server
+pub async fn validate_token(state: &State, headers: &HeaderMap) -> Result<uuid::Uuid, ApiResponse> {
+    let key = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let (r#type, token) = match key.split_once(' ') {
+        Some((t, tok)) => (t, tok),
+        None => {
+            return Err(ApiResponse::error("invalid authorization header")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .with_header("WWW-Authenticate", "Bearer"));
+        }
+    };
+
+    if r#type != "Bearer" {
+        return Err(ApiResponse::error("invalid authorization header")
+            .with_status(StatusCode::UNAUTHORIZED));
+    }
+
+    let payload: crate::remote::jwt::BasePayload = match state.config.jwt.verify(token) {
+        Ok(payload) => payload,
+        Err(_) => {
+            return Err(ApiResponse::error("invalid token").with_status(StatusCode::UNAUTHORIZED));
+        }
+    };
+
+    if !payload.validate(&state.config.jwt).await {
+        return Err(ApiResponse::error("invalid token").with_status(StatusCode::UNAUTHORIZED));
+    }
+
+    let subject: uuid::Uuid = match payload.subject {
+        Some(subject) => match subject.parse() {
+            Ok(subject) => subject,
+            Err(_) => {
+                return Err(
+                    ApiResponse::error("invalid token").with_status(StatusCode::UNAUTHORIZED)
+                );
+            }
+        },
+        None => {
+            return Err(ApiResponse::error("invalid token").with_status(StatusCode::UNAUTHORIZED));
+        }
+    };
+
+    Ok(subject)
+}
+
 pub fn router(state: &State) -> OpenApiRouter<State> {
     OpenApiRouter::new()
         .routes(routes!(post::route).layer(DefaultBodyLimit::disable()))
+        .nest("/chunked", chunked::router(state))
         .nest("/{server}", _server_::router(state))
         .with_state(state.clone())
 }
@@ -38,6 +38,11 @@ mod post {
                     #[schema(value_type = Option<String>)]
                     bind_address: Option<std::net::IpAddr>,
                     bind_port: Option<u16>,
+
+                    #[schema(inline)]
+                    certificates: Option<#[derive(ToSchema, Deserialize)] pub struct SystemSftpCertificatesPayload {
+                        trusted_user_ca_keys: Option<Vec<String>>,
+                    }>,
                 }>,
             }>,
 
@@ -102,6 +107,11 @@ mod post {
             if let Some(bind_port) = sftp.bind_port {
                 config.system.sftp.bind_port = bind_port;
             }
+            if let Some(certificates) = sftp.certificates
+                && let Some(trusted_user_ca_keys) = certificates.trusted_user_ca_keys
+            {
+                config.system.sftp.certificates.trusted_user_ca_keys = trusted_user_ca_keys;
+            }
         }
         if let Some(allowed_origins) = data.allowed_origins {
             config.allowed_origins = allowed_origins;
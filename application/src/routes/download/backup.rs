@@ -4,10 +4,16 @@ use utoipa_axum::{router::OpenApiRouter, routes};
 mod get {
     use crate::{
         response::{ApiResponse, ApiResponseResult},
-        routes::GetState,
+        routes::{
+            GetState,
+            download::range::{self, Validators},
+        },
         server::filesystem::archive::StreamableArchiveFormat,
     };
-    use axum::{extract::Query, http::StatusCode};
+    use axum::{
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+    };
     use serde::Deserialize;
     use utoipa::ToSchema;
 
@@ -31,7 +37,10 @@ mod get {
 
     #[utoipa::path(get, path = "/", responses(
         (status = OK, body = String),
+        (status = PARTIAL_CONTENT, body = String),
+        (status = NOT_MODIFIED, body = String),
         (status = UNAUTHORIZED, body = String),
+        (status = RANGE_NOT_SATISFIABLE, body = String),
         (status = NOT_FOUND, body = String),
         (status = EXPECTATION_FAILED, body = String),
     ), params(
@@ -40,7 +49,11 @@ mod get {
             description = "The JWT token to use for authentication",
         ),
     ))]
-    pub async fn route(state: GetState, Query(data): Query<Params>) -> ApiResponseResult {
+    pub async fn route(
+        state: GetState,
+        request_headers: HeaderMap,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
         let payload: BackupJwtPayload = match state.config.jwt.verify(&data.token) {
             Ok(payload) => payload,
             Err(_) => {
@@ -56,12 +69,6 @@ mod get {
                 .ok();
         }
 
-        if !state.config.jwt.one_time_id(&payload.unique_id).await {
-            return ApiResponse::error("token has already been used")
-                .with_status(StatusCode::UNAUTHORIZED)
-                .ok();
-        }
-
         if let Some(server_uuid) = payload.server_uuid {
             let server = state
                 .server_manager
@@ -85,6 +92,56 @@ mod get {
             }
         };
 
+        match backup.download_file(&state.config).await {
+            Ok(Some(file)) => {
+                let mut headers = HeaderMap::with_capacity(2);
+                headers.insert(
+                    "Content-Disposition",
+                    format!("attachment; filename={}", file.file_name).parse()?,
+                );
+                headers.insert("Content-Type", file.content_type.parse()?);
+
+                let size = file.file.len();
+                let validators = match file.checksum {
+                    Some(ref checksum) => Validators::from_checksum(checksum, file.modified),
+                    None => Validators::from_metadata(size, file.modified),
+                };
+                if !state
+                    .config
+                    .jwt
+                    .download_session_id(
+                        &payload.unique_id,
+                        &validators.etag,
+                        validators.is_resume(&request_headers),
+                        state.config.api.download_range_token_uses,
+                    )
+                    .await
+                {
+                    return ApiResponse::error("token has already been used")
+                        .with_status(StatusCode::UNAUTHORIZED)
+                        .ok();
+                }
+
+                return range::respond(&request_headers, headers, size, validators, file.file)
+                    .await?
+                    .ok();
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("failed to open backup for download: {:#?}", err);
+
+                return ApiResponse::error("failed to download backup")
+                    .with_status(StatusCode::EXPECTATION_FAILED)
+                    .ok();
+            }
+        }
+
+        if !state.config.jwt.one_time_id(&payload.unique_id).await {
+            return ApiResponse::error("token has already been used")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .ok();
+        }
+
         match backup.download(&state.config, data.archive_format).await {
             Ok(response) => response,
             Err(err) => {
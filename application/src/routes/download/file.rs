@@ -4,7 +4,10 @@ use utoipa_axum::{router::OpenApiRouter, routes};
 mod get {
     use crate::{
         response::{ApiResponse, ApiResponseResult},
-        routes::GetState,
+        routes::{
+            GetState,
+            download::range::{self, FilesystemRangeSource, Validators},
+        },
     };
     use axum::{
         body::Body,
@@ -32,7 +35,10 @@ mod get {
 
     #[utoipa::path(get, path = "/", responses(
         (status = OK, body = String),
+        (status = PARTIAL_CONTENT, body = String),
+        (status = NOT_MODIFIED, body = String),
         (status = UNAUTHORIZED, body = String),
+        (status = RANGE_NOT_SATISFIABLE, body = String),
         (status = NOT_FOUND, body = String),
         (status = EXPECTATION_FAILED, body = String),
     ), params(
@@ -41,7 +47,11 @@ mod get {
             description = "The JWT token to use for authentication",
         ),
     ))]
-    pub async fn route(state: GetState, Query(data): Query<Params>) -> ApiResponseResult {
+    pub async fn route(
+        state: GetState,
+        request_headers: HeaderMap,
+        Query(data): Query<Params>,
+    ) -> ApiResponseResult {
         let payload: FileJwtPayload = match state.config.jwt.verify(&data.token) {
             Ok(payload) => payload,
             Err(_) => {
@@ -57,12 +67,6 @@ mod get {
                 .ok();
         }
 
-        if !state.config.jwt.one_time_id(&payload.unique_id).await {
-            return ApiResponse::error("token has already been used")
-                .with_status(StatusCode::UNAUTHORIZED)
-                .ok();
-        }
-
         let server = state
             .server_manager
             .get_servers()
@@ -96,6 +100,12 @@ mod get {
             .backup_fs(&server, &state.backup_manager, path)
             .await
         {
+            if !state.config.jwt.one_time_id(&payload.unique_id).await {
+                return ApiResponse::error("token has already been used")
+                    .with_status(StatusCode::UNAUTHORIZED)
+                    .ok();
+            }
+
             match backup.read_file(path.clone()).await {
                 Ok((size, reader)) => {
                     let mut headers = HeaderMap::new();
@@ -151,17 +161,7 @@ mod get {
             }
         };
 
-        let file = match server.filesystem.async_open(&path).await {
-            Ok(file) => file,
-            Err(_) => {
-                return ApiResponse::error("file not found")
-                    .with_status(StatusCode::NOT_FOUND)
-                    .ok();
-            }
-        };
-
         let mut headers = HeaderMap::new();
-        headers.insert("Content-Length", metadata.len().into());
         headers.insert(
             "Content-Disposition",
             format!(
@@ -172,10 +172,37 @@ mod get {
         );
         headers.insert("Content-Type", "application/octet-stream".parse()?);
 
-        ApiResponse::new(Body::from_stream(
-            tokio_util::io::ReaderStream::with_capacity(file, crate::BUFFER_SIZE),
-        ))
-        .with_headers(headers)
+        let validators = Validators::from_metadata(
+            metadata.len(),
+            metadata.modified().ok().map(|t| t.into_std()),
+        );
+        if !state
+            .config
+            .jwt
+            .download_session_id(
+                &payload.unique_id,
+                &validators.etag,
+                validators.is_resume(&request_headers),
+                state.config.api.download_range_token_uses,
+            )
+            .await
+        {
+            return ApiResponse::error("token has already been used")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .ok();
+        }
+
+        range::respond(
+            &request_headers,
+            headers,
+            metadata.len(),
+            validators,
+            FilesystemRangeSource {
+                filesystem: server.filesystem.clone(),
+                path: path.to_path_buf(),
+            },
+        )
+        .await?
         .ok()
     }
 }
@@ -5,6 +5,7 @@ mod backup;
 mod directory;
 mod file;
 mod files;
+mod range;
 
 pub fn router(state: &State) -> OpenApiRouter<State> {
     OpenApiRouter::new()
@@ -0,0 +1,343 @@
+use crate::{
+    response::ApiResponse, server::backup::encryption::BackupFile,
+    server::filesystem::cap::CapFilesystem,
+};
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode},
+};
+use futures_util::StreamExt;
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    pin::Pin,
+    time::SystemTime,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Requests with more ranges than this get the whole body instead
+const MAX_RANGES: usize = 16;
+
+pub type RangeReader = Pin<Box<dyn AsyncRead + Send>>;
+
+#[async_trait::async_trait]
+pub trait RangeSource: Send + Sync + 'static {
+    async fn open_range(&self, offset: u64, length: u64) -> std::io::Result<RangeReader>;
+}
+
+pub struct FilesystemRangeSource {
+    pub filesystem: CapFilesystem,
+    pub path: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl RangeSource for FilesystemRangeSource {
+    async fn open_range(&self, offset: u64, length: u64) -> std::io::Result<RangeReader> {
+        let mut file = self
+            .filesystem
+            .async_open(&self.path)
+            .await
+            .map_err(std::io::Error::other)?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(Box::pin(file.take(length)))
+    }
+}
+
+#[async_trait::async_trait]
+impl RangeSource for BackupFile {
+    async fn open_range(&self, offset: u64, length: u64) -> std::io::Result<RangeReader> {
+        let mut file = self.clone();
+        let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+
+        let task = tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            file.seek(SeekFrom::Start(offset))?;
+
+            let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+            crate::io::copy(&mut file.take(length), &mut writer)?;
+            writer.flush()?;
+
+            Ok(())
+        });
+
+        // the body would otherwise just end early if reading the backup fails
+        let result = futures_util::stream::once(async move {
+            match task.await {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => Some(Err(err)),
+                Err(err) => Some(Err(std::io::Error::other(err))),
+            }
+        })
+        .filter_map(std::future::ready);
+
+        Ok(Box::pin(tokio_util::io::StreamReader::new(Box::pin(
+            tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE).chain(result),
+        ))))
+    }
+}
+
+pub struct Validators {
+    pub etag: String,
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    pub fn from_metadata(size: u64, modified: Option<SystemTime>) -> Self {
+        let modified_nanos = modified
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default()
+            .as_nanos();
+
+        Self {
+            etag: format!("\"{size:x}-{modified_nanos:x}\""),
+            last_modified: modified,
+        }
+    }
+
+    #[inline]
+    pub fn from_checksum(checksum: &str, modified: Option<SystemTime>) -> Self {
+        Self {
+            etag: format!("\"{checksum}\""),
+            last_modified: modified,
+        }
+    }
+
+    /// Whether this request resumes a download of exactly this representation, which
+    /// needs a `Range` and an `If-Range` with the `ETag` of an earlier response
+    pub fn is_resume(&self, headers: &HeaderMap) -> bool {
+        headers.contains_key("Range")
+            && headers
+                .get("If-Range")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|if_range| if_range.trim() == self.etag)
+    }
+
+    #[inline]
+    fn last_modified_secs(&self) -> Option<i64> {
+        self.last_modified
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp())
+    }
+
+    /// Weak comparison against `If-None-Match` and `If-Modified-Since`
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = headers.get("If-None-Match").and_then(|v| v.to_str().ok()) {
+            let etag = self.etag.trim_start_matches("W/");
+
+            return if_none_match
+                .split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+        }
+
+        match (
+            headers
+                .get("If-Modified-Since")
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_http_date),
+            self.last_modified_secs(),
+        ) {
+            (Some(since), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Strong comparison against `If-Range`, a missing header always matches
+    fn if_range_matches(&self, headers: &HeaderMap) -> bool {
+        let if_range = match headers.get("If-Range").and_then(|v| v.to_str().ok()) {
+            Some(if_range) => if_range.trim(),
+            None => return true,
+        };
+
+        if if_range.starts_with('"') {
+            return if_range == self.etag;
+        }
+
+        match (parse_http_date(if_range), self.last_modified_secs()) {
+            (Some(date), Some(modified)) => date == modified,
+            _ => false,
+        }
+    }
+}
+
+#[inline]
+fn http_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[inline]
+fn parse_http_date(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// Parses a `Range` header into inclusive byte ranges. `None` means the header should be
+/// ignored, an empty list means none of the ranges can be satisfied.
+fn parse_ranges(value: &str, size: u64) -> Option<Vec<(u64, u64)>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+
+    let mut ranges = Vec::new();
+    let mut parsed = 0;
+    for part in spec.split(',').map(|part| part.trim()) {
+        if part.is_empty() {
+            continue;
+        }
+
+        let (start, end) = part.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        parsed += 1;
+
+        if start.is_empty() {
+            let suffix: u64 = end.parse().ok()?;
+            if suffix == 0 || size == 0 {
+                continue;
+            }
+
+            ranges.push((size.saturating_sub(suffix), size - 1));
+        } else {
+            let start: u64 = start.parse().ok()?;
+            let end = if end.is_empty() {
+                None
+            } else {
+                Some(end.parse::<u64>().ok()?)
+            };
+
+            if end.is_some_and(|end| end < start) {
+                return None;
+            }
+
+            if start >= size {
+                continue;
+            }
+
+            ranges.push((start, end.unwrap_or(u64::MAX).min(size - 1)));
+        }
+    }
+
+    if parsed == 0 || ranges.len() > MAX_RANGES {
+        return None;
+    }
+
+    Some(ranges)
+}
+
+/// Serves a seekable body, answering conditional and range requests
+pub async fn respond(
+    request_headers: &HeaderMap,
+    mut headers: HeaderMap,
+    size: u64,
+    validators: Validators,
+    source: impl RangeSource,
+) -> Result<ApiResponse, anyhow::Error> {
+    headers.insert("Accept-Ranges", "bytes".parse()?);
+    headers.insert("ETag", validators.etag.parse()?);
+    if let Some(last_modified) = validators.last_modified {
+        headers.insert("Last-Modified", http_date(last_modified).parse()?);
+    }
+
+    if validators.not_modified(request_headers) {
+        headers.remove("Content-Disposition");
+
+        return Ok(ApiResponse::new(Body::empty())
+            .with_status(StatusCode::NOT_MODIFIED)
+            .with_headers(headers));
+    }
+
+    let ranges = match request_headers.get("Range").and_then(|v| v.to_str().ok()) {
+        Some(range) if validators.if_range_matches(request_headers) => parse_ranges(range, size),
+        _ => None,
+    };
+
+    match ranges {
+        None => {
+            headers.insert("Content-Length", size.into());
+            let reader = source.open_range(0, size).await?;
+
+            Ok(ApiResponse::new(Body::from_stream(
+                tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+            ))
+            .with_headers(headers))
+        }
+        Some(ranges) if ranges.is_empty() => Ok(ApiResponse::error("range not satisfiable")
+            .with_status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .with_header("Content-Range", &format!("bytes */{size}"))),
+        Some(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+
+            headers.insert("Content-Length", (end - start + 1).into());
+            headers.insert(
+                "Content-Range",
+                format!("bytes {start}-{end}/{size}").parse()?,
+            );
+            let reader = source.open_range(start, end - start + 1).await?;
+
+            Ok(ApiResponse::new(Body::from_stream(
+                tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+            ))
+            .with_status(StatusCode::PARTIAL_CONTENT)
+            .with_headers(headers))
+        }
+        Some(ranges) => {
+            let boundary = format!("{:032x}", rand::random::<u128>());
+            let content_type = headers
+                .get("Content-Type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("application/octet-stream")
+                .to_string();
+
+            let parts = ranges
+                .into_iter()
+                .map(|(start, end)| {
+                    (
+                        format!(
+                            "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: bytes {start}-{end}/{size}\r\n\r\n"
+                        ),
+                        start,
+                        end - start + 1,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let trailer = format!("\r\n--{boundary}--\r\n");
+
+            let content_length = parts
+                .iter()
+                .map(|(header, _, length)| header.len() as u64 + length)
+                .sum::<u64>()
+                + trailer.len() as u64;
+
+            headers.insert("Content-Length", content_length.into());
+            headers.insert(
+                "Content-Type",
+                format!("multipart/byteranges; boundary={boundary}").parse()?,
+            );
+
+            let (reader, mut writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+            tokio::spawn(async move {
+                let result = async {
+                    for (header, offset, length) in parts {
+                        writer.write_all(header.as_bytes()).await?;
+
+                        let mut reader = source.open_range(offset, length).await?;
+                        tokio::io::copy(&mut reader, &mut writer).await?;
+                    }
+
+                    writer.write_all(trailer.as_bytes()).await?;
+                    writer.shutdown().await
+                }
+                .await;
+
+                if let Err(err) = result {
+                    tracing::debug!("failed to stream multipart range response: {:#?}", err);
+                }
+            });
+
+            Ok(ApiResponse::new(Body::from_stream(
+                tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+            ))
+            .with_status(StatusCode::PARTIAL_CONTENT)
+            .with_headers(headers))
+        }
+    }
+}
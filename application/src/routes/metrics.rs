@@ -0,0 +1,74 @@
+use super::{GetState, State};
+use crate::response::ApiResponse;
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+pub async fn auth(state: GetState, req: Request, next: Next) -> Result<Response<Body>, StatusCode> {
+    if !state.config.metrics.enabled {
+        return Ok(ApiResponse::error("route not found")
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    }
+
+    let key = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let token = match key.split_once(' ') {
+        Some(("Bearer", token)) => token,
+        _ => {
+            return Ok(ApiResponse::error("invalid authorization header")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .with_header("WWW-Authenticate", "Bearer")
+                .into_response());
+        }
+    };
+
+    if state.config.metrics.token.is_empty()
+        || !constant_time_eq::constant_time_eq(
+            token.as_bytes(),
+            state.config.metrics.token.as_bytes(),
+        )
+    {
+        return Ok(ApiResponse::error("invalid authorization token")
+            .with_status(StatusCode::UNAUTHORIZED)
+            .with_header("WWW-Authenticate", "Bearer")
+            .into_response());
+    }
+
+    Ok(next.run(req).await)
+}
+
+mod get {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+    };
+
+    #[utoipa::path(get, path = "/", responses(
+        (status = OK, body = String),
+        (status = UNAUTHORIZED, body = ApiError),
+    ))]
+    pub async fn route(state: GetState) -> ApiResponseResult {
+        ApiResponse::new(axum::body::Body::from(state.metrics.render(&state).await))
+            .with_header(
+                "Content-Type",
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )
+            .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(get::route))
+        .with_state(state.clone())
+}
@@ -6,6 +6,7 @@ use utoipa_axum::router::OpenApiRouter;
 
 pub mod api;
 mod download;
+mod metrics;
 mod upload;
 
 pub struct AppState {
@@ -16,6 +17,8 @@ pub struct AppState {
     pub docker: Arc<Docker>,
     pub server_manager: Arc<crate::server::manager::Manager>,
     pub backup_manager: Arc<crate::server::backup::manager::BackupManager>,
+    pub upload_manager: Arc<crate::server::upload::UploadManager>,
+    pub metrics: Arc<crate::metrics::Metrics>,
 }
 
 #[derive(ToSchema, Serialize)]
@@ -43,5 +46,12 @@ pub fn router(state: &State) -> OpenApiRouter<State> {
         .nest("/download", download::router(state))
         .nest("/upload", upload::router(state))
         .nest("/api", api::router(state))
+        .nest(
+            "/metrics",
+            metrics::router(state).route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                metrics::auth,
+            )),
+        )
         .with_state(state.clone())
 }
@@ -8,7 +8,7 @@ use utoipa_axum::{
 mod post {
     use crate::{
         response::{ApiResponse, ApiResponseResult},
-        routes::{ApiError, GetState},
+        routes::{ApiError, GetState, upload::FileJwtPayload},
         server::activity::{Activity, ActivityEvent},
     };
     use axum::{
@@ -31,19 +31,6 @@ mod post {
     #[derive(ToSchema, Serialize)]
     struct Response {}
 
-    #[derive(Deserialize)]
-    pub struct FileJwtPayload {
-        #[serde(flatten)]
-        pub base: crate::remote::jwt::BasePayload,
-
-        pub server_uuid: uuid::Uuid,
-        pub user_uuid: uuid::Uuid,
-        pub unique_id: String,
-
-        #[serde(default)]
-        pub ignored_files: Vec<String>,
-    }
-
     #[utoipa::path(post, path = "/", responses(
         (status = OK, body = inline(Response)),
         (status = UNAUTHORIZED, body = ApiError),
@@ -104,17 +91,7 @@ mod post {
             }
         };
 
-        let ignored = if payload.ignored_files.is_empty() {
-            None
-        } else {
-            let mut ignore_builder = ignore::gitignore::GitignoreBuilder::new("/");
-
-            for file in payload.ignored_files {
-                ignore_builder.add_line(None, &file).ok();
-            }
-
-            ignore_builder.build().ok()
-        };
+        let ignored = payload.ignored();
 
         let directory = PathBuf::from(data.directory);
 
@@ -1,10 +1,42 @@
 use super::State;
+use serde::Deserialize;
 use utoipa_axum::router::OpenApiRouter;
 
 mod file;
+mod session;
+
+#[derive(Deserialize)]
+pub struct FileJwtPayload {
+    #[serde(flatten)]
+    pub base: crate::remote::jwt::BasePayload,
+
+    pub server_uuid: uuid::Uuid,
+    pub user_uuid: uuid::Uuid,
+    pub unique_id: String,
+
+    #[serde(default)]
+    pub ignored_files: Vec<String>,
+}
+
+impl FileJwtPayload {
+    pub fn ignored(&self) -> Option<ignore::gitignore::Gitignore> {
+        if self.ignored_files.is_empty() {
+            return None;
+        }
+
+        let mut ignore_builder = ignore::gitignore::GitignoreBuilder::new("/");
+
+        for file in &self.ignored_files {
+            ignore_builder.add_line(None, file).ok();
+        }
+
+        ignore_builder.build().ok()
+    }
+}
 
 pub fn router(state: &State) -> OpenApiRouter<State> {
     OpenApiRouter::new()
         .nest("/file", file::router(state))
+        .nest("/session", session::router(state))
         .with_state(state.clone())
 }
@@ -0,0 +1,147 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+        server::activity::{Activity, ActivityEvent},
+    };
+    use axum::{extract::Path, http::StatusCode};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use tokio::io::AsyncWriteExt;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        /// sha256 of the complete file
+        #[serde(default)]
+        checksum: Option<String>,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "session" = uuid::Uuid,
+            description = "The upload session id",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        state: GetState,
+        Path(session): Path<uuid::Uuid>,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let session = match state.upload_manager.get(session).await {
+            Some(session) => session,
+            None => {
+                return ApiResponse::error("upload session not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let _guard = session.write_lock.lock().await;
+
+        // another finish request may have completed the session while waiting for the lock
+        if state.upload_manager.get(session.id).await.is_none() {
+            return ApiResponse::error("upload session not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        if session.offset() != session.size {
+            return ApiResponse::error("upload is not complete")
+                .with_status(StatusCode::CONFLICT)
+                .with_header("Upload-Offset", &session.offset().to_string())
+                .ok();
+        }
+
+        let result = async {
+            if let Some(checksum) = &data.checksum {
+                let actual =
+                    crate::server::transfer::chunked::file_checksum(&session.staging_path).await?;
+
+                if !actual.eq_ignore_ascii_case(checksum) {
+                    return Ok(Some(
+                        ApiResponse::error("checksum does not match")
+                            .with_status(StatusCode::EXPECTATION_FAILED),
+                    ));
+                }
+            }
+
+            if let Some(parent) = session.path.parent() {
+                session
+                    .server
+                    .filesystem
+                    .async_create_dir_all(parent)
+                    .await?;
+            }
+
+            let mut reader = tokio::fs::File::open(&session.staging_path).await?;
+            let mut writer = crate::server::filesystem::writer::AsyncFileSystemWriter::new(
+                session.server.clone(),
+                &session.path,
+                None,
+                None,
+            )
+            .await?
+            .ignorant();
+
+            tokio::io::copy(&mut reader, &mut writer).await?;
+            writer.flush().await?;
+
+            Ok::<_, anyhow::Error>(None)
+        }
+        .await;
+
+        // failed sessions are kept so the client can retry finishing them without uploading again
+        match result {
+            Ok(None) => {}
+            Ok(Some(response)) => {
+                session.touch();
+
+                return response.ok();
+            }
+            Err(err) => {
+                session.touch();
+
+                return Err(err.into());
+            }
+        }
+
+        state.upload_manager.remove(session.id).await;
+        session.discard().await;
+
+        session
+            .server
+            .activity
+            .log_activity(Activity {
+                event: ActivityEvent::FileUploaded,
+                user: Some(session.user_uuid),
+                ip: session.user_ip,
+                metadata: Some(json!({
+                    "files": [session.path.file_name().map(|name| name.to_string_lossy())],
+                    "directory": session.server.filesystem.relative_path(session.parent()),
+                })),
+                timestamp: chrono::Utc::now(),
+            })
+            .await;
+
+        ApiResponse::json(Response {}).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .with_state(state.clone())
+}
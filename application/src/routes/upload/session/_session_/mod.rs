@@ -0,0 +1,248 @@
+use super::State;
+use axum::extract::DefaultBodyLimit;
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+mod finish;
+
+mod head {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+    };
+    use axum::{body::Body, extract::Path, http::StatusCode};
+
+    #[utoipa::path(head, path = "/", responses(
+        (status = OK),
+        (status = NOT_FOUND, body = ApiError),
+    ), params(
+        (
+            "session" = uuid::Uuid,
+            description = "The upload session id",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ))]
+    pub async fn route(state: GetState, Path(session): Path<uuid::Uuid>) -> ApiResponseResult {
+        let session = match state.upload_manager.get(session).await {
+            Some(session) => session,
+            None => {
+                return ApiResponse::error("upload session not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        session.touch();
+
+        ApiResponse::new(Body::empty())
+            .with_header("Upload-Offset", &session.offset().to_string())
+            .with_header("Upload-Length", &session.size.to_string())
+            .with_header("Cache-Control", "no-store")
+            .ok()
+    }
+}
+
+mod patch {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+    };
+    use axum::{
+        body::Body,
+        extract::Path,
+        http::{HeaderMap, StatusCode},
+    };
+    use futures_util::StreamExt;
+    use std::io::SeekFrom;
+    use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+    /// Space is reserved in steps of this size ahead of the written data
+    const ALLOCATION_STEP: u64 = 8 * 1024 * 1024;
+
+    #[utoipa::path(patch, path = "/", responses(
+        (status = NO_CONTENT),
+        (status = BAD_REQUEST, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = CONFLICT, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "session" = uuid::Uuid,
+            description = "The upload session id",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+        (
+            "Upload-Offset" = u64, Header,
+            description = "The offset the body should be written at, has to match the current offset",
+        ),
+    ), request_body = String)]
+    pub async fn route(
+        state: GetState,
+        headers: HeaderMap,
+        Path(session): Path<uuid::Uuid>,
+        body: Body,
+    ) -> ApiResponseResult {
+        let session = match state.upload_manager.get(session).await {
+            Some(session) => session,
+            None => {
+                return ApiResponse::error("upload session not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let offset: u64 = match headers
+            .get("Upload-Offset")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+        {
+            Some(offset) => offset,
+            None => {
+                return ApiResponse::error("missing or invalid Upload-Offset header")
+                    .with_status(StatusCode::BAD_REQUEST)
+                    .ok();
+            }
+        };
+
+        let _guard = session.write_lock.lock().await;
+        if state.upload_manager.get(session.id).await.is_none() {
+            return ApiResponse::error("upload session not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        if offset != session.offset() {
+            return ApiResponse::error("upload offset does not match")
+                .with_status(StatusCode::CONFLICT)
+                .with_header("Upload-Offset", &session.offset().to_string())
+                .ok();
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&session.staging_path)
+            .await?;
+        file.set_len(offset).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        let mut writer = tokio::io::BufWriter::with_capacity(crate::BUFFER_SIZE, file);
+        let mut stream = body.into_data_stream();
+        let limit = state.config.api.upload_limit as u64 * 1000 * 1000;
+
+        let mut written = 0;
+        let mut allocated = 0;
+        let result = async {
+            while let Some(Ok(chunk)) = stream.next().await {
+                let length = chunk.len() as u64;
+
+                if crate::unlikely(offset + written + length > session.size) {
+                    return Err(
+                        ApiResponse::error("upload is larger than its declared size")
+                            .with_status(StatusCode::EXPECTATION_FAILED),
+                    );
+                }
+
+                if crate::unlikely(written + length > limit) {
+                    return Err(ApiResponse::error(&format!(
+                        "chunk size is larger than {}MB",
+                        state.config.api.upload_limit
+                    ))
+                    .with_status(StatusCode::EXPECTATION_FAILED));
+                }
+
+                if written + length > allocated {
+                    let step = (written + length - allocated)
+                        .max(ALLOCATION_STEP)
+                        .min(session.size - offset - allocated);
+
+                    if !session
+                        .server
+                        .filesystem
+                        .async_allocate_in_path(session.parent(), step as i64, false)
+                        .await
+                    {
+                        return Err(ApiResponse::error("failed to allocate space")
+                            .with_status(StatusCode::EXPECTATION_FAILED));
+                    }
+
+                    allocated += step;
+                }
+
+                writer.write_all(&chunk).await?;
+                written += length;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        let flushed = writer.flush().await;
+        let length = tokio::fs::metadata(&session.staging_path).await?.len();
+        let written = length.saturating_sub(offset).min(allocated);
+
+        session
+            .server
+            .filesystem
+            .async_allocate_in_path(session.parent(), -((allocated - written) as i64), true)
+            .await;
+        session.set_offset(offset + written);
+
+        result?;
+        flushed?;
+
+        ApiResponse::new(Body::empty())
+            .with_status(StatusCode::NO_CONTENT)
+            .with_header("Upload-Offset", &session.offset().to_string())
+            .ok()
+    }
+}
+
+mod delete {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState},
+    };
+    use axum::{extract::Path, http::StatusCode};
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {}
+
+    #[utoipa::path(delete, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = NOT_FOUND, body = ApiError),
+    ), params(
+        (
+            "session" = uuid::Uuid,
+            description = "The upload session id",
+            example = "123e4567-e89b-12d3-a456-426614174000",
+        ),
+    ))]
+    pub async fn route(state: GetState, Path(session): Path<uuid::Uuid>) -> ApiResponseResult {
+        let session = match state.upload_manager.remove(session).await {
+            Some(session) => session,
+            None => {
+                return ApiResponse::error("upload session not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let _guard = session.write_lock.lock().await;
+        session.discard().await;
+
+        ApiResponse::json(Response {}).ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(head::route))
+        .routes(routes!(patch::route).layer(DefaultBodyLimit::disable()))
+        .routes(routes!(delete::route))
+        .nest("/finish", finish::router(state))
+        .with_state(state.clone())
+}
@@ -0,0 +1,159 @@
+use super::State;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod _session_;
+
+mod post {
+    use crate::{
+        response::{ApiResponse, ApiResponseResult},
+        routes::{ApiError, GetState, upload::FileJwtPayload},
+    };
+    use axum::{
+        extract::{ConnectInfo, Query},
+        http::{HeaderMap, StatusCode},
+    };
+    use serde::{Deserialize, Serialize};
+    use std::{net::SocketAddr, path::PathBuf};
+    use utoipa::ToSchema;
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Params {
+        token: String,
+        #[serde(default)]
+        directory: String,
+    }
+
+    #[derive(ToSchema, Deserialize)]
+    pub struct Payload {
+        name: String,
+        size: u64,
+    }
+
+    #[derive(ToSchema, Serialize)]
+    struct Response {
+        id: uuid::Uuid,
+        offset: u64,
+    }
+
+    #[utoipa::path(post, path = "/", responses(
+        (status = OK, body = inline(Response)),
+        (status = UNAUTHORIZED, body = ApiError),
+        (status = NOT_FOUND, body = ApiError),
+        (status = EXPECTATION_FAILED, body = ApiError),
+    ), params(
+        (
+            "token" = String, Query,
+            description = "The JWT token to use for authentication",
+        ),
+        (
+            "directory" = String, Query,
+            description = "The directory to upload the file to",
+        ),
+    ), request_body = inline(Payload))]
+    pub async fn route(
+        state: GetState,
+        headers: HeaderMap,
+        connect_info: ConnectInfo<SocketAddr>,
+        Query(params): Query<Params>,
+        axum::Json(data): axum::Json<Payload>,
+    ) -> ApiResponseResult {
+        let payload: FileJwtPayload = match state.config.jwt.verify(&params.token) {
+            Ok(payload) => payload,
+            Err(_) => {
+                return ApiResponse::error("invalid token")
+                    .with_status(StatusCode::UNAUTHORIZED)
+                    .ok();
+            }
+        };
+
+        if !payload.base.validate(&state.config.jwt).await {
+            return ApiResponse::error("invalid token")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .ok();
+        }
+
+        if !state.config.jwt.one_time_id(&payload.unique_id).await {
+            return ApiResponse::error("token has already been used")
+                .with_status(StatusCode::UNAUTHORIZED)
+                .ok();
+        }
+
+        let server = state
+            .server_manager
+            .get_servers()
+            .await
+            .iter()
+            .find(|s| s.uuid == payload.server_uuid)
+            .cloned();
+
+        let server = match server {
+            Some(server) => server,
+            None => {
+                return ApiResponse::error("server not found")
+                    .with_status(StatusCode::NOT_FOUND)
+                    .ok();
+            }
+        };
+
+        let directory = PathBuf::from(params.directory);
+
+        let metadata = server.filesystem.async_metadata(&directory).await;
+        if !metadata.map(|m| m.is_dir()).unwrap_or(true) {
+            return ApiResponse::error("directory is not a directory")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        if data.name.is_empty() {
+            return ApiResponse::error("file name not found")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        let file_path = directory.join(&data.name);
+
+        if payload
+            .ignored()
+            .map(|o| o.matched(&file_path, false).is_ignore())
+            .unwrap_or(false)
+            || server.filesystem.is_ignored(&file_path, false).await
+        {
+            return ApiResponse::error("file not found")
+                .with_status(StatusCode::NOT_FOUND)
+                .ok();
+        }
+
+        if server.filesystem.disk_limit() != 0
+            && server.filesystem.limiter_usage().await as i64 + data.size as i64
+                > server.filesystem.disk_limit()
+        {
+            return ApiResponse::error("not enough disk space for this file")
+                .with_status(StatusCode::EXPECTATION_FAILED)
+                .ok();
+        }
+
+        let session = state
+            .upload_manager
+            .create(
+                server,
+                payload.user_uuid,
+                Some(state.config.find_ip(&headers, connect_info)),
+                file_path,
+                data.size,
+            )
+            .await?;
+
+        ApiResponse::json(Response {
+            id: session.id,
+            offset: 0,
+        })
+        .ok()
+    }
+}
+
+pub fn router(state: &State) -> OpenApiRouter<State> {
+    OpenApiRouter::new()
+        .routes(routes!(post::route))
+        .nest("/{session}", _session_::router(state))
+        .with_state(state.clone())
+}
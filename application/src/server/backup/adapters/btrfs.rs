@@ -318,6 +318,13 @@ impl BackupExt for BtrfsBackup {
         .with_headers(headers))
     }
 
+    async fn download_file(
+        &self,
+        _config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        Ok(None)
+    }
+
     async fn restore(
         &self,
         server: &crate::server::Server,
@@ -452,6 +459,30 @@ impl BackupExt for BtrfsBackup {
         Ok(())
     }
 
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let subvolume_path = Self::get_subvolume_path(config, self.uuid);
+
+        if tokio::fs::metadata(&subvolume_path).await.is_err() {
+            return Err(anyhow::anyhow!(
+                "btrfs backup subvolume does not exist: {}",
+                subvolume_path.display()
+            ));
+        }
+
+        let filesystem = crate::server::filesystem::cap::CapFilesystem::new(subvolume_path).await?;
+        let ignore = Self::get_ignore(config, self.uuid).await?;
+
+        super::verify_snapshot(filesystem, ignore, progress, total).await?;
+
+        Ok(None)
+    }
+
     async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         let subvolume_path = Self::get_subvolume_path(config, self.uuid);
 
@@ -10,7 +10,7 @@ use crate::{
     server::{
         backup::{
             Backup, BackupBrowseExt, BackupCleanExt, BackupCreateExt, BackupExt, BackupFindExt,
-            BrowseBackup,
+            BrowseBackup, encryption::EncryptedChunkStorage,
         },
         filesystem::archive::StreamableArchiveFormat,
     },
@@ -45,9 +45,11 @@ pub async fn get_repository(
 
     let path = PathBuf::from(&config.system.backup_directory);
     if tokio::fs::metadata(path.join(".ddup-bak")).await.is_ok() {
+        let storage = EncryptedChunkStorage::new(config, path.join(".ddup-bak/chunks"));
         let repository = Arc::new(
             tokio::task::spawn_blocking(move || {
-                ddup_bak::repository::Repository::open(&path, None, None).unwrap()
+                ddup_bak::repository::Repository::open(&path, None, Some(Arc::new(storage)))
+                    .unwrap()
             })
             .await
             .unwrap(),
@@ -56,9 +58,15 @@ pub async fn get_repository(
 
         repository
     } else {
+        let storage = EncryptedChunkStorage::new(config, path.join(".ddup-bak/chunks"));
         let repository = Arc::new(
             tokio::task::spawn_blocking(move || {
-                ddup_bak::repository::Repository::new(&path, 1024 * 1024, 0, None)
+                ddup_bak::repository::Repository::new(
+                    &path,
+                    1024 * 1024,
+                    0,
+                    Some(Arc::new(storage)),
+                )
             })
             .await
             .unwrap(),
@@ -471,6 +479,13 @@ impl BackupExt for DdupBakBackup {
         .with_headers(headers))
     }
 
+    async fn download_file(
+        &self,
+        _config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        Ok(None)
+    }
+
     async fn restore(
         &self,
         server: &crate::server::Server,
@@ -588,6 +603,93 @@ impl BackupExt for DdupBakBackup {
         Ok(())
     }
 
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let repository = get_repository(config).await;
+        let path = repository.archive_path(&self.uuid.to_string());
+
+        let archive = self.archive.clone();
+        tokio::task::spawn_blocking(move || -> Result<String, anyhow::Error> {
+            fn recursive_size(entry: &Entry) -> u64 {
+                match entry {
+                    Entry::File(file) => file.size_real,
+                    Entry::Directory(directory) => {
+                        directory.entries.iter().map(recursive_size).sum()
+                    }
+                    Entry::Symlink(_) => 0,
+                }
+            }
+
+            let mut file = std::fs::File::open(&path)?;
+            let file_size = file.metadata()?.len();
+
+            total.store(
+                file_size + archive.entries().iter().map(recursive_size).sum::<u64>(),
+                Ordering::SeqCst,
+            );
+
+            let mut sha1 = sha1::Sha1::new();
+            let mut buffer = vec![0; crate::BUFFER_SIZE];
+            loop {
+                let bytes_read = file.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+
+                sha1.update(&buffer[..bytes_read]);
+                progress.fetch_add(bytes_read as u64, Ordering::Relaxed);
+            }
+
+            fn recursive_verify(
+                repository: &Arc<ddup_bak::repository::Repository>,
+                entry: &Entry,
+                path: &Path,
+                progress: &Arc<AtomicU64>,
+            ) -> Result<(), anyhow::Error> {
+                let path = path.join(entry.name());
+
+                match entry {
+                    Entry::File(file) => {
+                        let reader = repository.entry_reader(Entry::File(file.clone()))?;
+                        let mut reader =
+                            CountingReader::new_with_bytes_read(reader, Arc::clone(progress));
+
+                        let bytes_read = std::io::copy(&mut reader, &mut std::io::sink())?;
+                        if bytes_read != file.size_real {
+                            return Err(anyhow::anyhow!(
+                                "file {} is {} bytes but its chunks hold {} bytes",
+                                path.display(),
+                                file.size_real,
+                                bytes_read
+                            ));
+                        }
+                    }
+                    Entry::Directory(directory) => {
+                        for entry in &directory.entries {
+                            recursive_verify(repository, entry, &path, progress)?;
+                        }
+                    }
+                    Entry::Symlink(_) => {}
+                }
+
+                Ok(())
+            }
+
+            for entry in archive.entries() {
+                recursive_verify(&repository, entry, Path::new(""), &progress)?;
+            }
+
+            Ok(format!("{}-{:x}", file_size, sha1.finalize()))
+        })
+        .await?
+        .map(Some)
+    }
+
     async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         let repository = get_repository(config).await;
 
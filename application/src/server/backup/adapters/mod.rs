@@ -3,7 +3,13 @@ use crate::{
     server::backup::{Backup, BackupCleanExt, BackupCreateExt, BackupFindExt},
 };
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, atomic::AtomicU64};
+use std::{
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 use utoipa::ToSchema;
 
 pub mod btrfs;
@@ -174,3 +180,46 @@ impl BackupAdapter {
         }
     }
 }
+
+/// Reads every file of a snapshot based backup, making sure the snapshot is still intact
+async fn verify_snapshot(
+    filesystem: crate::server::filesystem::cap::CapFilesystem,
+    ignore: ignore::gitignore::Gitignore,
+    progress: Arc<AtomicU64>,
+    total: Arc<AtomicU64>,
+) -> Result<(), anyhow::Error> {
+    let ignored = [ignore];
+
+    let mut walker = filesystem
+        .async_walk_dir(Path::new(""))
+        .await?
+        .with_ignored(&ignored);
+    while let Some(entry) = walker.next_entry().await {
+        let (_, path) = entry?;
+
+        let metadata = filesystem.async_symlink_metadata(&path).await?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        total.fetch_add(metadata.len(), Ordering::Relaxed);
+
+        let file = filesystem.async_open(&path).await?;
+        let mut reader = crate::io::counting_reader::AsyncCountingReader::new_with_bytes_read(
+            file,
+            Arc::clone(&progress),
+        );
+
+        let bytes_read = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+        if bytes_read != metadata.len() {
+            return Err(anyhow::anyhow!(
+                "file {} is {} bytes but {} bytes could be read",
+                path.display(),
+                metadata.len(),
+                bytes_read
+            ));
+        }
+    }
+
+    Ok(())
+}
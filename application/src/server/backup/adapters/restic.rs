@@ -675,6 +675,13 @@ impl BackupExt for ResticBackup {
         .with_headers(headers))
     }
 
+    async fn download_file(
+        &self,
+        _config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        Ok(None)
+    }
+
     async fn restore(
         &self,
         server: &crate::server::Server,
@@ -735,6 +742,70 @@ impl BackupExt for ResticBackup {
         Ok(())
     }
 
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        total.store(2, Ordering::SeqCst);
+
+        let output = Command::new("restic")
+            .envs(&self.configuration.environment)
+            .arg("--json")
+            .arg("--no-lock")
+            .arg("--repo")
+            .arg(&self.configuration.repository)
+            .args(self.configuration.password())
+            .arg("snapshots")
+            .arg(&self.short_id)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await?;
+
+        if !output.status.success()
+            || serde_json::from_slice::<Vec<serde_json::Value>>(&output.stdout)
+                .is_ok_and(|snapshots| snapshots.is_empty())
+        {
+            return Err(anyhow::anyhow!(
+                "restic snapshot {} not found: {}",
+                self.short_id,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        progress.store(1, Ordering::SeqCst);
+
+        let output = Command::new("restic")
+            .envs(&self.configuration.environment)
+            .arg("--repo")
+            .arg(&self.configuration.repository)
+            .args(self.configuration.password())
+            .arg("--retry-lock")
+            .arg(format!("{}s", self.configuration.retry_lock_seconds))
+            .arg("check")
+            .arg(format!(
+                "--read-data-subset={}",
+                config.system.backups.restic.verify_read_data_subset
+            ))
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "restic repository check failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        progress.store(2, Ordering::SeqCst);
+
+        Ok(None)
+    }
+
     async fn delete(&self, _config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         let output = Command::new("restic")
             .envs(&self.configuration.environment)
@@ -7,7 +7,10 @@ use crate::{
     },
     remote::backups::RawServerBackup,
     server::{
-        backup::{Backup, BackupCleanExt, BackupCreateExt, BackupExt, BackupFindExt, BrowseBackup},
+        backup::{
+            Backup, BackupCleanExt, BackupCreateExt, BackupExt, BackupFindExt, BrowseBackup,
+            encryption::{self, BackupReader, BackupWriter},
+        },
         filesystem::archive::StreamableArchiveFormat,
     },
 };
@@ -32,7 +35,7 @@ use tokio::{
 static CLIENT: RwLock<Option<Arc<reqwest::Client>>> = RwLock::const_new(None);
 
 #[inline]
-async fn get_client(server: &crate::server::Server) -> Arc<reqwest::Client> {
+async fn get_client(config: &crate::config::Config) -> Arc<reqwest::Client> {
     if let Some(client) = CLIENT.read().await.as_ref() {
         return Arc::clone(client);
     }
@@ -40,15 +43,9 @@ async fn get_client(server: &crate::server::Server) -> Arc<reqwest::Client> {
     let client = Arc::new(
         reqwest::ClientBuilder::new()
             .timeout(std::time::Duration::from_secs(
-                server
-                    .app_state
-                    .config
-                    .system
-                    .backups
-                    .s3
-                    .part_upload_timeout,
+                config.system.backups.s3.part_upload_timeout,
             ))
-            .danger_accept_invalid_certs(server.app_state.config.ignore_certificate_errors)
+            .danger_accept_invalid_certs(config.ignore_certificate_errors)
             .build()
             .unwrap(),
     );
@@ -216,11 +213,17 @@ impl BackupCreateExt for S3Backup {
             }
         };
 
+        let key = encryption::server_key(server).await;
+        let encrypted = key.is_some();
+
         let archive_task = async {
             let sources = server.filesystem.async_read_dir_all(Path::new("")).await?;
-            let writer = tokio_util::io::SyncIoBridge::new(checksum_writer);
+            let backup_writer = tokio::task::spawn_blocking(move || {
+                BackupWriter::new(tokio_util::io::SyncIoBridge::new(checksum_writer), key)
+            })
+            .await??;
             let writer = LimitedWriter::new_with_bytes_per_second(
-                writer,
+                backup_writer.clone(),
                 server.app_state.config.system.backups.write_limit * 1024 * 1024,
             );
 
@@ -237,10 +240,16 @@ impl BackupCreateExt for S3Backup {
                     threads: server.app_state.config.system.backups.s3.create_threads,
                 },
             )
-            .await
+            .await?;
+
+            // dropping the finished writer closes the stream for the checksum task
+            tokio::task::spawn_blocking(move || backup_writer.finish()).await??;
+
+            Ok::<_, anyhow::Error>(())
         };
 
         let (checksum, total_files, _) = tokio::try_join!(checksum_task, total_task, archive_task)?;
+        file.flush().await?;
 
         let size = file.metadata().await?.len();
         let (part_size, part_urls) = server
@@ -277,11 +286,18 @@ impl BackupCreateExt for S3Backup {
                     server.uuid
                 );
 
-                match get_client(server)
+                match get_client(&server.app_state.config)
                     .await
                     .put(&url)
                     .header("Content-Length", part_size)
-                    .header("Content-Type", "application/gzip")
+                    .header(
+                        "Content-Type",
+                        if encrypted {
+                            "application/octet-stream"
+                        } else {
+                            "application/gzip"
+                        },
+                    )
                     .body(reqwest::Body::wrap_stream(
                         tokio_util::io::ReaderStream::with_capacity(
                             AsyncLimitedReader::new_with_bytes_per_second(
@@ -375,6 +391,13 @@ impl BackupExt for S3Backup {
         ))
     }
 
+    async fn download_file(
+        &self,
+        _config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        Ok(None)
+    }
+
     async fn restore(
         &self,
         server: &crate::server::Server,
@@ -382,7 +405,7 @@ impl BackupExt for S3Backup {
         total: Arc<AtomicU64>,
         download_url: Option<String>,
     ) -> Result<(), anyhow::Error> {
-        let response = get_client(server)
+        let response = get_client(&server.app_state.config)
             .await
             .get(download_url.unwrap())
             .send()
@@ -405,6 +428,7 @@ impl BackupExt for S3Backup {
                 server.app_state.config.system.backups.read_limit * 1024 * 1024,
             );
             let reader = CountingReader::new_with_bytes_read(reader, progress);
+            let reader = BackupReader::new(&server.app_state.config, reader)?;
             let reader = CompressionReader::new(reader, CompressionType::Gz);
 
             let mut archive = tar::Archive::new(reader);
@@ -498,6 +522,72 @@ impl BackupExt for S3Backup {
         Ok(())
     }
 
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let download_url = match download_url {
+            Some(download_url) => download_url,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "unable to verify s3 backup without download_url"
+                ));
+            }
+        };
+
+        let response = get_client(config)
+            .await
+            .get(download_url)
+            .send()
+            .await?
+            .error_for_status()?;
+        if let Some(content_length) = response.content_length() {
+            total.store(content_length, Ordering::SeqCst);
+        }
+
+        let (reader, mut writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+
+        let check_task = tokio::task::spawn_blocking({
+            let config = Arc::clone(config);
+
+            move || -> Result<(), anyhow::Error> {
+                let reader = tokio_util::io::SyncIoBridge::new(reader);
+                let reader = BackupReader::new(&config, reader)?;
+                let reader = CompressionReader::new(reader, CompressionType::Gz);
+
+                let mut archive = tar::Archive::new(reader);
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    std::io::copy(&mut entry, &mut std::io::sink())?;
+                }
+
+                Ok(())
+            }
+        });
+
+        let mut sha1 = sha1::Sha1::new();
+        let mut stream = response.bytes_stream();
+        let mut forwarding = true;
+        while let Some(chunk) = stream.try_next().await? {
+            sha1.update(&chunk);
+            progress.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+
+            // once the archive check has failed there is nobody left to read,
+            // keep hashing so the checksum still reflects the stored object
+            if forwarding && writer.write_all(&chunk).await.is_err() {
+                forwarding = false;
+            }
+        }
+        drop(writer);
+
+        check_task.await??;
+
+        Ok(Some(format!("{:x}", sha1.finalize())))
+    }
+
     async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         let file_name = Self::get_file_name(config, self.uuid);
         if tokio::fs::metadata(&file_name).await.is_ok() {
@@ -0,0 +1,806 @@
+use crate::{
+    io::{
+        abort::{AbortGuard, AbortWriter},
+        compression::{CompressionLevel, CompressionType, writer::CompressionWriter},
+        counting_reader::CountingReader,
+        fixed_reader::FixedReader,
+    },
+    server::backup::encryption::{BackupEncryptionKey, BackupFile, BackupReader, BackupWriter},
+};
+use cap_std::fs::PermissionsExt;
+use chrono::{Datelike, Timelike};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha1::Digest;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::AtomicU64},
+};
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupManifestEntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct BackupManifestEntry {
+    pub kind: BackupManifestEntryKind,
+    pub size: u64,
+    pub mtime: u64,
+    pub mode: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+
+    /// The backup in the chain that holds the contents of this entry
+    pub backup: uuid::Uuid,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BackupManifest {
+    pub server: uuid::Uuid,
+    pub format: crate::config::SystemBackupsWingsArchiveFormat,
+    /// Every backup needed to rebuild this one, starting with the full backup and ending with this one
+    pub chain: Vec<uuid::Uuid>,
+
+    pub entries: BTreeMap<String, BackupManifestEntry>,
+}
+
+impl BackupManifest {
+    #[inline]
+    fn get_file_name(config: &crate::config::Config, uuid: uuid::Uuid) -> PathBuf {
+        Path::new(&config.system.backup_directory).join(format!("{uuid}.manifest.json"))
+    }
+
+    #[inline]
+    fn get_deleted_file_name(config: &crate::config::Config, uuid: uuid::Uuid) -> PathBuf {
+        Path::new(&config.system.backup_directory).join(format!("{uuid}.deleted"))
+    }
+
+    #[inline]
+    fn get_head_file_name(config: &crate::config::Config, server: uuid::Uuid) -> PathBuf {
+        Path::new(&config.system.backup_directory).join(format!("{server}.head"))
+    }
+
+    #[inline]
+    pub fn uuid(&self) -> uuid::Uuid {
+        self.chain.last().copied().unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn is_incremental(&self) -> bool {
+        self.chain.len() > 1
+    }
+
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.entries
+            .values()
+            .filter(|entry| entry.kind != BackupManifestEntryKind::Directory)
+            .count() as u64
+    }
+
+    /// Paths whose contents are stored in the archive of the given backup
+    pub fn owned_by(&self, backup: uuid::Uuid) -> HashSet<PathBuf> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.backup == backup)
+            .map(|(path, _)| PathBuf::from(path))
+            .collect()
+    }
+
+    pub async fn load(
+        config: &crate::config::Config,
+        uuid: uuid::Uuid,
+    ) -> Result<Option<Self>, anyhow::Error> {
+        let data = match tokio::fs::read(Self::get_file_name(config, uuid)).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Some(Self::parse(config, data).await?))
+    }
+
+    /// Manifests are encrypted like the archives they describe, since they list every file path
+    async fn parse<T: serde::de::DeserializeOwned + Send + 'static>(
+        config: &crate::config::Config,
+        data: Vec<u8>,
+    ) -> Result<T, anyhow::Error> {
+        let node_key = config.system.backups.encryption.key.clone();
+
+        tokio::task::spawn_blocking(move || -> Result<T, anyhow::Error> {
+            let reader = BackupReader::with_node_key(&node_key, std::io::Cursor::new(data))?;
+
+            Ok(serde_json::from_reader(reader)?)
+        })
+        .await?
+    }
+
+    pub async fn save(
+        &self,
+        config: &crate::config::Config,
+        key: Option<BackupEncryptionKey>,
+    ) -> Result<(), anyhow::Error> {
+        let mut writer = BackupWriter::new(Vec::new(), key)?;
+        serde_json::to_writer(&mut writer, self)?;
+
+        tokio::fs::write(Self::get_file_name(config, self.uuid()), writer.finish()?).await?;
+        tokio::fs::write(
+            Self::get_head_file_name(config, self.server),
+            self.uuid().to_string(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn exists(config: &crate::config::Config, uuid: uuid::Uuid) -> bool {
+        tokio::fs::metadata(Self::get_file_name(config, uuid))
+            .await
+            .is_ok()
+    }
+
+    #[inline]
+    pub async fn is_deleted(config: &crate::config::Config, uuid: uuid::Uuid) -> bool {
+        tokio::fs::metadata(Self::get_deleted_file_name(config, uuid))
+            .await
+            .is_ok()
+    }
+
+    /// Picks the backup a new backup of this server builds on, `None` means a full backup has to be created
+    pub async fn parent(config: &crate::config::Config, server: uuid::Uuid) -> Option<Self> {
+        let head = tokio::fs::read_to_string(Self::get_head_file_name(config, server))
+            .await
+            .ok()?;
+        let head: uuid::Uuid = head.trim().parse().ok()?;
+
+        if Self::is_deleted(config, head).await {
+            return None;
+        }
+
+        let manifest = Self::load(config, head).await.ok()??;
+        if manifest.server != server
+            || manifest.format != config.system.backups.wings.archive_format
+            || manifest.chain.len() >= config.system.backups.wings.incremental.max_chain_length
+        {
+            return None;
+        }
+
+        for backup in manifest.chain.iter() {
+            if super::WingsBackup::get_first_file_name(config, *backup)
+                .await
+                .is_err()
+            {
+                return None;
+            }
+        }
+
+        Some(manifest)
+    }
+
+    /// Walks the server filesystem and compares it against the parent, only files whose
+    /// size, mtime or mode changed are hashed. Returns the paths that have to be archived
+    pub fn build(
+        filesystem: crate::server::filesystem::cap::CapFilesystem,
+        ignored: Vec<ignore::gitignore::Gitignore>,
+        server: uuid::Uuid,
+        uuid: uuid::Uuid,
+        format: crate::config::SystemBackupsWingsArchiveFormat,
+        parent: Option<Self>,
+    ) -> Result<(Self, Vec<PathBuf>), anyhow::Error> {
+        let mut entries = BTreeMap::new();
+        let mut archived = Vec::new();
+
+        let mut walker = filesystem.walk_dir(Path::new(""))?.with_ignored(&ignored);
+        while let Some(Ok((_, path))) = walker.next_entry() {
+            let metadata = match filesystem.symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            let kind = if metadata.is_dir() {
+                BackupManifestEntryKind::Directory
+            } else if metadata.is_file() {
+                BackupManifestEntryKind::File
+            } else if metadata.is_symlink() {
+                BackupManifestEntryKind::Symlink
+            } else {
+                continue;
+            };
+
+            let key = path.to_string_lossy().to_string();
+            let mut entry = BackupManifestEntry {
+                kind,
+                size: if kind == BackupManifestEntryKind::Directory {
+                    0
+                } else {
+                    metadata.len()
+                },
+                mtime: metadata
+                    .modified()
+                    .map(|t| {
+                        t.into_std()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                    })
+                    .unwrap_or_default()
+                    .as_secs(),
+                mode: metadata.permissions().mode(),
+                hash: None,
+                backup: uuid,
+            };
+
+            // directories are cheap, every archive in a chain carries the full directory tree
+            let previous = match &parent {
+                Some(parent) if kind != BackupManifestEntryKind::Directory => parent
+                    .entries
+                    .get(&key)
+                    .filter(|previous| previous.kind == kind),
+                _ => None,
+            };
+
+            if let Some(previous) = previous {
+                if previous.size == entry.size
+                    && previous.mtime == entry.mtime
+                    && previous.mode == entry.mode
+                {
+                    entries.insert(key, previous.clone());
+                    continue;
+                }
+
+                entry.hash = Some(Self::hash(&filesystem, &path, kind)?);
+                if previous.hash.is_some()
+                    && previous.hash == entry.hash
+                    && previous.mode == entry.mode
+                {
+                    entry.backup = previous.backup;
+                    entries.insert(key, entry);
+                    continue;
+                }
+            } else if parent.is_some() && kind != BackupManifestEntryKind::Directory {
+                entry.hash = Some(Self::hash(&filesystem, &path, kind)?);
+            }
+
+            entries.insert(key, entry);
+            archived.push(path);
+        }
+
+        let mut chain = parent.map(|parent| parent.chain).unwrap_or_default();
+        chain.push(uuid);
+
+        Ok((
+            Self {
+                server,
+                format,
+                chain,
+                entries,
+            },
+            archived,
+        ))
+    }
+
+    fn hash(
+        filesystem: &crate::server::filesystem::cap::CapFilesystem,
+        path: &Path,
+        kind: BackupManifestEntryKind,
+    ) -> Result<String, anyhow::Error> {
+        let mut hasher = sha1::Sha1::new();
+
+        if kind == BackupManifestEntryKind::Symlink {
+            hasher.update(
+                filesystem
+                    .read_link_contents(path)?
+                    .as_os_str()
+                    .as_encoded_bytes(),
+            );
+        } else {
+            let mut file = filesystem.open(path)?;
+            let mut buffer = vec![0; crate::BUFFER_SIZE];
+
+            loop {
+                match file.read(&mut buffer)? {
+                    0 => break,
+                    bytes_read => hasher.update(&buffer[..bytes_read]),
+                }
+            }
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    pub async fn mark_deleted(
+        config: &crate::config::Config,
+        uuid: uuid::Uuid,
+    ) -> Result<(), anyhow::Error> {
+        tokio::fs::write(Self::get_deleted_file_name(config, uuid), b"").await?;
+
+        Ok(())
+    }
+
+    /// Removes deleted backups that are no longer needed to rebuild any remaining backup
+    pub async fn collect_garbage(config: &crate::config::Config) -> Result<(), anyhow::Error> {
+        #[derive(Deserialize)]
+        struct ManifestChain {
+            chain: Vec<uuid::Uuid>,
+        }
+
+        let mut deleted = Vec::new();
+        let mut referenced = HashSet::new();
+
+        let mut directory = tokio::fs::read_dir(&config.system.backup_directory).await?;
+        while let Some(entry) = directory.next_entry().await? {
+            let uuid: uuid::Uuid = match entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".manifest.json"))
+                .and_then(|uuid| uuid.parse().ok())
+            {
+                Some(uuid) => uuid,
+                None => continue,
+            };
+
+            if Self::is_deleted(config, uuid).await {
+                deleted.push(uuid);
+                continue;
+            }
+
+            let data = tokio::fs::read(entry.path()).await?;
+            let manifest: ManifestChain = Self::parse(config, data).await?;
+            referenced.extend(manifest.chain);
+        }
+
+        for uuid in deleted {
+            if referenced.contains(&uuid) {
+                continue;
+            }
+
+            if let Ok((_, path)) = super::WingsBackup::get_first_file_name(config, uuid).await {
+                tokio::fs::remove_file(path).await?;
+            }
+            tokio::fs::remove_file(super::WingsBackup::get_checksum_file_name(config, uuid))
+                .await
+                .ok();
+            tokio::fs::remove_file(Self::get_file_name(config, uuid)).await?;
+            tokio::fs::remove_file(Self::get_deleted_file_name(config, uuid)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct CreateIncrementalOptions {
+    pub format: crate::config::SystemBackupsWingsArchiveFormat,
+    pub compression_level: CompressionLevel,
+    pub threads: usize,
+}
+
+/// Archives exactly the given paths, directories are added without their contents
+pub async fn create_archive(
+    filesystem: crate::server::filesystem::cap::CapFilesystem,
+    destination: impl Write + Send + 'static,
+    paths: Vec<PathBuf>,
+    bytes_archived: Arc<AtomicU64>,
+    options: CreateIncrementalOptions,
+) -> Result<(), anyhow::Error> {
+    let (_guard, listener) = AbortGuard::new();
+
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let compression_type = match options.format {
+            crate::config::SystemBackupsWingsArchiveFormat::Tar => CompressionType::None,
+            crate::config::SystemBackupsWingsArchiveFormat::TarGz => CompressionType::Gz,
+            crate::config::SystemBackupsWingsArchiveFormat::TarZstd => CompressionType::Zstd,
+            crate::config::SystemBackupsWingsArchiveFormat::Zip => {
+                let writer = AbortWriter::new(destination, listener);
+                let mut archive = zip::ZipWriter::new_stream(writer);
+
+                let mut read_buffer = vec![0; crate::BUFFER_SIZE];
+                for path in paths {
+                    let metadata = match filesystem.symlink_metadata(&path) {
+                        Ok(metadata) => metadata,
+                        Err(_) => continue,
+                    };
+
+                    let mut zip_options: zip::write::FileOptions<'_, ()> =
+                        zip::write::FileOptions::default()
+                            .compression_level(Some(
+                                options.compression_level.to_deflate_level() as i64
+                            ))
+                            .unix_permissions(metadata.permissions().mode())
+                            .large_file(true);
+
+                    if let Ok(mtime) = metadata.modified() {
+                        let mtime: chrono::DateTime<chrono::Utc> =
+                            chrono::DateTime::from(mtime.into_std());
+
+                        if let Ok(mtime) = zip::DateTime::from_date_and_time(
+                            mtime.year() as u16,
+                            mtime.month() as u8,
+                            mtime.day() as u8,
+                            mtime.hour() as u8,
+                            mtime.minute() as u8,
+                            mtime.second() as u8,
+                        ) {
+                            zip_options = zip_options.last_modified_time(mtime);
+                        }
+                    }
+
+                    if metadata.is_dir() {
+                        archive.add_directory(path.to_string_lossy(), zip_options)?;
+                    } else if metadata.is_file() {
+                        let file = filesystem.open(&path)?;
+                        let mut reader =
+                            CountingReader::new_with_bytes_read(file, Arc::clone(&bytes_archived));
+
+                        archive.start_file(path.to_string_lossy(), zip_options)?;
+                        crate::io::copy_shared(&mut read_buffer, &mut reader, &mut archive)?;
+                    } else if let Ok(link_target) = filesystem.read_link_contents(&path) {
+                        archive.add_symlink(
+                            path.to_string_lossy(),
+                            link_target.to_string_lossy(),
+                            zip_options,
+                        )?;
+                    }
+                }
+
+                archive.finish()?;
+
+                return Ok(());
+            }
+        };
+
+        let writer = CompressionWriter::new(
+            destination,
+            compression_type,
+            options.compression_level,
+            options.threads,
+        );
+        let writer = AbortWriter::new(writer, listener);
+        let mut archive = tar::Builder::new(writer);
+
+        for path in paths {
+            let metadata = match filesystem.symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_mode(metadata.permissions().mode());
+            header.set_mtime(
+                metadata
+                    .modified()
+                    .map(|t| {
+                        t.into_std()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                    })
+                    .unwrap_or_default()
+                    .as_secs(),
+            );
+
+            if metadata.is_dir() {
+                header.set_entry_type(tar::EntryType::Directory);
+
+                archive.append_data(&mut header, &path, std::io::empty())?;
+            } else if metadata.is_file() {
+                let file = filesystem.open(&path)?;
+                let reader = CountingReader::new_with_bytes_read(file, Arc::clone(&bytes_archived));
+                let reader = FixedReader::new_with_fixed_bytes(reader, metadata.len() as usize);
+
+                header.set_size(metadata.len());
+                header.set_entry_type(tar::EntryType::Regular);
+
+                archive.append_data(&mut header, &path, reader)?;
+            } else if let Ok(link_target) = filesystem.read_link_contents(&path) {
+                header.set_entry_type(tar::EntryType::Symlink);
+
+                if header.set_link_name(link_target).is_ok() {
+                    archive.append_data(&mut header, &path, std::io::empty())?;
+                }
+            }
+        }
+
+        let inner = archive.into_inner()?;
+        inner.into_inner().finish()?;
+
+        Ok(())
+    })
+    .await??;
+
+    Ok(())
+}
+
+/// The zip archives of an incremental chain, seen as the single archive of its last backup
+#[derive(Clone)]
+pub struct ChainZipArchive {
+    archives: Vec<zip::ZipArchive<BackupFile>>,
+    entries: Arc<Vec<(String, usize, usize)>>,
+    names: Arc<std::collections::HashMap<String, usize>>,
+}
+
+impl ChainZipArchive {
+    pub async fn open(
+        config: &Arc<crate::config::Config>,
+        manifest: Option<&BackupManifest>,
+        path: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        let mut archives = Vec::new();
+        match manifest {
+            Some(manifest) => {
+                for backup in manifest.chain.iter() {
+                    let (_, path) =
+                        super::WingsBackup::get_first_file_name(config, *backup).await?;
+
+                    archives.push(zip::ZipArchive::new(
+                        BackupFile::async_open(config, &path).await?,
+                    )?);
+                }
+            }
+            None => {
+                archives.push(zip::ZipArchive::new(
+                    BackupFile::async_open(config, path).await?,
+                )?);
+            }
+        }
+
+        let mut entries = Vec::new();
+        let mut names = std::collections::HashMap::new();
+
+        for (archive_index, archive) in archives.iter().enumerate() {
+            for i in 0..archive.len() {
+                let name = match archive.name_for_index(i) {
+                    Some(name) => name,
+                    None => continue,
+                };
+
+                if let Some(manifest) = manifest {
+                    match manifest.entries.get(name.trim_end_matches('/')) {
+                        Some(entry) if entry.backup == manifest.chain[archive_index] => {}
+                        _ => continue,
+                    }
+                }
+
+                names.insert(name.to_string(), entries.len());
+                entries.push((name.to_string(), archive_index, i));
+            }
+        }
+
+        Ok(Self {
+            archives,
+            entries: Arc::new(entries),
+            names: Arc::new(names),
+        })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(name, _, _)| name.as_str())
+    }
+
+    pub fn by_index(
+        &mut self,
+        index: usize,
+    ) -> zip::result::ZipResult<zip::read::ZipFile<'_, BackupFile>> {
+        let (_, archive_index, i) = self
+            .entries
+            .get(index)
+            .ok_or(zip::result::ZipError::FileNotFound)?;
+
+        self.archives[*archive_index].by_index(*i)
+    }
+
+    pub fn by_name(
+        &mut self,
+        name: &str,
+    ) -> zip::result::ZipResult<zip::read::ZipFile<'_, BackupFile>> {
+        let index = *self
+            .names
+            .get(name)
+            .ok_or(zip::result::ZipError::FileNotFound)?;
+
+        self.by_index(index)
+    }
+}
+
+#[inline]
+fn tar_compression_type(format: crate::config::SystemBackupsWingsArchiveFormat) -> CompressionType {
+    match format {
+        crate::config::SystemBackupsWingsArchiveFormat::TarGz => CompressionType::Gz,
+        crate::config::SystemBackupsWingsArchiveFormat::TarZstd => CompressionType::Zstd,
+        _ => CompressionType::None,
+    }
+}
+
+type ChainTarArchive = (
+    uuid::Uuid,
+    crate::config::SystemBackupsWingsArchiveFormat,
+    BackupFile,
+);
+
+async fn open_tar_chain(
+    config: &Arc<crate::config::Config>,
+    manifest: &BackupManifest,
+) -> Result<Vec<ChainTarArchive>, anyhow::Error> {
+    let mut archives = Vec::new();
+    for backup in manifest.chain.iter() {
+        let (format, path) = super::WingsBackup::get_first_file_name(config, *backup).await?;
+        archives.push((
+            *backup,
+            format,
+            BackupFile::async_open(config, &path).await?,
+        ));
+    }
+
+    Ok(archives)
+}
+
+/// Appends the entries of every archive of the chain that are owned by it according to the manifest
+fn append_tar_chain<W: Write>(
+    tar: &mut tar::Builder<W>,
+    manifest: &BackupManifest,
+    archives: Vec<ChainTarArchive>,
+) -> Result<(), anyhow::Error> {
+    for (backup, format, file) in archives {
+        let reader = crate::io::compression::reader::CompressionReader::new(
+            std::io::BufReader::new(file),
+            tar_compression_type(format),
+        );
+        let mut archive = tar::Archive::new(reader);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_path_buf();
+
+            match manifest
+                .entries
+                .get(path.to_string_lossy().trim_end_matches('/'))
+            {
+                Some(manifest_entry) if manifest_entry.backup == backup => {}
+                _ => continue,
+            }
+
+            let mut header = entry.header().clone();
+            match header.entry_type() {
+                tar::EntryType::Symlink => {
+                    if let Some(link_name) = entry.link_name()? {
+                        tar.append_link(&mut header, &path, link_name)?;
+                    }
+                }
+                _ => tar.append_data(&mut header, &path, &mut entry)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams every entry of a tar based incremental chain as one tar archive,
+/// the stream ends with an error if the archive could not be completed
+pub async fn read_tar_chain_archive(
+    config: &Arc<crate::config::Config>,
+    manifest: BackupManifest,
+    archive_format: crate::server::filesystem::archive::StreamableArchiveFormat,
+) -> Result<impl tokio::io::AsyncRead + Send + Unpin + 'static, anyhow::Error> {
+    let archives = open_tar_chain(config, &manifest).await?;
+
+    let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+    let writer = CompressionWriter::new(
+        tokio_util::io::SyncIoBridge::new(writer),
+        archive_format.compression_format(),
+        config.system.backups.compression_level,
+        config.api.file_compression_threads,
+    );
+
+    let task = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let mut tar = tar::Builder::new(writer);
+        tar.mode(tar::HeaderMode::Complete);
+
+        append_tar_chain(&mut tar, &manifest, archives)?;
+
+        tar.into_inner()?.finish()?;
+
+        Ok(())
+    });
+
+    let result = futures_util::stream::once(async move {
+        let err = match task.await {
+            Ok(Ok(())) => return None,
+            Ok(Err(err)) => err,
+            Err(err) => err.into(),
+        };
+
+        tracing::error!("failed to stream incremental backup chain: {:#?}", err);
+
+        Some(Err(std::io::Error::other(err)))
+    })
+    .filter_map(std::future::ready);
+
+    Ok(tokio_util::io::StreamReader::new(Box::pin(
+        tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE).chain(result),
+    )))
+}
+
+/// Writes every entry of an incremental chain into one standalone archive of the chain's format
+pub async fn write_full_archive(
+    config: &Arc<crate::config::Config>,
+    manifest: BackupManifest,
+    format: crate::config::SystemBackupsWingsArchiveFormat,
+    destination: &Path,
+    key: Option<BackupEncryptionKey>,
+) -> Result<(), anyhow::Error> {
+    let destination = destination.to_path_buf();
+
+    match format {
+        crate::config::SystemBackupsWingsArchiveFormat::Zip => {
+            let mut archive = ChainZipArchive::open(config, Some(&manifest), Path::new("")).await?;
+
+            tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                let file = std::fs::File::create(&destination)?;
+                let writer = BackupWriter::new(std::io::BufWriter::new(file), key)?;
+                let mut zip = zip::ZipWriter::new_stream(writer);
+
+                for i in 0..archive.len() {
+                    zip.raw_copy_file(archive.by_index(i)?)?;
+                }
+
+                let file = zip
+                    .finish()?
+                    .into_inner()
+                    .finish()?
+                    .into_inner()
+                    .map_err(|err| err.into_error())?;
+                file.sync_all()?;
+
+                Ok(())
+            })
+            .await?
+        }
+        _ => {
+            let archives = open_tar_chain(config, &manifest).await?;
+            let config = Arc::clone(config);
+
+            tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                let file = std::fs::File::create(&destination)?;
+                let writer = BackupWriter::new(std::io::BufWriter::new(file), key)?;
+                let compression_writer = CompressionWriter::new(
+                    writer.clone(),
+                    tar_compression_type(format),
+                    config.system.backups.compression_level,
+                    config.api.file_compression_threads,
+                );
+
+                let mut tar = tar::Builder::new(compression_writer);
+                tar.mode(tar::HeaderMode::Complete);
+
+                append_tar_chain(&mut tar, &manifest, archives)?;
+
+                tar.into_inner()?.finish()?;
+
+                let file = writer
+                    .finish()?
+                    .into_inner()
+                    .map_err(|err| err.into_error())?;
+                file.sync_all()?;
+
+                Ok(())
+            })
+            .await?
+        }
+    }
+}
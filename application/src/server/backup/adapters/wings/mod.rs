@@ -12,16 +12,16 @@ use crate::{
         backup::{
             Backup, BackupBrowseExt, BackupCleanExt, BackupCreateExt, BackupExt, BackupFindExt,
             BrowseBackup,
+            encryption::{self, BackupFile, BackupWriter},
         },
-        filesystem::archive::{
-            StreamableArchiveFormat, multi_reader::MultiReader, zip_entry_get_modified_time,
-        },
+        filesystem::archive::{StreamableArchiveFormat, zip_entry_get_modified_time},
     },
 };
 use axum::{body::Body, http::HeaderMap};
 use cap_std::fs::{Permissions, PermissionsExt};
 use sha1::Digest;
 use std::{
+    collections::HashSet,
     io::{Read, Seek, Write},
     path::{Path, PathBuf},
     sync::{
@@ -31,6 +31,8 @@ use std::{
 };
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+pub mod incremental;
+
 pub struct WingsBackup {
     uuid: uuid::Uuid,
     format: crate::config::SystemBackupsWingsArchiveFormat,
@@ -59,6 +61,11 @@ impl WingsBackup {
         Path::new(&config.system.backup_directory).join(format!("{uuid}.zip"))
     }
 
+    #[inline]
+    fn get_checksum_file_name(config: &crate::config::Config, uuid: uuid::Uuid) -> PathBuf {
+        Path::new(&config.system.backup_directory).join(format!("{uuid}.sha1"))
+    }
+
     #[inline]
     fn get_file_name(config: &crate::config::Config, uuid: uuid::Uuid) -> PathBuf {
         match config.system.backups.wings.archive_format {
@@ -118,19 +125,91 @@ impl WingsBackup {
     }
 }
 
+/// A backup file prepared for a server transfer, rebuilt archives are removed once dropped
+pub struct TransferBackupFile {
+    pub path: PathBuf,
+    pub file_name: String,
+
+    temporary: bool,
+}
+
+impl Drop for TransferBackupFile {
+    fn drop(&mut self) {
+        if self.temporary {
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+impl WingsBackup {
+    /// The file to send for a backup in a server transfer, incremental backups are rebuilt
+    /// into a full archive since the rest of their chain does not exist on the destination
+    pub async fn transfer_file(
+        server: &crate::server::Server,
+        uuid: uuid::Uuid,
+    ) -> Result<TransferBackupFile, anyhow::Error> {
+        let config = &server.app_state.config;
+
+        let (format, path) = Self::get_first_file_name(config, uuid).await?;
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let manifest = match incremental::BackupManifest::load(config, uuid)
+            .await?
+            .filter(|manifest| manifest.is_incremental())
+        {
+            Some(manifest) => manifest,
+            None => {
+                return Ok(TransferBackupFile {
+                    path,
+                    file_name,
+                    temporary: false,
+                });
+            }
+        };
+
+        let mut rebuilt_path = path.into_os_string();
+        rebuilt_path.push(".transfer");
+        let file = TransferBackupFile {
+            path: PathBuf::from(rebuilt_path),
+            file_name,
+            temporary: true,
+        };
+
+        incremental::write_full_archive(
+            config,
+            manifest,
+            format,
+            &file.path,
+            encryption::server_key(server).await,
+        )
+        .await?;
+
+        Ok(file)
+    }
+}
+
 #[async_trait::async_trait]
 impl BackupFindExt for WingsBackup {
     async fn exists(
         config: &Arc<crate::config::Config>,
         uuid: uuid::Uuid,
     ) -> Result<bool, anyhow::Error> {
-        Ok(Self::get_first_file_name(config, uuid).await.is_ok())
+        Ok(Self::get_first_file_name(config, uuid).await.is_ok()
+            && !incremental::BackupManifest::is_deleted(config, uuid).await)
     }
 
     async fn find(
         config: &Arc<crate::config::Config>,
         uuid: uuid::Uuid,
     ) -> Result<Option<Backup>, anyhow::Error> {
+        if incremental::BackupManifest::is_deleted(config, uuid).await {
+            return Ok(None);
+        }
+
         if let Ok((format, path)) = Self::get_first_file_name(config, uuid).await {
             Ok(Some(Backup::Wings(Self { uuid, format, path })))
         } else {
@@ -151,108 +230,212 @@ impl BackupCreateExt for WingsBackup {
     ) -> Result<RawServerBackup, anyhow::Error> {
         let file_name = Self::get_file_name(&server.app_state.config, uuid);
         let file = tokio::fs::File::create(&file_name).await?.into_std().await;
+        let writer = BackupWriter::new(file, encryption::server_key(server).await)?;
 
-        let total_task = {
-            let server = server.clone();
-            let ignore = ignore.clone();
-
-            async move {
-                let ignored = [ignore];
-
-                let mut walker = server
-                    .filesystem
-                    .async_walk_dir(Path::new(""))
-                    .await?
-                    .with_ignored(&ignored);
-                let mut total_files = 0;
-                while let Some(Ok((_, path))) = walker.next_entry().await {
-                    let metadata = match server.filesystem.async_symlink_metadata(&path).await {
-                        Ok(metadata) => metadata,
-                        Err(_) => continue,
-                    };
+        // the manifest is built before archiving, files modified while archiving will be picked up by the next backup
+        let manifest = if server
+            .app_state
+            .config
+            .system
+            .backups
+            .wings
+            .incremental
+            .enabled
+        {
+            let parent =
+                incremental::BackupManifest::parent(&server.app_state.config, server.uuid).await;
+            let filesystem = server.filesystem.clone();
+            let ignored = vec![ignore.clone()];
+            let server_uuid = server.uuid;
+            let format = server.app_state.config.system.backups.wings.archive_format;
+
+            Some(
+                tokio::task::spawn_blocking(move || {
+                    incremental::BackupManifest::build(
+                        filesystem,
+                        ignored,
+                        server_uuid,
+                        uuid,
+                        format,
+                        parent,
+                    )
+                })
+                .await??,
+            )
+        } else {
+            None
+        };
 
-                    total.fetch_add(metadata.len(), Ordering::Relaxed);
-                    if !metadata.is_dir() {
-                        total_files += 1;
-                    }
-                }
+        let total_files = match manifest {
+            Some((ref manifest, ref archived)) if manifest.is_incremental() => {
+                total.store(
+                    archived
+                        .iter()
+                        .filter_map(|path| manifest.entries.get(&*path.to_string_lossy()))
+                        .map(|entry| entry.size)
+                        .sum(),
+                    Ordering::Relaxed,
+                );
 
-                Ok::<_, anyhow::Error>(total_files)
+                incremental::create_archive(
+                    server.filesystem.clone(),
+                    LimitedWriter::new_with_bytes_per_second(
+                        writer.clone(),
+                        server.app_state.config.system.backups.write_limit * 1024 * 1024,
+                    ),
+                    archived.clone(),
+                    progress,
+                    incremental::CreateIncrementalOptions {
+                        format: server.app_state.config.system.backups.wings.archive_format,
+                        compression_level: server.app_state.config.system.backups.compression_level,
+                        threads: server.app_state.config.system.backups.wings.create_threads,
+                    },
+                )
+                .await?;
+
+                manifest.files()
             }
-        };
+            _ => {
+                let total_task = {
+                    let server = server.clone();
+                    let ignore = ignore.clone();
+
+                    async move {
+                        let ignored = [ignore];
+
+                        let mut walker = server
+                            .filesystem
+                            .async_walk_dir(Path::new(""))
+                            .await?
+                            .with_ignored(&ignored);
+                        let mut total_files = 0;
+                        while let Some(Ok((_, path))) = walker.next_entry().await {
+                            let metadata =
+                                match server.filesystem.async_symlink_metadata(&path).await {
+                                    Ok(metadata) => metadata,
+                                    Err(_) => continue,
+                                };
+
+                            total.fetch_add(metadata.len(), Ordering::Relaxed);
+                            if !metadata.is_dir() {
+                                total_files += 1;
+                            }
+                        }
 
-        let archive_task = async move {
-            let sources = server.filesystem.async_read_dir_all(Path::new("")).await?;
-            let writer = LimitedWriter::new_with_bytes_per_second(
-                file,
-                server.app_state.config.system.backups.write_limit * 1024 * 1024,
-            );
+                        Ok::<_, anyhow::Error>(total_files)
+                    }
+                };
 
-            match server.app_state.config.system.backups.wings.archive_format {
-                crate::config::SystemBackupsWingsArchiveFormat::Tar
-                | crate::config::SystemBackupsWingsArchiveFormat::TarGz
-                | crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
-                    crate::server::filesystem::archive::create::create_tar(
-                        server.filesystem.clone(),
-                        writer,
-                        Path::new(""),
-                        sources.into_iter().map(PathBuf::from).collect(),
-                        Some(progress),
-                        vec![ignore],
-                        crate::server::filesystem::archive::create::CreateTarOptions {
-                            compression_type: match server
-                                .app_state
-                                .config
-                                .system
-                                .backups
-                                .wings
-                                .archive_format
-                            {
-                                crate::config::SystemBackupsWingsArchiveFormat::Tar => {
-                                    CompressionType::None
-                                }
-                                crate::config::SystemBackupsWingsArchiveFormat::TarGz => {
-                                    CompressionType::Gz
-                                }
-                                crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
-                                    CompressionType::Zstd
-                                }
-                                _ => unreachable!(),
-                            },
-                            compression_level: server
-                                .app_state
-                                .config
-                                .system
-                                .backups
-                                .compression_level,
-                            threads: server.app_state.config.system.backups.wings.create_threads,
-                        },
-                    )
-                    .await
-                }
-                crate::config::SystemBackupsWingsArchiveFormat::Zip => {
-                    crate::server::filesystem::archive::create::create_zip(
-                        server.filesystem.clone(),
-                        writer,
-                        Path::new(""),
-                        sources.into_iter().map(PathBuf::from).collect(),
-                        Some(progress),
-                        vec![ignore],
-                        crate::server::filesystem::archive::create::CreateZipOptions {
-                            compression_level: server
-                                .app_state
-                                .config
-                                .system
-                                .backups
-                                .compression_level,
-                        },
-                    )
-                    .await
-                }
+                let archive_task = async {
+                    let sources = server.filesystem.async_read_dir_all(Path::new("")).await?;
+                    let encrypted = writer.is_encrypted();
+                    let writer = LimitedWriter::new_with_bytes_per_second(
+                        writer.clone(),
+                        server.app_state.config.system.backups.write_limit * 1024 * 1024,
+                    );
+
+                    match server.app_state.config.system.backups.wings.archive_format {
+                        crate::config::SystemBackupsWingsArchiveFormat::Tar
+                        | crate::config::SystemBackupsWingsArchiveFormat::TarGz
+                        | crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
+                            crate::server::filesystem::archive::create::create_tar(
+                                server.filesystem.clone(),
+                                writer,
+                                Path::new(""),
+                                sources.into_iter().map(PathBuf::from).collect(),
+                                Some(progress),
+                                vec![ignore],
+                                crate::server::filesystem::archive::create::CreateTarOptions {
+                                    compression_type: match server
+                                        .app_state
+                                        .config
+                                        .system
+                                        .backups
+                                        .wings
+                                        .archive_format
+                                    {
+                                        crate::config::SystemBackupsWingsArchiveFormat::Tar => {
+                                            CompressionType::None
+                                        }
+                                        crate::config::SystemBackupsWingsArchiveFormat::TarGz => {
+                                            CompressionType::Gz
+                                        }
+                                        crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
+                                            CompressionType::Zstd
+                                        }
+                                        _ => unreachable!(),
+                                    },
+                                    compression_level: server
+                                        .app_state
+                                        .config
+                                        .system
+                                        .backups
+                                        .compression_level,
+                                    threads: server
+                                        .app_state
+                                        .config
+                                        .system
+                                        .backups
+                                        .wings
+                                        .create_threads,
+                                },
+                            )
+                            .await
+                        }
+                        crate::config::SystemBackupsWingsArchiveFormat::Zip => {
+                            let options =
+                                crate::server::filesystem::archive::create::CreateZipOptions {
+                                    compression_level: server
+                                        .app_state
+                                        .config
+                                        .system
+                                        .backups
+                                        .compression_level,
+                                };
+
+                            if encrypted {
+                                crate::server::filesystem::archive::create::create_zip_streaming(
+                                    server.filesystem.clone(),
+                                    writer,
+                                    Path::new(""),
+                                    sources.into_iter().map(PathBuf::from).collect(),
+                                    Some(progress),
+                                    vec![ignore],
+                                    options,
+                                )
+                                .await
+                            } else {
+                                crate::server::filesystem::archive::create::create_zip(
+                                    server.filesystem.clone(),
+                                    writer,
+                                    Path::new(""),
+                                    sources.into_iter().map(PathBuf::from).collect(),
+                                    Some(progress),
+                                    vec![ignore],
+                                    options,
+                                )
+                                .await
+                            }
+                        }
+                    }
+                };
+
+                let (total_files, _) = tokio::try_join!(total_task, archive_task)?;
+
+                total_files
             }
         };
 
-        let (total_files, _) = tokio::try_join!(total_task, archive_task)?;
+        tokio::task::spawn_blocking(move || writer.finish()).await??;
+
+        if let Some((manifest, _)) = manifest {
+            manifest
+                .save(
+                    &server.app_state.config,
+                    encryption::server_key(server).await,
+                )
+                .await?;
+        }
 
         let mut checksum_writer = sha1::Sha1::new();
         let mut file = tokio::fs::File::open(&file_name).await?;
@@ -265,8 +448,15 @@ impl BackupCreateExt for WingsBackup {
             }
         }
 
+        let checksum = format!("{:x}", checksum_writer.finalize());
+        tokio::fs::write(
+            Self::get_checksum_file_name(&server.app_state.config, uuid),
+            &checksum,
+        )
+        .await?;
+
         Ok(RawServerBackup {
-            checksum: format!("{:x}", checksum_writer.finalize()),
+            checksum,
             checksum_type: "sha1".to_string(),
             size: tokio::fs::metadata(file_name).await?.len(),
             files: total_files,
@@ -276,74 +466,87 @@ impl BackupCreateExt for WingsBackup {
     }
 }
 
-#[async_trait::async_trait]
-impl BackupExt for WingsBackup {
-    #[inline]
-    fn uuid(&self) -> uuid::Uuid {
-        self.uuid
-    }
-
-    async fn download(
-        &self,
-        _config: &Arc<crate::config::Config>,
-        _archive_format: StreamableArchiveFormat,
-    ) -> Result<ApiResponse, anyhow::Error> {
-        let file = tokio::fs::File::open(&self.path).await?;
-
-        let mut headers = HeaderMap::with_capacity(3);
+impl WingsBackup {
+    /// The extension and mime type of the stored file
+    fn file_type(&self) -> (&'static str, &'static str) {
         match self.format {
-            crate::config::SystemBackupsWingsArchiveFormat::Tar => {
-                headers.insert(
-                    "Content-Disposition",
-                    format!("attachment; filename={}.tar", self.uuid).parse()?,
-                );
-                headers.insert("Content-Type", "application/x-tar".parse()?);
-            }
-            crate::config::SystemBackupsWingsArchiveFormat::TarGz => {
-                headers.insert(
-                    "Content-Disposition",
-                    format!("attachment; filename={}.tar.gz", self.uuid).parse()?,
-                );
-                headers.insert("Content-Type", "application/gzip".parse()?);
-            }
+            crate::config::SystemBackupsWingsArchiveFormat::Tar => ("tar", "application/x-tar"),
+            crate::config::SystemBackupsWingsArchiveFormat::TarGz => ("tar.gz", "application/gzip"),
             crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
-                headers.insert(
-                    "Content-Disposition",
-                    format!("attachment; filename={}.tar.zst", self.uuid).parse()?,
-                );
-                headers.insert("Content-Type", "application/zstd".parse()?);
+                ("tar.zst", "application/zstd")
             }
+            crate::config::SystemBackupsWingsArchiveFormat::Zip => ("zip", "application/zip"),
+        }
+    }
+
+    /// Reads every entry of a single archive, returning the names it contains
+    fn verify_archive(
+        config: &crate::config::Config,
+        format: crate::config::SystemBackupsWingsArchiveFormat,
+        path: &Path,
+        progress: &Arc<AtomicU64>,
+    ) -> Result<HashSet<String>, anyhow::Error> {
+        let file = BackupFile::open(config, path)?;
+        let mut names = HashSet::new();
+
+        match format {
             crate::config::SystemBackupsWingsArchiveFormat::Zip => {
-                headers.insert(
-                    "Content-Disposition",
-                    format!("attachment; filename={}.zip", self.uuid).parse()?,
-                );
-                headers.insert("Content-Type", "application/zip".parse()?);
-            }
-        };
+                let mut archive = zip::ZipArchive::new(file)?;
 
-        headers.insert("Content-Length", file.metadata().await?.len().into());
+                for i in 0..archive.len() {
+                    let mut entry = archive.by_index(i)?;
+                    progress.fetch_add(entry.compressed_size(), Ordering::Relaxed);
 
-        Ok(ApiResponse::new(Body::from_stream(
-            tokio_util::io::ReaderStream::with_capacity(file, crate::BUFFER_SIZE),
-        ))
-        .with_headers(headers))
+                    std::io::copy(&mut entry, &mut std::io::sink())?;
+                    names.insert(entry.name().trim_end_matches('/').to_string());
+                }
+            }
+            _ => {
+                let compression_type = match format {
+                    crate::config::SystemBackupsWingsArchiveFormat::TarGz => CompressionType::Gz,
+                    crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
+                        CompressionType::Zstd
+                    }
+                    _ => CompressionType::None,
+                };
+                let reader = CountingReader::new_with_bytes_read(file, Arc::clone(progress));
+                let reader =
+                    CompressionReader::new(std::io::BufReader::new(reader), compression_type);
+                let mut archive = tar::Archive::new(reader);
+
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    let name = entry
+                        .path()?
+                        .to_string_lossy()
+                        .trim_end_matches('/')
+                        .to_string();
+
+                    std::io::copy(&mut entry, &mut std::io::sink())?;
+                    names.insert(name);
+                }
+            }
+        }
+
+        Ok(names)
     }
 
-    async fn restore(
-        &self,
+    /// Extracts a single archive, if a filter is given only the listed paths are restored
+    async fn restore_archive(
         server: &crate::server::Server,
+        path: &Path,
+        format: crate::config::SystemBackupsWingsArchiveFormat,
         progress: Arc<AtomicU64>,
         total: Arc<AtomicU64>,
-        _download_url: Option<String>,
+        filter: Option<Arc<HashSet<PathBuf>>>,
     ) -> Result<(), anyhow::Error> {
-        let file = tokio::fs::File::open(&self.path).await?.into_std().await;
+        let file = BackupFile::async_open(&server.app_state.config, path).await?;
 
-        match self.format {
+        match format {
             crate::config::SystemBackupsWingsArchiveFormat::Tar
             | crate::config::SystemBackupsWingsArchiveFormat::TarGz
             | crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
-                let compression_type = match self.format {
+                let compression_type = match format {
                     crate::config::SystemBackupsWingsArchiveFormat::Tar => CompressionType::None,
                     crate::config::SystemBackupsWingsArchiveFormat::TarGz => CompressionType::Gz,
                     crate::config::SystemBackupsWingsArchiveFormat::TarZstd => {
@@ -355,7 +558,7 @@ impl BackupExt for WingsBackup {
                 let server = server.clone();
 
                 tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    total.store(file.metadata()?.len(), Ordering::SeqCst);
+                    total.fetch_add(file.len(), Ordering::SeqCst);
 
                     let reader = LimitedReader::new_with_bytes_per_second(
                         file,
@@ -376,6 +579,12 @@ impl BackupExt for WingsBackup {
                             continue;
                         }
 
+                        if let Some(filter) = &filter
+                            && !filter.contains(path.as_ref())
+                        {
+                            continue;
+                        }
+
                         let destination_path = path.as_ref();
                         let header = entry.header();
 
@@ -462,14 +671,20 @@ impl BackupExt for WingsBackup {
                 let server = server.clone();
 
                 tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    let reader = MultiReader::new(Arc::new(file))?;
-                    let mut archive = zip::ZipArchive::new(reader)?;
+                    let mut archive = zip::ZipArchive::new(file)?;
                     let entry_index = Arc::new(AtomicUsize::new(0));
 
                     for i in 0..archive.len() {
                         let entry = archive.by_index(i)?;
 
-                        if entry.enclosed_name().is_none() {
+                        let path = match entry.enclosed_name() {
+                            Some(path) => path,
+                            None => continue,
+                        };
+
+                        if let Some(filter) = &filter
+                            && !filter.contains(&path)
+                        {
                             continue;
                         }
 
@@ -485,6 +700,7 @@ impl BackupExt for WingsBackup {
                     pool.in_place_scope(|scope| {
                         let archive = archive.clone();
                         let server = server.clone();
+                        let filter = filter.clone();
                         let error_clone = Arc::clone(&error);
 
                         scope.spawn_broadcast(move |_, _| {
@@ -494,6 +710,7 @@ impl BackupExt for WingsBackup {
                             let entry_index = Arc::clone(&entry_index);
                             let error_clone2 = Arc::clone(&error_clone);
                             let server = server.clone();
+                            let filter = filter.clone();
 
                             let mut run = move || -> Result<(), anyhow::Error> {
                                 let mut read_buffer = vec![0; crate::BUFFER_SIZE];
@@ -519,6 +736,12 @@ impl BackupExt for WingsBackup {
                                         continue;
                                     }
 
+                                    if let Some(filter) = &filter
+                                        && !filter.contains(&path)
+                                    {
+                                        continue;
+                                    }
+
                                     if entry.is_dir() {
                                         server.filesystem.create_dir_all(&path)?;
                                         server.filesystem.set_permissions(
@@ -599,6 +822,12 @@ impl BackupExt for WingsBackup {
                                 continue;
                             }
 
+                            if let Some(filter) = &filter
+                                && !filter.contains(&path)
+                            {
+                                continue;
+                            }
+
                             if server
                                 .filesystem
                                 .is_ignored_sync(&path, entry.is_dir())
@@ -628,9 +857,259 @@ impl BackupExt for WingsBackup {
 
         Ok(())
     }
+}
+
+#[async_trait::async_trait]
+impl BackupExt for WingsBackup {
+    #[inline]
+    fn uuid(&self) -> uuid::Uuid {
+        self.uuid
+    }
+
+    async fn download(
+        &self,
+        config: &Arc<crate::config::Config>,
+        archive_format: StreamableArchiveFormat,
+    ) -> Result<ApiResponse, anyhow::Error> {
+        if let Some(manifest) = incremental::BackupManifest::load(config, self.uuid)
+            .await?
+            .filter(|manifest| manifest.is_incremental())
+        {
+            let (archive_format, reader): (_, Box<dyn tokio::io::AsyncRead + Send + Unpin>) =
+                match self.format {
+                    crate::config::SystemBackupsWingsArchiveFormat::Zip => {
+                        let archive =
+                            incremental::ChainZipArchive::open(config, Some(&manifest), &self.path)
+                                .await?;
+                        let browse = BrowseWingsBackup {
+                            config: Arc::clone(config),
+                            archive,
+                        };
+
+                        (
+                            archive_format,
+                            Box::new(
+                                browse
+                                    .read_directory_archive(PathBuf::new(), archive_format)
+                                    .await?,
+                            ),
+                        )
+                    }
+                    _ => {
+                        let archive_format = match archive_format {
+                            StreamableArchiveFormat::Zip => StreamableArchiveFormat::Tar,
+                            archive_format => archive_format,
+                        };
+
+                        (
+                            archive_format,
+                            Box::new(
+                                incremental::read_tar_chain_archive(
+                                    config,
+                                    manifest,
+                                    archive_format,
+                                )
+                                .await?,
+                            ),
+                        )
+                    }
+                };
+
+            let mut headers = HeaderMap::with_capacity(2);
+            headers.insert(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename={}.{}",
+                    self.uuid,
+                    archive_format.extension()
+                )
+                .parse()?,
+            );
+            headers.insert("Content-Type", archive_format.mime_type().parse()?);
+
+            return Ok(ApiResponse::new(Body::from_stream(
+                tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+            ))
+            .with_headers(headers));
+        }
+
+        let file = BackupFile::async_open(config, &self.path).await?;
+        let content_length = file.len();
+
+        let (extension, content_type) = self.file_type();
+        let mut headers = HeaderMap::with_capacity(3);
+        headers.insert(
+            "Content-Disposition",
+            format!("attachment; filename={}.{extension}", self.uuid).parse()?,
+        );
+        headers.insert("Content-Type", content_type.parse()?);
+
+        headers.insert("Content-Length", content_length.into());
+
+        if !file.is_encrypted() {
+            let file = tokio::fs::File::open(&self.path).await?;
+
+            return Ok(ApiResponse::new(Body::from_stream(
+                tokio_util::io::ReaderStream::with_capacity(file, crate::BUFFER_SIZE),
+            ))
+            .with_headers(headers));
+        }
+
+        let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+        tokio::task::spawn_blocking(move || {
+            let mut file = file;
+            let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+
+            if let Err(err) = crate::io::copy(&mut file, &mut writer) {
+                tracing::error!("failed to stream decrypted backup: {:#?}", err);
+            }
+        });
+
+        Ok(ApiResponse::new(Body::from_stream(
+            tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
+        ))
+        .with_headers(headers))
+    }
+
+    async fn download_file(
+        &self,
+        config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        if incremental::BackupManifest::load(config, self.uuid)
+            .await?
+            .is_some_and(|manifest| manifest.is_incremental())
+        {
+            return Ok(None);
+        }
+
+        let (extension, content_type) = self.file_type();
+
+        Ok(Some(crate::server::backup::BackupDownloadFile {
+            file: BackupFile::async_open(config, &self.path).await?,
+            file_name: format!("{}.{extension}", self.uuid),
+            content_type,
+            modified: tokio::fs::metadata(&self.path).await?.modified().ok(),
+            checksum: tokio::fs::read_to_string(Self::get_checksum_file_name(config, self.uuid))
+                .await
+                .ok()
+                .map(|checksum| checksum.trim().to_string()),
+        }))
+    }
+
+    async fn restore(
+        &self,
+        server: &crate::server::Server,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        match incremental::BackupManifest::load(&server.app_state.config, self.uuid).await? {
+            Some(manifest) if manifest.is_incremental() => {
+                for backup in manifest.chain.iter() {
+                    let owned = manifest.owned_by(*backup);
+                    if owned.is_empty() {
+                        continue;
+                    }
+
+                    let (format, path) =
+                        Self::get_first_file_name(&server.app_state.config, *backup).await?;
+
+                    Self::restore_archive(
+                        server,
+                        &path,
+                        format,
+                        Arc::clone(&progress),
+                        Arc::clone(&total),
+                        Some(Arc::new(owned)),
+                    )
+                    .await?;
+                }
+
+                Ok(())
+            }
+            _ => {
+                Self::restore_archive(server, &self.path, self.format, progress, total, None).await
+            }
+        }
+    }
+
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let manifest = incremental::BackupManifest::load(config, self.uuid)
+            .await?
+            .filter(|manifest| manifest.is_incremental());
+
+        let mut archives = Vec::new();
+        match &manifest {
+            Some(manifest) => {
+                for backup in manifest.chain.iter() {
+                    let (format, path) = Self::get_first_file_name(config, *backup).await?;
+                    archives.push((*backup, format, path));
+                }
+            }
+            None => archives.push((self.uuid, self.format, self.path.clone())),
+        }
+
+        let mut total_size = tokio::fs::metadata(&self.path).await?.len();
+        for (_, _, path) in archives.iter() {
+            total_size += tokio::fs::metadata(path).await?.len();
+        }
+        total.store(total_size, Ordering::SeqCst);
+
+        let mut checksum_writer = sha1::Sha1::new();
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        let mut buffer = vec![0; crate::BUFFER_SIZE];
+
+        loop {
+            match file.read(&mut buffer).await? {
+                0 => break,
+                bytes_read => {
+                    checksum_writer.write_all(&buffer[..bytes_read])?;
+                    progress.fetch_add(bytes_read as u64, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let config = Arc::clone(config);
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            for (backup, format, path) in archives {
+                let names = Self::verify_archive(&config, format, &path, &progress)?;
+
+                if let Some(manifest) = &manifest
+                    && let Some((missing, _)) = manifest
+                        .entries
+                        .iter()
+                        .find(|(name, entry)| entry.backup == backup && !names.contains(*name))
+                {
+                    return Err(anyhow::anyhow!(
+                        "backup {backup} of the chain is missing {missing}"
+                    ));
+                }
+            }
+
+            Ok(())
+        })
+        .await??;
+
+        Ok(Some(format!("{:x}", checksum_writer.finalize())))
+    }
+
+    async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
+        if incremental::BackupManifest::exists(config, self.uuid).await {
+            incremental::BackupManifest::mark_deleted(config, self.uuid).await?;
+
+            return incremental::BackupManifest::collect_garbage(config).await;
+        }
 
-    async fn delete(&self, _config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         tokio::fs::remove_file(&self.path).await?;
+        tokio::fs::remove_file(Self::get_checksum_file_name(config, self.uuid))
+            .await
+            .ok();
 
         Ok(())
     }
@@ -638,12 +1117,19 @@ impl BackupExt for WingsBackup {
     async fn browse(&self, server: &crate::server::Server) -> Result<BrowseBackup, anyhow::Error> {
         match self.format {
             crate::config::SystemBackupsWingsArchiveFormat::Zip => {
-                let archive = zip::ZipArchive::new(Arc::new(
-                    tokio::fs::File::open(&self.path).await?.into_std().await,
-                ))?;
+                let manifest =
+                    incremental::BackupManifest::load(&server.app_state.config, self.uuid)
+                        .await?
+                        .filter(|manifest| manifest.is_incremental());
+                let archive = incremental::ChainZipArchive::open(
+                    &server.app_state.config,
+                    manifest.as_ref(),
+                    &self.path,
+                )
+                .await?;
 
                 Ok(BrowseBackup::Wings(BrowseWingsBackup {
-                    server: server.clone(),
+                    config: Arc::clone(&server.app_state.config),
                     archive,
                 }))
             }
@@ -669,8 +1155,8 @@ impl BackupCleanExt for WingsBackup {
 }
 
 pub struct BrowseWingsBackup {
-    server: crate::server::Server,
-    archive: zip::ZipArchive<Arc<std::fs::File>>,
+    config: Arc<crate::config::Config>,
+    archive: incremental::ChainZipArchive,
 }
 
 impl BrowseWingsBackup {
@@ -915,13 +1401,7 @@ impl BackupBrowseExt for BrowseWingsBackup {
         let mut archive = self.archive.clone();
 
         let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
-        let compression_level = self
-            .server
-            .app_state
-            .config
-            .system
-            .backups
-            .compression_level;
+        let compression_level = self.config.system.backups.compression_level;
 
         match archive_format {
             StreamableArchiveFormat::Zip => {
@@ -963,7 +1443,7 @@ impl BackupBrowseExt for BrowseWingsBackup {
                     tokio_util::io::SyncIoBridge::new(writer),
                     archive_format.compression_format(),
                     compression_level,
-                    self.server.app_state.config.api.file_compression_threads,
+                    self.config.api.file_compression_threads,
                 );
 
                 tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
@@ -1030,13 +1510,7 @@ impl BackupBrowseExt for BrowseWingsBackup {
         let mut archive = self.archive.clone();
 
         let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
-        let compression_level = self
-            .server
-            .app_state
-            .config
-            .system
-            .backups
-            .compression_level;
+        let compression_level = self.config.system.backups.compression_level;
 
         match archive_format {
             StreamableArchiveFormat::Zip => {
@@ -1082,7 +1556,7 @@ impl BackupBrowseExt for BrowseWingsBackup {
                     tokio_util::io::SyncIoBridge::new(writer),
                     archive_format.compression_format(),
                     compression_level,
-                    self.server.app_state.config.api.file_compression_threads,
+                    self.config.api.file_compression_threads,
                 );
 
                 tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
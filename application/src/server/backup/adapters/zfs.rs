@@ -305,6 +305,13 @@ impl BackupExt for ZfsBackup {
         .with_headers(headers))
     }
 
+    async fn download_file(
+        &self,
+        _config: &Arc<crate::config::Config>,
+    ) -> Result<Option<crate::server::backup::BackupDownloadFile>, anyhow::Error> {
+        Ok(None)
+    }
+
     async fn restore(
         &self,
         server: &crate::server::Server,
@@ -440,6 +447,30 @@ impl BackupExt for ZfsBackup {
         Ok(())
     }
 
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        _download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let snapshot_path = Self::get_snapshot_path(config, self.server_uuid, self.uuid);
+
+        if tokio::fs::metadata(&snapshot_path).await.is_err() {
+            return Err(anyhow::anyhow!(
+                "zfs backup subvolume does not exist: {}",
+                snapshot_path.display()
+            ));
+        }
+
+        let filesystem = crate::server::filesystem::cap::CapFilesystem::new(snapshot_path).await?;
+        let ignore = Self::get_ignore(config, self.uuid).await?;
+
+        super::verify_snapshot(filesystem, ignore, progress, total).await?;
+
+        Ok(None)
+    }
+
     async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         let backup_path = Self::get_backup_path(config, self.uuid);
         let dataset_path = Self::get_dataset_path(config, self.uuid);
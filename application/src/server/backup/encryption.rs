@@ -0,0 +1,340 @@
+use crate::{
+    io::encryption::{
+        DecryptionReader, EncryptedFile, EncryptionHeader, EncryptionWriter, HEADER_SIZE,
+    },
+    server::filesystem::archive::multi_reader::MultiReader,
+};
+use ddup_bak::chunks::{ChunkHash, storage::ChunkStorage};
+use std::{
+    collections::HashMap,
+    io::{Read, Seek, SeekFrom, Write},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, RwLock},
+};
+
+pub type BackupEncryptionKey = (uuid::Uuid, Arc<[u8]>);
+
+static SERVER_KEYS: LazyLock<RwLock<HashMap<uuid::Uuid, Arc<[u8]>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Remembers the key the panel sent for a server, backups only know the uuid of their key
+pub fn set_server_key(server: uuid::Uuid, key: Option<&str>) {
+    let mut keys = SERVER_KEYS.write().unwrap();
+
+    match key {
+        Some(key) if !key.is_empty() => {
+            keys.insert(server, Arc::from(key.as_bytes()));
+        }
+        _ => {
+            keys.remove(&server);
+        }
+    }
+}
+
+fn resolve_key(node_key: &str, key_id: uuid::Uuid) -> std::io::Result<Arc<[u8]>> {
+    if key_id.is_nil() {
+        if node_key.is_empty() {
+            return Err(std::io::Error::other(
+                "backup is encrypted with the node key, but no key is configured",
+            ));
+        }
+
+        return Ok(Arc::from(node_key.as_bytes()));
+    }
+
+    SERVER_KEYS
+        .read()
+        .unwrap()
+        .get(&key_id)
+        .cloned()
+        .ok_or_else(|| {
+            std::io::Error::other(format!(
+                "backup is encrypted with the key of server {key_id}, which is not known to this node"
+            ))
+        })
+}
+
+#[inline]
+pub fn node_key(config: &crate::config::Config) -> Option<BackupEncryptionKey> {
+    let encryption = &config.system.backups.encryption;
+    if !encryption.enabled || encryption.key.is_empty() {
+        return None;
+    }
+
+    Some((uuid::Uuid::nil(), Arc::from(encryption.key.as_bytes())))
+}
+
+/// The key new backups of this server are encrypted with, `None` if they are stored in plaintext
+pub async fn server_key(server: &crate::server::Server) -> Option<BackupEncryptionKey> {
+    if let Some(key) = server
+        .configuration
+        .read()
+        .await
+        .backup_encryption_key
+        .as_deref()
+        .filter(|key| !key.is_empty())
+    {
+        return Some((server.uuid, Arc::from(key.as_bytes())));
+    }
+
+    node_key(&server.app_state.config)
+}
+
+enum BackupWriterInner<W: Write> {
+    Plain(W),
+    Encrypted(EncryptionWriter<W>),
+}
+
+/// A backup file being written, encrypted on the fly if a key is given. Clones share the same
+/// stream, so one can be handed to an archiver that consumes it and the other finished afterwards
+pub struct BackupWriter<W: Write> {
+    inner: Arc<Mutex<Option<BackupWriterInner<W>>>>,
+    encrypted: bool,
+}
+
+impl<W: Write> BackupWriter<W> {
+    pub fn new(inner: W, key: Option<BackupEncryptionKey>) -> std::io::Result<Self> {
+        let (inner, encrypted) = match key {
+            Some((key_id, key)) => (
+                BackupWriterInner::Encrypted(EncryptionWriter::new(inner, key_id, &key)?),
+                true,
+            ),
+            None => (BackupWriterInner::Plain(inner), false),
+        };
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Some(inner))),
+            encrypted,
+        })
+    }
+
+    /// Encrypted streams can not be seeked, archivers have to write them sequentially
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// Writes the final encrypted segment, has to be called once the archive is complete
+    pub fn finish(self) -> std::io::Result<W> {
+        match self.inner.lock().unwrap().take() {
+            Some(BackupWriterInner::Plain(mut inner)) => {
+                inner.flush()?;
+
+                Ok(inner)
+            }
+            Some(BackupWriterInner::Encrypted(inner)) => inner.finish(),
+            None => Err(std::io::Error::other("backup writer is already finished")),
+        }
+    }
+}
+
+impl<W: Write> Clone for BackupWriter<W> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            encrypted: self.encrypted,
+        }
+    }
+}
+
+impl<W: Write> Write for BackupWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.inner.lock().unwrap().as_mut() {
+            Some(BackupWriterInner::Plain(inner)) => inner.write(buf),
+            Some(BackupWriterInner::Encrypted(inner)) => inner.write(buf),
+            None => Err(std::io::Error::other("backup writer is already finished")),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.inner.lock().unwrap().as_mut() {
+            Some(BackupWriterInner::Plain(inner)) => inner.flush(),
+            Some(BackupWriterInner::Encrypted(inner)) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write + Seek> Seek for BackupWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self.inner.lock().unwrap().as_mut() {
+            Some(BackupWriterInner::Plain(inner)) => inner.seek(pos),
+            Some(BackupWriterInner::Encrypted(_)) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "encrypted backups can not be seeked while writing",
+            )),
+            None => Err(std::io::Error::other("backup writer is already finished")),
+        }
+    }
+}
+
+/// A stored backup file, decrypted on the fly if it is encrypted, clones do not share the position
+#[derive(Clone)]
+pub enum BackupFile {
+    Plain(MultiReader),
+    Encrypted(Box<EncryptedFile>),
+}
+
+impl BackupFile {
+    pub fn open(config: &crate::config::Config, path: &Path) -> Result<Self, anyhow::Error> {
+        let file = std::fs::File::open(path)?;
+
+        let mut header = [0; HEADER_SIZE];
+        let header = match file.read_exact_at(&mut header, 0) {
+            Ok(()) => EncryptionHeader::parse(&header),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(match header {
+            Some(header) => {
+                let key = resolve_key(&config.system.backups.encryption.key, header.key_id)?;
+
+                Self::Encrypted(Box::new(EncryptedFile::new(Arc::new(file), header, &key)?))
+            }
+            None => Self::Plain(MultiReader::new(Arc::new(file))?),
+        })
+    }
+
+    pub async fn async_open(
+        config: &Arc<crate::config::Config>,
+        path: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        let config = Arc::clone(config);
+        let path = path.to_path_buf();
+
+        tokio::task::spawn_blocking(move || Self::open(&config, &path)).await?
+    }
+
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, Self::Encrypted(_))
+    }
+
+    /// Size of the decrypted contents
+    #[inline]
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Plain(reader) => reader.len(),
+            Self::Encrypted(file) => file.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Read for BackupFile {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(reader) => reader.read(buf),
+            Self::Encrypted(file) => file.read(buf),
+        }
+    }
+}
+
+impl Seek for BackupFile {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::Plain(reader) => reader.seek(pos),
+            Self::Encrypted(file) => file.seek(pos),
+        }
+    }
+}
+
+/// A backup stream, decrypted on the fly if it starts with an encryption header
+pub enum BackupReader<R: Read> {
+    Plain(std::io::Chain<std::io::Cursor<Vec<u8>>, R>),
+    Encrypted(Box<DecryptionReader<R>>),
+}
+
+impl<R: Read> BackupReader<R> {
+    #[inline]
+    pub fn new(config: &crate::config::Config, reader: R) -> std::io::Result<Self> {
+        Self::with_node_key(&config.system.backups.encryption.key, reader)
+    }
+
+    pub fn with_node_key(node_key: &str, mut reader: R) -> std::io::Result<Self> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        reader
+            .by_ref()
+            .take(HEADER_SIZE as u64)
+            .read_to_end(&mut header)?;
+
+        Ok(match EncryptionHeader::parse(&header) {
+            Some(header) => {
+                let key = resolve_key(node_key, header.key_id)?;
+
+                Self::Encrypted(Box::new(DecryptionReader::new(reader, header, &key)))
+            }
+            None => Self::Plain(std::io::Cursor::new(header).chain(reader)),
+        })
+    }
+}
+
+impl<R: Read> Read for BackupReader<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(reader) => reader.read(buf),
+            Self::Encrypted(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Chunks of the ddup_bak repository are shared between servers, so only the node key is used
+pub struct EncryptedChunkStorage {
+    inner: ddup_bak::chunks::storage::ChunkStorageLocal,
+    node_key: String,
+    encrypt: bool,
+}
+
+impl EncryptedChunkStorage {
+    pub fn new(config: &crate::config::Config, path: PathBuf) -> Self {
+        Self {
+            inner: ddup_bak::chunks::storage::ChunkStorageLocal(path),
+            node_key: config.system.backups.encryption.key.clone(),
+            encrypt: node_key(config).is_some(),
+        }
+    }
+}
+
+impl ChunkStorage for EncryptedChunkStorage {
+    fn read_chunk_content(&self, chunk: &ChunkHash) -> std::io::Result<Box<dyn Read + Send>> {
+        let reader = self.inner.read_chunk_content(chunk)?;
+
+        Ok(Box::new(BackupReader::with_node_key(
+            &self.node_key,
+            reader,
+        )?))
+    }
+
+    fn write_chunk_content(
+        &self,
+        chunk: &ChunkHash,
+        mut content: Box<dyn Read + Send>,
+    ) -> std::io::Result<()> {
+        if !self.encrypt {
+            return self.inner.write_chunk_content(chunk, content);
+        }
+
+        let mut writer =
+            EncryptionWriter::new(Vec::new(), uuid::Uuid::nil(), self.node_key.as_bytes())?;
+        crate::io::copy(&mut content, &mut writer)?;
+        writer.flush()?;
+
+        self.inner
+            .write_chunk_content(chunk, Box::new(std::io::Cursor::new(writer.finish()?)))
+    }
+
+    #[inline]
+    fn delete_chunk_content(&self, chunk: &ChunkHash) -> std::io::Result<()> {
+        self.inner.delete_chunk_content(chunk)
+    }
+}
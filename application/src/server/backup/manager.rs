@@ -1,4 +1,10 @@
-use crate::{remote::backups::RawServerBackup, server::backup::adapters::BackupAdapter};
+use crate::{
+    remote::backups::RawServerBackup,
+    server::backup::{
+        adapters::BackupAdapter,
+        retention::{BackupRetentionEntry, BackupRetentionLedger, ScheduleBackupRetention},
+    },
+};
 use ignore::gitignore::GitignoreBuilder;
 use std::{
     collections::HashMap,
@@ -17,6 +23,7 @@ pub struct BackupManager {
     cached_browse_backups: Arc<RwLock<HashMap<uuid::Uuid, BrowseBackupValue>>>,
     cached_browse_backup_locks: Arc<RwLock<HashMap<uuid::Uuid, Arc<tokio::sync::Mutex<()>>>>>,
     cached_backup_adapters: RwLock<HashMap<uuid::Uuid, BackupAdapter>>,
+    retention_lock: tokio::sync::Mutex<()>,
 
     task: tokio::task::JoinHandle<()>,
 }
@@ -33,6 +40,7 @@ impl BackupManager {
             cached_browse_backups: Arc::clone(&cached_browse_backups),
             cached_backup_adapters: RwLock::new(HashMap::new()),
             cached_browse_backup_locks: Arc::clone(&cached_browse_backup_locks),
+            retention_lock: tokio::sync::Mutex::new(()),
             task: tokio::spawn({
                 async move {
                     loop {
@@ -92,6 +100,7 @@ impl BackupManager {
         server: &crate::server::Server,
         uuid: uuid::Uuid,
         ignore: String,
+        retention: Option<ScheduleBackupRetention>,
     ) -> Result<RawServerBackup, anyhow::Error> {
         tracing::info!(
             server = %server.uuid,
@@ -128,6 +137,9 @@ impl BackupManager {
 
         ignore_raw.shrink_to_fit();
 
+        let _running_guard =
+            crate::metrics::GaugeGuard::new(&server.app_state.metrics.backups_running);
+
         let progress = Arc::new(AtomicU64::new(0));
         let total = Arc::new(AtomicU64::new(0));
 
@@ -171,11 +183,21 @@ impl BackupManager {
         {
             Ok(backup) => {
                 progress_task.abort();
+                server
+                    .app_state
+                    .metrics
+                    .backups_successful
+                    .fetch_add(1, Ordering::Relaxed);
 
                 backup
             }
             Err(e) => {
                 progress_task.abort();
+                server
+                    .app_state
+                    .metrics
+                    .backups_failed
+                    .fetch_add(1, Ordering::Relaxed);
 
                 server
                     .app_state
@@ -257,9 +279,153 @@ impl BackupManager {
             uuid,
         );
 
+        if adapter == BackupAdapter::S3 {
+            if retention.is_some() || server.configuration.read().await.backup_retention.is_some() {
+                tracing::warn!(
+                    server = %server.uuid,
+                    backup = %uuid,
+                    "backup retention is not supported for s3 backups, skipping",
+                );
+            }
+        } else if let Err(err) = self
+            .apply_retention(server, adapter, uuid, backup.size, retention)
+            .await
+        {
+            tracing::error!(
+                server = %server.uuid,
+                backup = %uuid,
+                "failed to apply backup retention: {:#?}",
+                err
+            );
+        }
+
         Ok(backup)
     }
 
+    /// Records a newly created backup and deletes the backups that fall outside of the retention policy,
+    /// a schedule policy only covers the backups of its schedule, the server policy covers all others
+    async fn apply_retention(
+        &self,
+        server: &crate::server::Server,
+        adapter: BackupAdapter,
+        uuid: uuid::Uuid,
+        size: u64,
+        retention: Option<ScheduleBackupRetention>,
+    ) -> Result<(), anyhow::Error> {
+        let _guard = self.retention_lock.lock().await;
+
+        let schedule = retention.as_ref().map(|retention| retention.schedule);
+
+        let mut ledger = BackupRetentionLedger::load(&self.config, server.uuid).await?;
+        ledger.entries.retain(|entry| entry.uuid != uuid);
+        ledger.entries.push(BackupRetentionEntry {
+            uuid,
+            adapter,
+            schedule,
+            size,
+            created: chrono::Utc::now(),
+        });
+
+        let retention = match retention {
+            Some(retention) => retention.retention,
+            None => server
+                .configuration
+                .read()
+                .await
+                .backup_retention
+                .clone()
+                .unwrap_or_default(),
+        };
+
+        let scoped: Vec<_> = ledger
+            .entries
+            .iter()
+            .filter(|entry| entry.schedule == schedule)
+            .cloned()
+            .collect();
+        let expired: Vec<_> = retention
+            .expired(&scoped)
+            .into_iter()
+            .map(|entry| (entry.uuid, entry.adapter))
+            .collect();
+
+        for (backup_uuid, backup_adapter) in expired {
+            match self.find_adapter(backup_adapter, backup_uuid).await {
+                Ok(Some(backup)) => {
+                    if let Err(err) = backup.delete(&self.config).await {
+                        tracing::error!(
+                            server = %server.uuid,
+                            backup = %backup_uuid,
+                            adapter = ?backup_adapter,
+                            "failed to delete expired backup: {:#?}",
+                            err
+                        );
+
+                        continue;
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        backup = %backup_uuid,
+                        adapter = ?backup_adapter,
+                        "failed to find expired backup: {:#?}",
+                        err
+                    );
+
+                    continue;
+                }
+            }
+
+            self.forget(server, backup_uuid).await;
+            ledger.entries.retain(|entry| entry.uuid != backup_uuid);
+
+            if let Err(err) = self.config.client.delete_backup(backup_uuid).await {
+                tracing::error!(
+                    server = %server.uuid,
+                    backup = %backup_uuid,
+                    "failed to report expired backup deletion: {:#?}",
+                    err
+                );
+            }
+
+            tracing::info!(
+                server = %server.uuid,
+                adapter = ?backup_adapter,
+                "deleted expired backup {}",
+                backup_uuid,
+            );
+        }
+
+        ledger.save().await
+    }
+
+    async fn forget(&self, server: &crate::server::Server, uuid: uuid::Uuid) {
+        self.cached_backups.write().await.remove(&uuid);
+        self.cached_browse_backups.write().await.remove(&uuid);
+        self.cached_backup_adapters.write().await.remove(&uuid);
+        server
+            .configuration
+            .write()
+            .await
+            .backups
+            .retain(|backup| *backup != uuid);
+    }
+
+    /// Deletes a backup and removes it from the retention ledger of the server
+    pub async fn delete(
+        &self,
+        backup: &super::Backup,
+        server: &crate::server::Server,
+    ) -> Result<(), anyhow::Error> {
+        backup.delete(&self.config).await?;
+        self.forget(server, backup.uuid()).await;
+
+        let _guard = self.retention_lock.lock().await;
+        BackupRetentionLedger::remove(&self.config, server.uuid, backup.uuid()).await
+    }
+
     pub async fn restore(
         &self,
         backup: &super::Backup,
@@ -271,6 +437,9 @@ impl BackupManager {
             return Err(anyhow::anyhow!("Server is in a locked state"));
         }
 
+        let _running_guard =
+            crate::metrics::GaugeGuard::new(&server.app_state.metrics.backup_restores_running);
+
         server.restoring.store(true, Ordering::SeqCst);
         if let Err(err) = server
             .stop_with_kill_timeout(std::time::Duration::from_secs(30), false)
@@ -409,6 +578,96 @@ impl BackupManager {
         }
     }
 
+    pub async fn verify(
+        &self,
+        backup: &super::Backup,
+        server: &crate::server::Server,
+        checksum: Option<String>,
+        download_url: Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        tracing::info!(
+            server = %server.uuid,
+            backup = %backup.uuid(),
+            adapter = ?backup.adapter(),
+            "verifying backup",
+        );
+
+        let progress = Arc::new(AtomicU64::new(0));
+        let total = Arc::new(AtomicU64::new(1));
+
+        let progress_task = tokio::spawn({
+            let progress = Arc::clone(&progress);
+            let total = Arc::clone(&total);
+            let server = server.clone();
+            let uuid = backup.uuid();
+
+            async move {
+                loop {
+                    let progress = progress.load(Ordering::SeqCst);
+                    let total = total.load(Ordering::SeqCst);
+
+                    server
+                        .websocket
+                        .send(crate::server::websocket::WebsocketMessage::new(
+                            crate::server::websocket::WebsocketEvent::ServerBackupVerifyProgress,
+                            &[
+                                uuid.to_string(),
+                                serde_json::to_string(&crate::models::Progress { progress, total })
+                                    .unwrap(),
+                            ],
+                        ))
+                        .ok();
+
+                    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                }
+            }
+        });
+
+        let result = backup
+            .verify(
+                &self.config,
+                progress,
+                total,
+                checksum.as_deref(),
+                download_url,
+            )
+            .await;
+        progress_task.abort();
+
+        server
+            .websocket
+            .send(crate::server::websocket::WebsocketMessage::new(
+                crate::server::websocket::WebsocketEvent::ServerBackupVerifyCompleted,
+                &[
+                    backup.uuid().to_string(),
+                    match &result {
+                        Ok(checksum) => serde_json::json!({
+                            "successful": true,
+                            "checksum": checksum,
+                            "error": null,
+                        }),
+                        Err(err) => serde_json::json!({
+                            "successful": false,
+                            "checksum": null,
+                            "error": err.to_string(),
+                        }),
+                    }
+                    .to_string(),
+                ],
+            ))?;
+
+        result?;
+
+        tracing::info!(
+            server = %server.uuid,
+            backup = %backup.uuid(),
+            adapter = ?backup.adapter(),
+            "completed verification of backup",
+        );
+
+        Ok(())
+    }
+
     pub async fn find(
         &self,
         uuid: uuid::Uuid,
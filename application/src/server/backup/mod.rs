@@ -9,7 +9,19 @@ use std::{
 use tokio::io::AsyncRead;
 
 pub mod adapters;
+pub mod encryption;
 pub mod manager;
+pub mod retention;
+
+/// A backup stored as a single file on disk, which can be served with range requests
+pub struct BackupDownloadFile {
+    pub file: encryption::BackupFile,
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub modified: Option<std::time::SystemTime>,
+    /// The checksum recorded when the backup was created, used as its `ETag`
+    pub checksum: Option<String>,
+}
 
 pub enum Backup {
     Wings(adapters::wings::WingsBackup),
@@ -59,6 +71,20 @@ impl Backup {
         }
     }
 
+    pub async fn download_file(
+        &self,
+        config: &Arc<crate::config::Config>,
+    ) -> Result<Option<BackupDownloadFile>, anyhow::Error> {
+        match self {
+            Backup::Wings(backup) => backup.download_file(config).await,
+            Backup::S3(backup) => backup.download_file(config).await,
+            Backup::DdupBak(backup) => backup.download_file(config).await,
+            Backup::Btrfs(backup) => backup.download_file(config).await,
+            Backup::Zfs(backup) => backup.download_file(config).await,
+            Backup::Restic(backup) => backup.download_file(config).await,
+        }
+    }
+
     pub async fn restore(
         &self,
         server: &crate::server::Server,
@@ -76,6 +102,35 @@ impl Backup {
         }
     }
 
+    /// Re-reads the backup, failing if it is damaged or its checksum differs from `checksum`
+    pub async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        checksum: Option<&str>,
+        download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let actual = match self {
+            Backup::Wings(backup) => backup.verify(config, progress, total, download_url).await,
+            Backup::S3(backup) => backup.verify(config, progress, total, download_url).await,
+            Backup::DdupBak(backup) => backup.verify(config, progress, total, download_url).await,
+            Backup::Btrfs(backup) => backup.verify(config, progress, total, download_url).await,
+            Backup::Zfs(backup) => backup.verify(config, progress, total, download_url).await,
+            Backup::Restic(backup) => backup.verify(config, progress, total, download_url).await,
+        }?;
+
+        if let (Some(expected), Some(actual)) = (checksum, &actual)
+            && !expected.eq_ignore_ascii_case(actual)
+        {
+            return Err(anyhow::anyhow!(
+                "checksum mismatch, expected {expected} but got {actual}"
+            ));
+        }
+
+        Ok(actual)
+    }
+
     pub async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error> {
         match self {
             Backup::Wings(backup) => backup.delete(config).await,
@@ -230,6 +285,11 @@ pub trait BackupExt {
         config: &Arc<crate::config::Config>,
         archive_format: StreamableArchiveFormat,
     ) -> Result<ApiResponse, anyhow::Error>;
+    /// Returns `None` for adapters that do not store the backup as a single seekable file
+    async fn download_file(
+        &self,
+        config: &Arc<crate::config::Config>,
+    ) -> Result<Option<BackupDownloadFile>, anyhow::Error>;
 
     async fn restore(
         &self,
@@ -238,6 +298,14 @@ pub trait BackupExt {
         total: Arc<AtomicU64>,
         download_url: Option<String>,
     ) -> Result<(), anyhow::Error>;
+    /// Returns the recomputed checksum for adapters that record one
+    async fn verify(
+        &self,
+        config: &Arc<crate::config::Config>,
+        progress: Arc<AtomicU64>,
+        total: Arc<AtomicU64>,
+        download_url: Option<String>,
+    ) -> Result<Option<String>, anyhow::Error>;
     async fn delete(&self, config: &Arc<crate::config::Config>) -> Result<(), anyhow::Error>;
 
     async fn browse(&self, server: &crate::server::Server) -> Result<BrowseBackup, anyhow::Error>;
@@ -0,0 +1,212 @@
+use super::adapters::BackupAdapter;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use utoipa::ToSchema;
+
+#[derive(ToSchema, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BackupRetention {
+    /// Keep the most recent N backups
+    #[serde(default)]
+    pub keep_last: Option<usize>,
+    /// Keep the newest backup of each of the last N days
+    #[serde(default)]
+    pub keep_daily: Option<usize>,
+    /// Keep the newest backup of each of the last N ISO weeks
+    #[serde(default)]
+    pub keep_weekly: Option<usize>,
+    /// Keep the newest backup of each of the last N months
+    #[serde(default)]
+    pub keep_monthly: Option<usize>,
+    /// Bytes, the newest backups are kept until the total size is exceeded
+    #[serde(default)]
+    pub max_size: Option<u64>,
+}
+
+impl BackupRetention {
+    #[inline]
+    fn has_keep_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.keep_monthly.is_some()
+    }
+
+    /// Returns the entries that are no longer covered by this policy, the newest backup is always kept
+    pub fn expired<'a>(
+        &self,
+        entries: &'a [BackupRetentionEntry],
+    ) -> Vec<&'a BackupRetentionEntry> {
+        let mut sorted: Vec<&BackupRetentionEntry> = entries.iter().collect();
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.created));
+
+        let mut kept = HashSet::new();
+        if let Some(newest) = sorted.first() {
+            kept.insert(newest.uuid);
+        }
+
+        if self.has_keep_rules() {
+            if let Some(keep_last) = self.keep_last {
+                kept.extend(sorted.iter().take(keep_last).map(|entry| entry.uuid));
+            }
+
+            Self::keep_buckets(&sorted, self.keep_daily, &mut kept, |entry| {
+                let date = entry.created.date_naive();
+                (date.year(), date.ordinal())
+            });
+            Self::keep_buckets(&sorted, self.keep_weekly, &mut kept, |entry| {
+                let week = entry.created.iso_week();
+                (week.year(), week.week())
+            });
+            Self::keep_buckets(&sorted, self.keep_monthly, &mut kept, |entry| {
+                (entry.created.year(), entry.created.month())
+            });
+        } else {
+            kept.extend(sorted.iter().map(|entry| entry.uuid));
+        }
+
+        if let Some(max_size) = self.max_size {
+            let mut total_size = 0;
+
+            for (i, entry) in sorted.iter().enumerate() {
+                if !kept.contains(&entry.uuid) {
+                    continue;
+                }
+
+                total_size += entry.size;
+                if i > 0 && total_size > max_size {
+                    kept.remove(&entry.uuid);
+                }
+            }
+        }
+
+        sorted
+            .into_iter()
+            .filter(|entry| !kept.contains(&entry.uuid))
+            .collect()
+    }
+
+    fn keep_buckets<K: PartialEq>(
+        sorted: &[&BackupRetentionEntry],
+        count: Option<usize>,
+        kept: &mut HashSet<uuid::Uuid>,
+        bucket: impl Fn(&BackupRetentionEntry) -> K,
+    ) {
+        let count = match count {
+            Some(count) if count > 0 => count,
+            _ => return,
+        };
+
+        let mut last_bucket = None;
+        let mut buckets = 0;
+
+        for entry in sorted {
+            let current = bucket(entry);
+            if last_bucket.as_ref() == Some(&current) {
+                continue;
+            }
+
+            kept.insert(entry.uuid);
+            last_bucket = Some(current);
+
+            buckets += 1;
+            if buckets >= count {
+                break;
+            }
+        }
+    }
+}
+
+/// A retention policy of a schedule, it only applies to the backups created by that schedule
+#[derive(Clone)]
+pub struct ScheduleBackupRetention {
+    pub schedule: uuid::Uuid,
+    pub retention: BackupRetention,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct BackupRetentionEntry {
+    pub uuid: uuid::Uuid,
+    pub adapter: BackupAdapter,
+    /// The schedule whose retention policy covers this backup, `None` for backups covered by the server policy
+    #[serde(default)]
+    pub schedule: Option<uuid::Uuid>,
+    pub size: u64,
+    pub created: chrono::DateTime<chrono::Utc>,
+}
+
+/// Backups created by this node for a single server, the panel does not send creation
+/// dates or sizes so retention can only consider backups recorded here
+pub struct BackupRetentionLedger {
+    path: PathBuf,
+    pub entries: Vec<BackupRetentionEntry>,
+}
+
+impl BackupRetentionLedger {
+    #[inline]
+    fn path(config: &crate::config::Config, server: uuid::Uuid) -> PathBuf {
+        Path::new(&config.system.root_directory)
+            .join("backup_retention")
+            .join(format!("{server}.json"))
+    }
+
+    pub async fn load(
+        config: &crate::config::Config,
+        server: uuid::Uuid,
+    ) -> Result<Self, anyhow::Error> {
+        let path = Self::path(config, server);
+
+        let entries = match tokio::fs::read(&path).await {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { path, entries })
+    }
+
+    pub async fn save(&self) -> Result<(), anyhow::Error> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(&self.path, serde_json::to_vec(&self.entries)?).await?;
+
+        Ok(())
+    }
+
+    /// Forgets a backup that was deleted outside of retention
+    pub async fn remove(
+        config: &crate::config::Config,
+        server: uuid::Uuid,
+        backup: uuid::Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let mut ledger = Self::load(config, server).await?;
+
+        let len = ledger.entries.len();
+        ledger.entries.retain(|entry| entry.uuid != backup);
+
+        if ledger.entries.len() != len {
+            ledger.save().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn destroy(config: &crate::config::Config, server: uuid::Uuid) {
+        match tokio::fs::remove_file(Self::path(config, server)).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                tracing::error!(
+                    server = %server,
+                    "failed to remove backup retention ledger: {:#?}",
+                    err
+                );
+            }
+        }
+    }
+}
@@ -66,7 +66,15 @@ nestify::nest! {
         #[serde(default)]
         pub backups: Vec<uuid::Uuid>,
         #[serde(default)]
+        pub backup_retention: Option<super::backup::retention::BackupRetention>,
+        /// Encrypts this server's wings and s3 backups with its own key instead of the node key
+        #[serde(default)]
+        pub backup_encryption_key: Option<String>,
+        #[serde(default)]
         pub schedules: Vec<Schedule>,
+        /// Keeps deleted and overwritten files outside of the volume, disabled when unset
+        #[serde(default)]
+        pub trash: Option<super::filesystem::trash::TrashPolicy>,
 
         #[schema(inline)]
         pub allocations: #[derive(ToSchema, Deserialize, Serialize)] pub struct ServerConfigurationAllocations {
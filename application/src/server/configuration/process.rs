@@ -15,6 +15,7 @@ pub enum ServerConfigurationFileParser {
     Ini,
     Json,
     Xml,
+    Toml,
 }
 
 #[derive(ToSchema, Deserialize, Clone)]
@@ -336,6 +337,13 @@ impl ProcessConfiguration {
                     );
                     process_xml_file(&file_content, &config, server).await
                 }
+                ServerConfigurationFileParser::Toml => {
+                    tracing::debug!(
+                        server = %server.uuid,
+                        "using toml parser"
+                    );
+                    process_toml_file(&file_content, &config, server).await
+                }
                 ServerConfigurationFileParser::File => {
                     tracing::debug!(
                         server = %server.uuid,
@@ -988,6 +996,198 @@ async fn process_xml_file(
     xml_content
 }
 
+async fn process_toml_file(
+    content: &str,
+    config: &ServerConfigurationFile,
+    server: &crate::server::Server,
+) -> String {
+    tracing::debug!(
+        server = %server.uuid,
+        "processing toml file with {} bytes",
+        content.len()
+    );
+
+    let mut document = if content.trim().is_empty() {
+        tracing::debug!(
+            server = %server.uuid,
+            "content is empty, starting with empty document"
+        );
+        toml_edit::DocumentMut::new()
+    } else {
+        match content.parse::<toml_edit::DocumentMut>() {
+            Ok(document) => {
+                tracing::debug!(
+                    server = %server.uuid,
+                    "successfully parsed toml content"
+                );
+                document
+            }
+            Err(e) => {
+                tracing::error!(
+                    server = %server.uuid,
+                    "failed to parse toml content: {}. starting with empty document.",
+                    e
+                );
+                toml_edit::DocumentMut::new()
+            }
+        }
+    };
+
+    tracing::debug!(
+        server = %server.uuid,
+        "applying {} replacements to toml",
+        config.replace.len()
+    );
+
+    for (index, replacement) in config.replace.iter().enumerate() {
+        let path_parts: Vec<&str> = replacement.r#match.split('.').collect();
+
+        tracing::debug!(
+            server = %server.uuid,
+            "processing replacement {}/{}: path '{}'",
+            index + 1,
+            config.replace.len(),
+            replacement.r#match
+        );
+
+        if let Some(if_value) = &replacement.if_value
+            && let Some(current_value) = get_toml_value(document.as_item(), &path_parts)
+            && current_value != *if_value
+        {
+            tracing::debug!(
+                server = %server.uuid,
+                "value '{}' does not match required if_value '{}', skipping",
+                current_value, if_value
+            );
+            continue;
+        }
+
+        if let Some(value) =
+            ServerConfigurationFile::lookup_value(server, &replacement.replace_with).await
+        {
+            tracing::debug!(
+                server = %server.uuid,
+                "updating toml value at path '{}' with value '{}'",
+                replacement.r#match, value
+            );
+            update_toml_value(document.as_item_mut(), &path_parts, value, server);
+        }
+    }
+
+    document.to_string()
+}
+
+fn get_toml_value(item: &toml_edit::Item, path: &[&str]) -> Option<String> {
+    let mut current = item;
+    for part in path {
+        current = current.as_table_like()?.get(part)?;
+    }
+
+    let value = current.as_value()?;
+    Some(match value {
+        toml_edit::Value::String(s) => s.value().clone(),
+        toml_edit::Value::Integer(i) => i.value().to_string(),
+        toml_edit::Value::Float(f) => f.value().to_string(),
+        toml_edit::Value::Boolean(b) => b.value().to_string(),
+        _ => value.to_string().trim().to_string(),
+    })
+}
+
+fn update_toml_value(
+    item: &mut toml_edit::Item,
+    path: &[&str],
+    value: String,
+    server: &crate::server::Server,
+) {
+    if path.is_empty() {
+        tracing::debug!(
+            server = %server.uuid,
+            "empty path provided to update_toml_value, skipping"
+        );
+        return;
+    }
+
+    if !item.is_table_like() {
+        tracing::debug!(
+            server = %server.uuid,
+            "toml value at '{}' is not a table, replacing with new table",
+            path[0]
+        );
+        *item = toml_edit::table();
+    }
+
+    let table = match item.as_table_like_mut() {
+        Some(table) => table,
+        None => return,
+    };
+
+    if path.len() == 1 {
+        let mut new_value = if value.eq_ignore_ascii_case("true") {
+            toml_edit::Value::from(true)
+        } else if value.eq_ignore_ascii_case("false") {
+            toml_edit::Value::from(false)
+        } else if let Ok(i) = value.parse::<i64>() {
+            toml_edit::Value::from(i)
+        } else if let Ok(f) = value.parse::<f64>()
+            && f.fract() != 0.0
+            && f.is_finite()
+        {
+            toml_edit::Value::from(f)
+        } else {
+            toml_edit::Value::from(value)
+        };
+
+        match table.get_mut(path[0]) {
+            Some(toml_edit::Item::Value(old_value)) => {
+                tracing::debug!(
+                    server = %server.uuid,
+                    "replacing existing toml key '{}', keeping its formatting",
+                    path[0]
+                );
+
+                *new_value.decor_mut() = old_value.decor().clone();
+                *old_value = new_value;
+            }
+            Some(old_item) => {
+                tracing::debug!(
+                    server = %server.uuid,
+                    "replacing non-value toml item at key '{}'",
+                    path[0]
+                );
+
+                *old_item = toml_edit::Item::Value(new_value);
+            }
+            None => {
+                tracing::debug!(
+                    server = %server.uuid,
+                    "adding new toml key '{}'",
+                    path[0]
+                );
+
+                table.insert(path[0], toml_edit::Item::Value(new_value));
+            }
+        }
+
+        return;
+    }
+
+    tracing::debug!(
+        server = %server.uuid,
+        "navigating to nested toml path: {}",
+        path[0]
+    );
+
+    let entry = table.entry(path[0]).or_insert_with(|| {
+        tracing::debug!(
+            server = %server.uuid,
+            "creating new toml table for key '{}'",
+            path[0]
+        );
+        toml_edit::table()
+    });
+    update_toml_value(entry, &path[1..], value, server);
+}
+
 async fn process_plain_file(
     content: &str,
     config: &ServerConfigurationFile,
@@ -0,0 +1,520 @@
+use super::{Archive, ArchiveType, dos_time_to_unix, zip_entry_get_modified_time};
+use crate::io::compression::{CompressionType, reader::CompressionReader};
+use serde::Serialize;
+use std::{
+    io::SeekFrom,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use tokio::io::{AsyncRead, AsyncSeekExt};
+use utoipa::ToSchema;
+
+#[derive(ToSchema, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "snake_case")]
+pub enum ArchiveEntryType {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(ToSchema, Serialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "type")]
+    pub kind: ArchiveEntryType,
+}
+
+/// Normalizes a path inside of an archive, returns `None` for empty
+/// paths and paths that would escape the extraction directory
+pub fn normalize_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// The entries to extract from an archive, an entry is selected
+/// if it is one of the paths or located inside of one of them
+#[derive(Clone)]
+pub struct ArchiveSelection(Arc<Vec<PathBuf>>);
+
+impl ArchiveSelection {
+    pub fn new(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        Self(Arc::new(
+            paths
+                .into_iter()
+                .filter_map(|path| normalize_entry_path(path.as_ref()))
+                .collect(),
+        ))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        match normalize_entry_path(path) {
+            Some(path) => self.0.iter().any(|selected| path.starts_with(selected)),
+            None => false,
+        }
+    }
+
+    /// Whether a directory has to be entered to reach a selected entry
+    pub fn leads_to(&self, path: &Path) -> bool {
+        match normalize_entry_path(path) {
+            Some(path) => self.0.iter().any(|selected| selected.starts_with(&path)),
+            None => false,
+        }
+    }
+}
+
+#[inline]
+fn not_found() -> anyhow::Error {
+    anyhow::anyhow!(std::io::Error::from(rustix::io::Errno::NOENT))
+}
+
+impl Archive {
+    /// Lists the entries of the archive without extracting them, tar based
+    /// archives have no index and have to be decompressed to be listed
+    pub async fn entries(mut self) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        self.file.seek(SeekFrom::Start(0)).await?;
+
+        match self.archive {
+            ArchiveType::None => {
+                let name = match self.path.file_stem() {
+                    Some(stem) => stem.to_string_lossy().to_string(),
+                    None => return Err(anyhow::anyhow!("Invalid file name")),
+                };
+
+                let modified = self
+                    .file
+                    .metadata()
+                    .await?
+                    .modified()
+                    .ok()
+                    .map(|modified| modified.into());
+
+                Ok(vec![ArchiveEntry {
+                    name,
+                    size: self.estimated_size().await.unwrap_or_default(),
+                    modified,
+                    kind: ArchiveEntryType::File,
+                }])
+            }
+            ArchiveType::Tar => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<_, anyhow::Error> {
+                    let reader = CompressionReader::new(file, self.compression);
+                    let mut archive = tar::Archive::new(reader);
+
+                    let mut entries = Vec::new();
+                    for entry in archive.entries()? {
+                        let entry = entry?;
+                        let path = match normalize_entry_path(&entry.path()?) {
+                            Some(path) => path,
+                            None => continue,
+                        };
+
+                        let header = entry.header();
+                        let kind = match header.entry_type() {
+                            tar::EntryType::Directory => ArchiveEntryType::Directory,
+                            tar::EntryType::Regular => ArchiveEntryType::File,
+                            tar::EntryType::Symlink => ArchiveEntryType::Symlink,
+                            _ => continue,
+                        };
+
+                        entries.push(ArchiveEntry {
+                            name: path.to_string_lossy().to_string(),
+                            size: header.size().unwrap_or_default(),
+                            modified: header.mtime().ok().and_then(|mtime| {
+                                chrono::DateTime::from_timestamp(mtime as i64, 0)
+                            }),
+                            kind,
+                        });
+                    }
+
+                    Ok(entries)
+                })
+                .await?
+            }
+            ArchiveType::Zip => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<_, anyhow::Error> {
+                    let mut archive = zip::ZipArchive::new(file)?;
+
+                    let mut entries = Vec::new();
+                    for i in 0..archive.len() {
+                        let entry = archive.by_index_raw(i)?;
+                        let path = match entry
+                            .enclosed_name()
+                            .and_then(|path| normalize_entry_path(&path))
+                        {
+                            Some(path) => path,
+                            None => continue,
+                        };
+
+                        let kind = if entry.is_dir() {
+                            ArchiveEntryType::Directory
+                        } else if entry.is_symlink() {
+                            ArchiveEntryType::Symlink
+                        } else {
+                            ArchiveEntryType::File
+                        };
+
+                        entries.push(ArchiveEntry {
+                            name: path.to_string_lossy().to_string(),
+                            size: entry.size(),
+                            modified: zip_entry_get_modified_time(&entry)
+                                .map(|modified| modified.into_std().into()),
+                            kind,
+                        });
+                    }
+
+                    Ok(entries)
+                })
+                .await?
+            }
+            ArchiveType::Rar => {
+                drop(self.file);
+
+                tokio::task::spawn_blocking(move || -> Result<_, anyhow::Error> {
+                    let archive = unrar::Archive::new_owned(
+                        self.server.filesystem.base_path.join(&self.path),
+                    )
+                    .open_for_listing()?;
+
+                    let mut entries = Vec::new();
+                    for entry in archive {
+                        let entry = entry?;
+                        let path = match normalize_entry_path(&entry.filename) {
+                            Some(path) => path,
+                            None => continue,
+                        };
+
+                        entries.push(ArchiveEntry {
+                            name: path.to_string_lossy().to_string(),
+                            size: entry.unpacked_size,
+                            modified: dos_time_to_unix(entry.file_time)
+                                .and_then(|time| chrono::DateTime::from_timestamp(time as i64, 0)),
+                            kind: if entry.is_directory() {
+                                ArchiveEntryType::Directory
+                            } else {
+                                ArchiveEntryType::File
+                            },
+                        });
+                    }
+
+                    Ok(entries)
+                })
+                .await?
+            }
+            ArchiveType::SevenZip => {
+                let mut file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<_, anyhow::Error> {
+                    let password = sevenz_rust2::Password::empty();
+                    let archive = sevenz_rust2::Archive::read(&mut file, &password)?;
+
+                    Ok(archive
+                        .files
+                        .iter()
+                        .filter(|entry| !entry.is_anti_item())
+                        .filter_map(|entry| {
+                            let path = normalize_entry_path(Path::new(entry.name()))?;
+
+                            Some(ArchiveEntry {
+                                name: path.to_string_lossy().to_string(),
+                                size: entry.size(),
+                                modified: if entry.has_last_modified_date {
+                                    Some(
+                                        std::time::SystemTime::from(entry.last_modified_date)
+                                            .into(),
+                                    )
+                                } else {
+                                    None
+                                },
+                                kind: if entry.is_directory() {
+                                    ArchiveEntryType::Directory
+                                } else {
+                                    ArchiveEntryType::File
+                                },
+                            })
+                        })
+                        .collect())
+                })
+                .await?
+            }
+            ArchiveType::Ddup => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<_, anyhow::Error> {
+                    let archive = ddup_bak::archive::Archive::open_file(file)?;
+
+                    fn recursive_list(
+                        parent: &Path,
+                        entry: &ddup_bak::archive::entries::Entry,
+                        entries: &mut Vec<ArchiveEntry>,
+                    ) {
+                        let path = parent.join(entry.name());
+
+                        let (size, modified, kind) = match entry {
+                            ddup_bak::archive::entries::Entry::File(file) => {
+                                (file.size, file.mtime, ArchiveEntryType::File)
+                            }
+                            ddup_bak::archive::entries::Entry::Directory(dir) => {
+                                (0, dir.mtime, ArchiveEntryType::Directory)
+                            }
+                            ddup_bak::archive::entries::Entry::Symlink(link) => {
+                                (0, link.mtime, ArchiveEntryType::Symlink)
+                            }
+                        };
+
+                        entries.push(ArchiveEntry {
+                            name: path.to_string_lossy().to_string(),
+                            size,
+                            modified: Some(modified.into()),
+                            kind,
+                        });
+
+                        if let ddup_bak::archive::entries::Entry::Directory(dir) = entry {
+                            for entry in dir.entries.iter() {
+                                recursive_list(&path, entry, entries);
+                            }
+                        }
+                    }
+
+                    let mut entries = Vec::new();
+                    for entry in archive.entries() {
+                        recursive_list(Path::new(""), entry, &mut entries);
+                    }
+
+                    Ok(entries)
+                })
+                .await?
+            }
+        }
+    }
+
+    /// Reads a single file out of the archive, returns the size if it is known upfront
+    pub async fn read_entry(
+        mut self,
+        path: &Path,
+    ) -> Result<(Option<u64>, Box<dyn AsyncRead + Unpin + Send>), anyhow::Error> {
+        let path = normalize_entry_path(path).ok_or_else(not_found)?;
+        let server = self.server.uuid;
+        self.file.seek(SeekFrom::Start(0)).await?;
+
+        let (reader, writer) = tokio::io::duplex(crate::BUFFER_SIZE);
+        let (size_sender, size_receiver) = tokio::sync::oneshot::channel::<Option<u64>>();
+
+        let task = match self.archive {
+            ArchiveType::None => {
+                if self.path.file_stem() != Some(path.as_os_str()) {
+                    return Err(not_found());
+                }
+
+                let size = match self.compression {
+                    CompressionType::None => self.estimated_size().await,
+                    _ => None,
+                };
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let mut reader = CompressionReader::new(file, self.compression);
+                    let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+
+                    size_sender.send(size).ok();
+                    crate::io::copy(&mut reader, &mut writer)?;
+
+                    Ok(())
+                })
+            }
+            ArchiveType::Tar => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let reader = CompressionReader::new(file, self.compression);
+                    let mut archive = tar::Archive::new(reader);
+
+                    for entry in archive.entries()? {
+                        let mut entry = entry?;
+                        if entry.header().entry_type() != tar::EntryType::Regular
+                            || normalize_entry_path(&entry.path()?).as_ref() != Some(&path)
+                        {
+                            continue;
+                        }
+
+                        let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+
+                        size_sender.send(entry.header().size().ok()).ok();
+                        crate::io::copy(&mut entry, &mut writer)?;
+
+                        break;
+                    }
+
+                    Ok(())
+                })
+            }
+            ArchiveType::Zip => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let mut archive = zip::ZipArchive::new(file)?;
+
+                    let mut index = None;
+                    for i in 0..archive.len() {
+                        let entry = archive.by_index_raw(i)?;
+                        if entry.is_file()
+                            && entry
+                                .enclosed_name()
+                                .and_then(|name| normalize_entry_path(&name))
+                                .as_ref()
+                                == Some(&path)
+                        {
+                            index = Some(i);
+                            break;
+                        }
+                    }
+
+                    let Some(index) = index else {
+                        return Ok(());
+                    };
+
+                    let mut entry = archive.by_index(index)?;
+                    let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+
+                    size_sender.send(Some(entry.size())).ok();
+                    crate::io::copy(&mut entry, &mut writer)?;
+
+                    Ok(())
+                })
+            }
+            ArchiveType::Rar => {
+                drop(self.file);
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let mut archive =
+                        unrar::Archive::new_owned(self.server.filesystem.base_path.join(self.path))
+                            .open_for_processing()?;
+
+                    while let Some(entry) = archive.read_header()? {
+                        if !entry.entry().is_file()
+                            || normalize_entry_path(&entry.entry().filename).as_ref() != Some(&path)
+                        {
+                            archive = entry.skip()?;
+                            continue;
+                        }
+
+                        size_sender.send(Some(entry.entry().unpacked_size)).ok();
+
+                        let (unrar::Stream(_, err), _) = entry
+                            .read_to_stream(Box::new(tokio_util::io::SyncIoBridge::new(writer)))?;
+                        if let Some(err) = err {
+                            return Err(err.into());
+                        }
+
+                        break;
+                    }
+
+                    Ok(())
+                })
+            }
+            ArchiveType::SevenZip => {
+                let mut file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let password = sevenz_rust2::Password::empty();
+                    let archive = sevenz_rust2::Archive::read(&mut file, &password)?;
+
+                    let Some(index) = archive.files.iter().position(|entry| {
+                        !entry.is_directory()
+                            && normalize_entry_path(Path::new(entry.name())).as_ref() == Some(&path)
+                    }) else {
+                        return Ok(());
+                    };
+
+                    let Some(block_index) = archive.stream_map.file_block_index[index] else {
+                        size_sender.send(Some(0)).ok();
+                        return Ok(());
+                    };
+
+                    let name = archive.files[index].name().to_string();
+                    let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+                    let mut size_sender = Some(size_sender);
+
+                    // only the block containing the entry has to be decoded
+                    sevenz_rust2::BlockDecoder::new(1, block_index, &archive, &password, &mut file)
+                        .for_each_entries(&mut |entry, reader| {
+                            if entry.name() != name {
+                                return Ok(true);
+                            }
+
+                            if let Some(size_sender) = size_sender.take() {
+                                size_sender.send(Some(entry.size())).ok();
+                            }
+                            crate::io::copy(reader, &mut writer)?;
+
+                            Ok(false)
+                        })?;
+
+                    Ok(())
+                })
+            }
+            ArchiveType::Ddup => {
+                let file = self.file.into_std().await;
+
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let archive = ddup_bak::archive::Archive::open_file(file)?;
+
+                    let mut entry = match archive.find_archive_entry(&path) {
+                        Some(ddup_bak::archive::entries::Entry::File(file)) => file.clone(),
+                        _ => return Ok(()),
+                    };
+                    let mut writer = tokio_util::io::SyncIoBridge::new(writer);
+
+                    size_sender.send(Some(entry.size)).ok();
+                    crate::io::copy(&mut entry, &mut writer)?;
+
+                    Ok(())
+                })
+            }
+        };
+
+        match size_receiver.await {
+            Ok(size) => {
+                tokio::spawn(async move {
+                    if let Ok(Err(err)) = task.await {
+                        tracing::error!(
+                            server = %server,
+                            "error reading archive entry: {:#?}",
+                            err
+                        );
+                    }
+                });
+
+                Ok((size, Box::new(reader)))
+            }
+            Err(_) => {
+                task.await??;
+
+                Err(not_found())
+            }
+        }
+    }
+}
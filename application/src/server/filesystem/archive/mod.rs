@@ -24,6 +24,7 @@ use tokio::{
 use utoipa::ToSchema;
 
 pub mod create;
+pub mod entries;
 pub mod multi_reader;
 
 #[derive(Clone, Copy)]
@@ -130,6 +131,29 @@ pub fn zip_entry_get_modified_time(
     None
 }
 
+fn dos_time_to_unix(dos_time: u32) -> Option<u64> {
+    let seconds = (dos_time & 0x1F) * 2;
+    let minutes = (dos_time >> 5) & 0x3F;
+    let hours = (dos_time >> 11) & 0x1F;
+    let day = (dos_time >> 16) & 0x1F;
+    let month = (dos_time >> 21) & 0x0F;
+    let year = ((dos_time >> 25) & 0x7F) + 1980;
+
+    if seconds >= 60
+        || minutes >= 60
+        || hours >= 24
+        || !(1..=31).contains(&day)
+        || !(1..=12).contains(&month)
+    {
+        return None;
+    }
+
+    let date = chrono::NaiveDate::from_ymd_opt(year as i32, month, day)?;
+    let time = chrono::NaiveTime::from_hms_opt(hours, minutes, seconds)?;
+
+    Some(chrono::NaiveDateTime::new(date, time).and_utc().timestamp() as u64)
+}
+
 pub struct Archive {
     pub compression: CompressionType,
     pub archive: ArchiveType,
@@ -139,6 +163,8 @@ pub struct Archive {
 
     pub file: File,
     pub path: PathBuf,
+
+    pub selection: Option<entries::ArchiveSelection>,
 }
 
 impl Archive {
@@ -186,9 +212,17 @@ impl Archive {
             header,
             file,
             path,
+            selection: None,
         })
     }
 
+    /// Limits extraction to the selected entries and everything inside of them
+    #[inline]
+    pub fn with_selection(mut self, selection: entries::ArchiveSelection) -> Self {
+        self.selection = Some(selection);
+        self
+    }
+
     pub async fn estimated_size(&mut self) -> Option<u64> {
         match self.compression {
             CompressionType::None => Some(self.file.metadata().await.ok()?.len()),
@@ -288,6 +322,7 @@ impl Archive {
         total: Option<Arc<AtomicU64>>,
     ) -> Result<(), anyhow::Error> {
         self.file.seek(SeekFrom::Start(0)).await?;
+        let selection = self.selection.clone();
 
         match self.archive {
             ArchiveType::None => {
@@ -355,7 +390,11 @@ impl Archive {
                     while let Some(Ok(mut entry)) = entries.next() {
                         let path = entry.path()?;
 
-                        if path.is_absolute() {
+                        if path.is_absolute()
+                            || selection
+                                .as_ref()
+                                .is_some_and(|selection| !selection.contains(&path))
+                        {
                             continue;
                         }
 
@@ -458,7 +497,15 @@ impl Archive {
                     if let Some(total) = total {
                         let mut entry_total = 0;
                         for i in 0..archive.len() {
-                            let entry = archive.by_index(i)?;
+                            let entry = archive.by_index_raw(i)?;
+                            if let Some(selection) = &selection
+                                && !entry
+                                    .enclosed_name()
+                                    .is_some_and(|path| selection.contains(&path))
+                            {
+                                continue;
+                            }
+
                             entry_total += entry.size();
                         }
 
@@ -475,11 +522,13 @@ impl Archive {
                         let archive = archive.clone();
                         let destination = destination.clone();
                         let server = self.server.clone();
+                        let selection = selection.clone();
                         let error_clone = Arc::clone(&error);
 
                         scope.spawn_broadcast(move |_, _| {
                             let mut archive = archive.clone();
                             let progress = progress.clone();
+                            let selection = selection.clone();
                             let entry_index = Arc::clone(&entry_index);
                             let error_clone2 = Arc::clone(&error_clone);
                             let destination = destination.clone();
@@ -504,7 +553,11 @@ impl Archive {
                                         None => continue,
                                     };
 
-                                    if path.is_absolute() {
+                                    if path.is_absolute()
+                                        || selection
+                                            .as_ref()
+                                            .is_some_and(|selection| !selection.contains(&path))
+                                    {
                                         continue;
                                     }
 
@@ -602,7 +655,11 @@ impl Archive {
                                     None => continue,
                                 };
 
-                                if path.is_absolute() {
+                                if path.is_absolute()
+                                    || selection
+                                        .as_ref()
+                                        .is_some_and(|selection| !selection.contains(&path))
+                                {
                                     continue;
                                 }
 
@@ -636,29 +693,6 @@ impl Archive {
             ArchiveType::Rar => {
                 let (guard, listener) = AbortGuard::new();
 
-                fn dos_time_to_unix(dos_time: u32) -> Option<u64> {
-                    let seconds = (dos_time & 0x1F) * 2;
-                    let minutes = (dos_time >> 5) & 0x3F;
-                    let hours = (dos_time >> 11) & 0x1F;
-                    let day = (dos_time >> 16) & 0x1F;
-                    let month = (dos_time >> 21) & 0x0F;
-                    let year = ((dos_time >> 25) & 0x7F) + 1980;
-
-                    if seconds >= 60
-                        || minutes >= 60
-                        || hours >= 24
-                        || !(1..=31).contains(&day)
-                        || !(1..=12).contains(&month)
-                    {
-                        return None;
-                    }
-
-                    let date = chrono::NaiveDate::from_ymd_opt(year as i32, month, day)?;
-                    let time = chrono::NaiveTime::from_hms_opt(hours, minutes, seconds)?;
-
-                    Some(chrono::NaiveDateTime::new(date, time).and_utc().timestamp() as u64)
-                }
-
                 tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
                     drop(self.file);
 
@@ -669,7 +703,12 @@ impl Archive {
                         )
                         .open_for_listing()?;
                         for entry in archive.flatten() {
-                            entry_total += entry.unpacked_size;
+                            if selection
+                                .as_ref()
+                                .is_none_or(|selection| selection.contains(&entry.filename))
+                            {
+                                entry_total += entry.unpacked_size;
+                            }
                         }
 
                         total.store(entry_total, Ordering::Relaxed);
@@ -687,7 +726,11 @@ impl Archive {
                         };
 
                         let path = &entry.entry().filename;
-                        if path.is_absolute() {
+                        if path.is_absolute()
+                            || selection
+                                .as_ref()
+                                .is_some_and(|selection| !selection.contains(path))
+                        {
                             archive = entry.skip()?;
                             continue;
                         }
@@ -779,13 +822,33 @@ impl Archive {
                     let password = sevenz_rust2::Password::empty();
                     let archive = sevenz_rust2::Archive::read(&mut reader.clone(), &password)?;
 
+                    let is_selected = |entry: &sevenz_rust2::ArchiveEntry| {
+                        selection
+                            .as_ref()
+                            .is_none_or(|selection| selection.contains(Path::new(entry.name())))
+                    };
+
                     if let Some(total) = total {
                         total.store(
-                            archive.files.iter().map(|f| f.size).sum(),
+                            archive
+                                .files
+                                .iter()
+                                .filter(|f| is_selected(f))
+                                .map(|f| f.size)
+                                .sum(),
                             Ordering::Relaxed,
                         );
                     }
 
+                    // blocks without any selected entries do not have to be decoded
+                    let blocks: std::collections::HashSet<usize> = archive
+                        .files
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, f)| is_selected(f))
+                        .filter_map(|(i, _)| archive.stream_map.file_block_index[i])
+                        .collect();
+
                     let pool = rayon::ThreadPoolBuilder::new()
                         .num_threads(self.server.app_state.config.api.file_decompression_threads)
                         .build()
@@ -795,11 +858,16 @@ impl Archive {
 
                     pool.in_place_scope(|scope| {
                         for block_index in 0..archive.blocks.len() {
+                            if !blocks.contains(&block_index) {
+                                continue;
+                            }
+
                             let archive = archive.clone();
                             let progress = progress.clone();
                             let mut reader = reader.clone();
                             let destination = destination.clone();
                             let server = self.server.clone();
+                            let selection = selection.clone();
                             let error_clone = Arc::clone(&error);
 
                             scope.spawn(move |_| {
@@ -819,7 +887,12 @@ impl Archive {
                                 let mut read_buffer = vec![0; crate::BUFFER_SIZE];
                                 if let Err(err) = folder.for_each_entries(&mut |entry, reader| {
                                     let path = entry.name();
-                                    if path.starts_with('/') || path.starts_with('\\') {
+                                    if path.starts_with('/')
+                                        || path.starts_with('\\')
+                                        || selection.as_ref().is_some_and(|selection| {
+                                            !selection.contains(Path::new(path))
+                                        })
+                                    {
                                         return Ok(true);
                                     }
 
@@ -893,8 +966,11 @@ impl Archive {
                     if let Some(err) = error.write().unwrap().take() {
                         Err(err.into())
                     } else {
-                        for entry in archive.files {
-                            if entry.is_directory() && entry.has_last_modified_date {
+                        for entry in archive.files.iter() {
+                            if entry.is_directory()
+                                && entry.has_last_modified_date
+                                && is_selected(entry)
+                            {
                                 let path = entry.name();
                                 if path.starts_with('/') || path.starts_with('\\') {
                                     continue;
@@ -934,18 +1010,36 @@ impl Archive {
                     let archive = ddup_bak::archive::Archive::open_file(file)?;
 
                     if let Some(total) = total {
-                        fn recursive_size(entry: &ddup_bak::archive::entries::Entry) -> u64 {
+                        fn recursive_size(
+                            selection: &Option<entries::ArchiveSelection>,
+                            parent: &Path,
+                            entry: &ddup_bak::archive::entries::Entry,
+                        ) -> u64 {
+                            let path = parent.join(entry.name());
+
                             match entry {
-                                ddup_bak::archive::entries::Entry::File(file) => file.size,
-                                ddup_bak::archive::entries::Entry::Directory(dir) => {
-                                    dir.entries.iter().map(recursive_size).sum()
+                                ddup_bak::archive::entries::Entry::File(file)
+                                    if selection
+                                        .as_ref()
+                                        .is_none_or(|selection| selection.contains(&path)) =>
+                                {
+                                    file.size
                                 }
+                                ddup_bak::archive::entries::Entry::Directory(dir) => dir
+                                    .entries
+                                    .iter()
+                                    .map(|entry| recursive_size(selection, &path, entry))
+                                    .sum(),
                                 _ => 0,
                             }
                         }
 
                         total.store(
-                            archive.entries().iter().map(recursive_size).sum(),
+                            archive
+                                .entries()
+                                .iter()
+                                .map(|entry| recursive_size(&selection, Path::new(""), entry))
+                                .sum(),
                             Ordering::Relaxed,
                         );
                     }
@@ -955,14 +1049,18 @@ impl Archive {
                         .build()
                         .unwrap();
 
+                    #[allow(clippy::too_many_arguments)]
                     fn recursive_traverse(
                         scope: &rayon::Scope,
                         listener: &AbortListener,
                         progress: &Option<Arc<AtomicU64>>,
+                        selection: &Option<entries::ArchiveSelection>,
                         server: &crate::server::Server,
+                        parent: &Path,
                         destination: &Path,
                         entry: ddup_bak::archive::entries::Entry,
                     ) -> Result<(), anyhow::Error> {
+                        let path = parent.join(entry.name());
                         let destination_path = destination.join(entry.name());
                         if server
                             .filesystem
@@ -975,6 +1073,32 @@ impl Archive {
                             return Err(anyhow::anyhow!("operation aborted"));
                         }
 
+                        if let Some(selected) = selection
+                            && !selected.contains(&path)
+                        {
+                            // directories above a selected entry are created, but not restored
+                            if let ddup_bak::archive::entries::Entry::Directory(dir) = entry
+                                && selected.leads_to(&path)
+                            {
+                                server.filesystem.create_dir_all(&destination_path)?;
+
+                                for entry in dir.entries {
+                                    recursive_traverse(
+                                        scope,
+                                        listener,
+                                        progress,
+                                        selection,
+                                        server,
+                                        &path,
+                                        &destination_path,
+                                        entry,
+                                    )?;
+                                }
+                            }
+
+                            return Ok(());
+                        }
+
                         match entry {
                             ddup_bak::archive::entries::Entry::Directory(dir) => {
                                 server.filesystem.create_dir_all(&destination_path)?;
@@ -988,7 +1112,9 @@ impl Archive {
                                         scope,
                                         listener,
                                         progress,
+                                        selection,
                                         server,
+                                        &path,
                                         &destination_path,
                                         entry,
                                     )?;
@@ -1051,7 +1177,9 @@ impl Archive {
                                 scope,
                                 &listener,
                                 &progress,
+                                &selection,
                                 &self.server,
+                                Path::new(""),
                                 &destination,
                                 entry,
                             )?;
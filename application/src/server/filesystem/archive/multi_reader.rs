@@ -22,6 +22,16 @@ impl MultiReader {
             offset: 0,
         })
     }
+
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.file_size
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.file_size == 0
+    }
 }
 
 impl Read for MultiReader {
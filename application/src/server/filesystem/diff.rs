@@ -0,0 +1,407 @@
+use serde::Deserialize;
+use sha2::Digest;
+use utoipa::ToSchema;
+
+/// Files larger than this are not diffed or patched
+pub const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+/// Diffs needing more edits than this are reported as one replaced block,
+/// keeps the memory used for backtracking bounded
+const MAX_EDIT_DISTANCE: isize = 1024;
+
+#[derive(Debug)]
+pub enum PatchError {
+    Invalid(String),
+    Conflict(usize),
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(reason) => write!(f, "invalid patch: {reason}"),
+            Self::Conflict(hunk) => write!(f, "hunk {} does not apply", hunk + 1),
+        }
+    }
+}
+
+#[derive(ToSchema, Deserialize)]
+pub struct EditOperation {
+    /// The first line to replace, 0 based
+    pub start_line: usize,
+    /// The line after the last replaced line, equal to `start_line` to insert
+    pub end_line: usize,
+    /// The replacement text including its trailing newline, empty to delete the lines
+    #[serde(default)]
+    pub content: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[inline]
+pub fn hash(content: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(content.as_bytes()))
+}
+
+#[inline]
+fn lines(content: &str) -> Vec<&str> {
+    content.split_inclusive('\n').collect()
+}
+
+/// Myers' diff, returns `None` if the inputs differ by more than `MAX_EDIT_DISTANCE` lines
+fn myers(a: &[&str], b: &[&str]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = MAX_EDIT_DISTANCE.min(n + m);
+    let index = |k: isize| (k + limit) as usize;
+
+    let mut v = vec![0isize; 2 * limit as usize + 2];
+    let mut trace = Vec::new();
+
+    for d in 0..=limit {
+        trace.push(v.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[index(k)] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m, index));
+            }
+        }
+    }
+
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize, index: impl Fn(isize) -> usize) -> Vec<Op> {
+    let (mut x, mut y) = (n, m);
+    let mut ops = Vec::with_capacity((n + m) as usize);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+fn diff_ops(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    match myers(a_middle, b_middle) {
+        Some(middle) => ops.extend(middle),
+        None => {
+            ops.extend(std::iter::repeat_n(Op::Delete, a_middle.len()));
+            ops.extend(std::iter::repeat_n(Op::Insert, b_middle.len()));
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+
+    ops
+}
+
+fn push_line(output: &mut String, prefix: char, line: &str) {
+    output.push(prefix);
+    output.push_str(line);
+
+    if !line.ends_with('\n') {
+        output.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Creates a unified diff turning `old` into `new`, empty if both are equal
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let (a, b) = (lines(old), lines(new));
+    let ops = diff_ops(&a, &b);
+
+    // the position in both files before each op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        positions.push((i, j));
+
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&i| ops[i] != Op::Equal).collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!("--- {old_name}\n+++ {new_name}\n");
+    let mut change = 0;
+    while change < changes.len() {
+        let start = changes[change].saturating_sub(context);
+        let mut last = changes[change];
+
+        while change + 1 < changes.len() && changes[change + 1] - last <= 2 * context + 1 {
+            change += 1;
+            last = changes[change];
+        }
+        change += 1;
+
+        let end = (last + 1 + context).min(ops.len());
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let (old_count, new_count) = (old_end - old_start, new_end - new_start);
+
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            if old_count == 0 {
+                old_start
+            } else {
+                old_start + 1
+            },
+            old_count,
+            if new_count == 0 {
+                new_start
+            } else {
+                new_start + 1
+            },
+            new_count
+        ));
+
+        for (op, &(i, j)) in ops[start..end].iter().zip(&positions[start..end]) {
+            match op {
+                Op::Equal => push_line(&mut output, ' ', a[i]),
+                Op::Delete => push_line(&mut output, '-', a[i]),
+                Op::Insert => push_line(&mut output, '+', b[j]),
+            }
+        }
+    }
+
+    output
+}
+
+struct Hunk {
+    old_start: usize,
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn parse_hunks(patch: &str) -> Result<Vec<Hunk>, PatchError> {
+    let mut hunks = Vec::new();
+    let mut patch_lines = patch.split_inclusive('\n').peekable();
+
+    while let Some(line) = patch_lines.next() {
+        let Some(header) = line.strip_prefix("@@ ") else {
+            continue;
+        };
+
+        let mut ranges = header.split_whitespace();
+        let (old_start, old_count) = ranges
+            .next()
+            .and_then(|range| range.strip_prefix('-'))
+            .and_then(parse_range)
+            .ok_or_else(|| PatchError::Invalid(format!("bad hunk header {}", line.trim_end())))?;
+        let (_, new_count) = ranges
+            .next()
+            .and_then(|range| range.strip_prefix('+'))
+            .and_then(parse_range)
+            .ok_or_else(|| PatchError::Invalid(format!("bad hunk header {}", line.trim_end())))?;
+
+        let mut hunk = Hunk {
+            old_start: if old_count == 0 {
+                old_start
+            } else {
+                old_start.saturating_sub(1)
+            },
+            old_lines: Vec::with_capacity(old_count),
+            new_lines: Vec::with_capacity(new_count),
+        };
+
+        // which side the previous line went to, for "\ No newline at end of file"
+        let mut previous = (false, false);
+        while hunk.old_lines.len() < old_count
+            || hunk.new_lines.len() < new_count
+            || patch_lines
+                .peek()
+                .is_some_and(|line| line.starts_with('\\'))
+        {
+            let Some(line) = patch_lines.next() else {
+                return Err(PatchError::Invalid("unexpected end of hunk".to_string()));
+            };
+
+            let mut content = line.get(1..).unwrap_or_default().to_string();
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+
+            match line.chars().next() {
+                // some editors strip the space of empty context lines
+                Some(' ') | Some('\n') => {
+                    hunk.old_lines.push(content.clone());
+                    hunk.new_lines.push(content);
+                    previous = (true, true);
+                }
+                Some('-') => {
+                    hunk.old_lines.push(content);
+                    previous = (true, false);
+                }
+                Some('+') => {
+                    hunk.new_lines.push(content);
+                    previous = (false, true);
+                }
+                Some('\\') => {
+                    if previous.0
+                        && let Some(line) = hunk.old_lines.last_mut()
+                    {
+                        line.pop();
+                    }
+                    if previous.1
+                        && let Some(line) = hunk.new_lines.last_mut()
+                    {
+                        line.pop();
+                    }
+                }
+                _ => {
+                    return Err(PatchError::Invalid(format!(
+                        "unexpected line in hunk: {}",
+                        line.trim_end()
+                    )));
+                }
+            }
+        }
+
+        if hunk.old_lines.len() != old_count || hunk.new_lines.len() != new_count {
+            return Err(PatchError::Invalid(
+                "hunk line counts do not match its header".to_string(),
+            ));
+        }
+
+        hunks.push(hunk);
+    }
+
+    if hunks.is_empty() {
+        return Err(PatchError::Invalid("patch contains no hunks".to_string()));
+    }
+
+    Ok(hunks)
+}
+
+/// Applies a unified diff, every hunk has to match the base exactly at its position
+pub fn apply_unified_diff(base: &str, patch: &str) -> Result<String, PatchError> {
+    let base_lines = lines(base);
+    let hunks = parse_hunks(patch)?;
+
+    let mut output = String::with_capacity(base.len());
+    let mut cursor = 0;
+    for (index, hunk) in hunks.iter().enumerate() {
+        let end = hunk.old_start + hunk.old_lines.len();
+
+        if hunk.old_start < cursor
+            || end > base_lines.len()
+            || base_lines[hunk.old_start..end]
+                .iter()
+                .zip(&hunk.old_lines)
+                .any(|(base, old)| base != old)
+        {
+            return Err(PatchError::Conflict(index));
+        }
+
+        output.extend(base_lines[cursor..hunk.old_start].iter().copied());
+        output.extend(hunk.new_lines.iter().map(String::as_str));
+        cursor = end;
+    }
+    output.extend(base_lines[cursor..].iter().copied());
+
+    Ok(output)
+}
+
+/// Applies line range replacements, all ranges refer to the base and may not overlap
+pub fn apply_edits(base: &str, edits: &[EditOperation]) -> Result<String, PatchError> {
+    let base_lines = lines(base);
+
+    let mut edits: Vec<&EditOperation> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.start_line);
+
+    let mut output = String::with_capacity(base.len());
+    let mut cursor = 0;
+    for (index, edit) in edits.into_iter().enumerate() {
+        if edit.start_line > edit.end_line || edit.end_line > base_lines.len() {
+            return Err(PatchError::Invalid(format!(
+                "operation {} is out of range",
+                index + 1
+            )));
+        }
+        if edit.start_line < cursor {
+            return Err(PatchError::Invalid(format!(
+                "operation {} overlaps another operation",
+                index + 1
+            )));
+        }
+
+        output.extend(base_lines[cursor..edit.start_line].iter().copied());
+        output.push_str(&edit.content);
+        cursor = edit.end_line;
+    }
+    output.extend(base_lines[cursor..].iter().copied());
+
+    Ok(output)
+}
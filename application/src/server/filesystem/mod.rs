@@ -7,7 +7,7 @@ use std::{
     path::{Path, PathBuf},
     sync::{
         Arc,
-        atomic::{AtomicI64, AtomicU64, Ordering},
+        atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
     },
 };
 use tokio::{
@@ -17,10 +17,14 @@ use tokio::{
 
 pub mod archive;
 pub mod cap;
+pub mod diff;
 pub mod limiter;
 pub mod operations;
 pub mod pull;
+pub mod replace;
+pub mod trash;
 pub mod usage;
+pub mod watcher;
 pub mod writer;
 
 pub struct Filesystem {
@@ -38,6 +42,10 @@ pub struct Filesystem {
 
     pub pulls: RwLock<HashMap<uuid::Uuid, Arc<RwLock<pull::Download>>>>,
     pub operations: operations::OperationManager,
+    /// Held while a patch checks its base hash and replaces the file
+    pub patch_lock: Mutex<()>,
+    /// Directories currently watched by websocket file watchers
+    pub watched_directories: AtomicUsize,
 }
 
 impl Filesystem {
@@ -181,6 +189,8 @@ impl Filesystem {
 
             pulls: RwLock::new(HashMap::new()),
             operations: operations::OperationManager::new(sender),
+            patch_lock: Mutex::new(()),
+            watched_directories: AtomicUsize::new(0),
         }
     }
 
@@ -982,3 +992,81 @@ impl Deref for Filesystem {
         &self.cap_filesystem
     }
 }
+
+/// Builds a matcher from gitignore style patterns that are relative to `root`
+pub fn build_gitignore(
+    root: &Path,
+    patterns: &[String],
+) -> Result<ignore::gitignore::Gitignore, ignore::Error> {
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder.add_line(None, pattern)?;
+    }
+
+    builder.build()
+}
+
+/// Replaces the contents of an existing file through a temporary file in the same directory,
+/// keeping its permissions and preserving the old contents in the trash. Returns `false`
+/// without touching the file if the additional space can not be allocated
+pub async fn replace_file_contents(
+    server: &crate::server::Server,
+    path: &Path,
+    content: Vec<u8>,
+) -> Result<bool, anyhow::Error> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("file has no parent"))?;
+    let metadata = server.filesystem.async_metadata(path).await?;
+
+    let delta = content.len() as i64 - metadata.len() as i64;
+    if !server
+        .filesystem
+        .async_allocate_in_path(parent, delta, false)
+        .await
+    {
+        return Ok(false);
+    }
+
+    if let Err(err) = server.trash.preserve_file(server, path).await {
+        tracing::warn!(
+            server = %server.uuid,
+            path = %path.display(),
+            "failed to preserve overwritten file in trash: {:#?}",
+            err
+        );
+    }
+
+    let tmp_path = parent.join(format!(
+        ".{}.{}.tmp",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        uuid::Uuid::new_v4()
+    ));
+
+    let result = async {
+        server.filesystem.async_write(&tmp_path, content).await?;
+        server
+            .filesystem
+            .async_set_permissions(&tmp_path, metadata.permissions())
+            .await?;
+        server
+            .filesystem
+            .async_rename(&tmp_path, &server.filesystem, path)
+            .await
+    }
+    .await;
+
+    if let Err(err) = result {
+        server.filesystem.async_remove_file(&tmp_path).await.ok();
+        server
+            .filesystem
+            .async_allocate_in_path(parent, -delta, true)
+            .await;
+
+        return Err(err);
+    }
+
+    server.filesystem.chown_path(path).await?;
+
+    Ok(true)
+}
@@ -36,6 +36,14 @@ pub enum FilesystemOperation {
     Pull {
         path: PathBuf,
 
+        #[serde(serialize_with = "serialize_arc")]
+        progress: Arc<AtomicU64>,
+        #[serde(serialize_with = "serialize_arc")]
+        total: Arc<AtomicU64>,
+    },
+    Replace {
+        path: PathBuf,
+
         #[serde(serialize_with = "serialize_arc")]
         progress: Arc<AtomicU64>,
         #[serde(serialize_with = "serialize_arc")]
@@ -73,6 +81,38 @@ impl OperationManager {
     ) -> (
         uuid::Uuid,
         tokio::task::JoinHandle<Option<Result<T, anyhow::Error>>>,
+    ) {
+        self.spawn_operation(operation, f, |_| None).await
+    }
+
+    /// Like `add_operation`, but the result of the operation is sent as json
+    /// with the completion event, for operations that run in the background
+    pub async fn add_operation_with_result<
+        T: Serialize + Send + 'static,
+        F: Future<Output = Result<T, anyhow::Error>> + Send + 'static,
+    >(
+        &self,
+        operation: FilesystemOperation,
+        f: F,
+    ) -> (
+        uuid::Uuid,
+        tokio::task::JoinHandle<Option<Result<T, anyhow::Error>>>,
+    ) {
+        self.spawn_operation(operation, f, |result| serde_json::to_string(result).ok())
+            .await
+    }
+
+    async fn spawn_operation<
+        T: Send + 'static,
+        F: Future<Output = Result<T, anyhow::Error>> + Send + 'static,
+    >(
+        &self,
+        operation: FilesystemOperation,
+        f: F,
+        serialize_result: fn(&T) -> Option<String>,
+    ) -> (
+        uuid::Uuid,
+        tokio::task::JoinHandle<Option<Result<T, anyhow::Error>>>,
     ) {
         let operation_uuid = uuid::Uuid::new_v4();
         let (abort_sender, abort_receiver) = tokio::sync::oneshot::channel();
@@ -140,10 +180,17 @@ impl OperationManager {
                         ))
                         .ok();
                 } else {
+                    let mut args = vec![operation_uuid.to_string()];
+                    if let Some(Ok(result)) = result.as_ref()
+                        && let Some(result) = serialize_result(result)
+                    {
+                        args.push(result);
+                    }
+
                     sender
                         .send(crate::server::websocket::WebsocketMessage::new(
                             crate::server::websocket::WebsocketEvent::ServerOperationCompleted,
-                            &[operation_uuid.to_string()],
+                            &args,
                         ))
                         .ok();
                 }
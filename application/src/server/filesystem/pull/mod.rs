@@ -2,7 +2,6 @@ use anyhow::Context;
 use rand::Rng;
 use std::{
     path::{Path, PathBuf},
-    str::FromStr,
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
@@ -14,7 +13,7 @@ mod resolver;
 
 static DOWNLOAD_CLIENT: RwLock<Option<Arc<reqwest::Client>>> = RwLock::const_new(None);
 
-async fn get_download_client(
+pub(crate) async fn get_download_client(
     config: &Arc<crate::config::Config>,
 ) -> Result<Arc<reqwest::Client>, anyhow::Error> {
     let client = DOWNLOAD_CLIENT.read().await;
@@ -29,6 +28,7 @@ async fn get_download_client(
         .user_agent("Pterodactyl Panel (https://pterodactyl.io)")
         .connect_timeout(std::time::Duration::from_secs(30))
         .dns_resolver(Arc::new(resolver::DnsResolver::new(config)))
+        .redirect(redirect_policy(config))
         .build()
         .context("failed to build download client")?;
 
@@ -38,6 +38,48 @@ async fn get_download_client(
     Ok(new_client)
 }
 
+/// Returns the ip address of `host` if it is an ip literal inside one of the blocked cidrs
+pub(crate) fn is_blocked_ip(
+    config: &crate::config::Config,
+    host: &str,
+) -> Option<std::net::IpAddr> {
+    let ip = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<std::net::IpAddr>()
+        .ok()?;
+
+    config
+        .api
+        .remote_download_blocked_cidrs
+        .iter()
+        .any(|cidr| cidr.contains(&ip))
+        .then_some(ip)
+}
+
+/// Follows up to 10 redirects, refusing any hop to a blocked ip address,
+/// hostnames are already checked by the dns resolver
+fn redirect_policy(config: &Arc<crate::config::Config>) -> reqwest::redirect::Policy {
+    let config = Arc::clone(config);
+
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 {
+            return attempt.error("too many redirects");
+        }
+
+        if let Some(ip) = attempt
+            .url()
+            .host_str()
+            .and_then(|host| is_blocked_ip(&config, host))
+        {
+            tracing::warn!("blocking redirect to internal IP address: {}", ip);
+            return attempt.error(format!("IP address {ip} is blocked"));
+        }
+
+        attempt.follow()
+    })
+}
+
 pub struct Download {
     pub identifier: uuid::Uuid,
     pub progress: Arc<AtomicU64>,
@@ -57,21 +99,12 @@ impl Download {
     ) -> Result<Self, anyhow::Error> {
         let url = reqwest::Url::parse(&url).context("failed to parse download URL")?;
 
-        if let Some(host) = url.host_str()
-            && let Ok(ip) = std::net::IpAddr::from_str(host)
+        if let Some(ip) = url
+            .host_str()
+            .and_then(|host| is_blocked_ip(&server.app_state.config, host))
         {
-            for cidr in server
-                .app_state
-                .config
-                .api
-                .remote_download_blocked_cidrs
-                .iter()
-            {
-                if cidr.contains(&ip) {
-                    tracing::warn!("blocking internal IP address in pull: {}", ip);
-                    return Err(anyhow::anyhow!("IP address {} is blocked", ip));
-                }
-            }
+            tracing::warn!("blocking internal IP address in pull: {}", ip);
+            return Err(anyhow::anyhow!("IP address {} is blocked", ip));
         }
 
         let response = get_download_client(&server.app_state.config)
@@ -0,0 +1,198 @@
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tokio::{io::AsyncReadExt, sync::Mutex};
+use utoipa::ToSchema;
+
+/// At most this many changed line numbers are reported per file
+const MAX_REPORTED_LINES: usize = 100;
+
+pub struct ReplaceOptions {
+    pub regex: regex::Regex,
+    pub replacement: String,
+    /// Whether `$1` style references in the replacement are expanded
+    pub expand: bool,
+
+    /// Gitignore style patterns relative to the root, only matching files are changed
+    pub include: Option<ignore::gitignore::Gitignore>,
+    pub max_size: u64,
+    pub dry_run: bool,
+    pub threads: usize,
+}
+
+#[derive(ToSchema, Serialize)]
+pub struct FileChange {
+    pub file: String,
+    pub replacements: usize,
+    /// The lines containing a match, 1 based
+    pub lines: Vec<usize>,
+    pub size_delta: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Replaces every match of the options' pattern in the text files below `root`,
+/// `ignored` decides which paths are never touched
+pub async fn replace(
+    server: &crate::server::Server,
+    root: &Path,
+    ignored: &[ignore::gitignore::Gitignore],
+    options: ReplaceOptions,
+    progress: Arc<AtomicU64>,
+) -> Result<Vec<FileChange>, anyhow::Error> {
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let options = Arc::new(options);
+
+    let mut walker = server
+        .filesystem
+        .async_walk_dir(root)
+        .await?
+        .with_ignored(ignored);
+
+    walker
+        .run_multithreaded(
+            options.threads,
+            Arc::new({
+                let server = server.clone();
+                let changes = Arc::clone(&changes);
+                let root = Arc::new(root.to_path_buf());
+
+                move |is_dir, path: PathBuf| {
+                    let server = server.clone();
+                    let changes = Arc::clone(&changes);
+                    let options = Arc::clone(&options);
+                    let progress = Arc::clone(&progress);
+                    let root = Arc::clone(&root);
+
+                    async move {
+                        if is_dir {
+                            return Ok(());
+                        }
+
+                        let relative = match path.strip_prefix(root.as_ref()) {
+                            Ok(relative) => relative.to_path_buf(),
+                            Err(_) => return Ok(()),
+                        };
+
+                        if let Some(include) = &options.include
+                            && !include
+                                .matched_path_or_any_parents(&relative, false)
+                                .is_ignore()
+                        {
+                            return Ok(());
+                        }
+
+                        let metadata = match server.filesystem.async_symlink_metadata(&path).await {
+                            Ok(metadata) => metadata,
+                            Err(_) => return Ok(()),
+                        };
+
+                        if !metadata.is_file() {
+                            return Ok(());
+                        }
+
+                        progress.fetch_add(metadata.len(), Ordering::Relaxed);
+
+                        if metadata.len() > options.max_size {
+                            return Ok(());
+                        }
+
+                        if let Some(change) = replace_file(
+                            &server,
+                            &path,
+                            relative.to_string_lossy().to_string(),
+                            &options,
+                        )
+                        .await
+                        {
+                            changes.lock().await.push(change);
+                        }
+
+                        Ok(())
+                    }
+                }
+            }),
+        )
+        .await?;
+
+    let mut changes = std::mem::take(&mut *changes.lock().await);
+    changes.sort_by(|a, b| a.file.cmp(&b.file));
+
+    Ok(changes)
+}
+
+async fn replace_file(
+    server: &crate::server::Server,
+    path: &Path,
+    name: String,
+    options: &ReplaceOptions,
+) -> Option<FileChange> {
+    let _guard = if options.dry_run {
+        None
+    } else {
+        Some(server.filesystem.patch_lock.lock().await)
+    };
+
+    let file = server.filesystem.async_open(path).await.ok()?;
+    let mut content = Vec::new();
+    file.take(options.max_size)
+        .read_to_end(&mut content)
+        .await
+        .ok()?;
+
+    if !crate::is_valid_utf8_slice(&content[..content.len().min(128)]) {
+        return None;
+    }
+    let content = String::from_utf8(content).ok()?;
+
+    let mut replacements = 0;
+    let mut lines = Vec::new();
+    let (mut line, mut position) = (1, 0);
+    for found in options.regex.find_iter(&content) {
+        line += content[position..found.start()].matches('\n').count();
+        position = found.start();
+        replacements += 1;
+
+        if lines.last() != Some(&line) && lines.len() < MAX_REPORTED_LINES {
+            lines.push(line);
+        }
+    }
+
+    if replacements == 0 {
+        return None;
+    }
+
+    let replaced = if options.expand {
+        options
+            .regex
+            .replace_all(&content, options.replacement.as_str())
+    } else {
+        options
+            .regex
+            .replace_all(&content, regex::NoExpand(&options.replacement))
+    };
+
+    let mut change = FileChange {
+        file: name,
+        replacements,
+        lines,
+        size_delta: replaced.len() as i64 - content.len() as i64,
+        error: None,
+    };
+
+    if !options.dry_run {
+        match super::replace_file_contents(server, path, replaced.into_owned().into_bytes()).await {
+            Ok(true) => {}
+            Ok(false) => change.error = Some("failed to allocate space".to_string()),
+            Err(err) => change.error = Some(err.to_string()),
+        }
+    }
+
+    Some(change)
+}
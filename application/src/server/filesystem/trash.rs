@@ -0,0 +1,631 @@
+use super::cap::CapFilesystem;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::sync::{Mutex, MutexGuard};
+use utoipa::ToSchema;
+
+#[derive(ToSchema, Clone, Debug, Deserialize, Serialize)]
+pub struct TrashPolicy {
+    /// Days a trashed entry is kept before it is purged, 0 keeps entries until the size cap is hit
+    #[serde(default)]
+    pub retention_days: u64,
+    /// MiB, the oldest entries are purged once the trash grows past this size, 0 for unlimited
+    #[serde(default)]
+    pub max_size: u64,
+}
+
+impl TrashPolicy {
+    #[inline]
+    fn max_size_bytes(&self) -> Option<u64> {
+        if self.max_size == 0 {
+            None
+        } else {
+            Some(self.max_size * 1024 * 1024)
+        }
+    }
+}
+
+#[derive(ToSchema, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashReason {
+    Deleted,
+    Overwritten,
+}
+
+#[derive(ToSchema, Clone, Debug, Deserialize, Serialize)]
+pub struct TrashEntry {
+    pub uuid: uuid::Uuid,
+    /// The original path, relative to the server root
+    pub path: String,
+    pub reason: TrashReason,
+    pub directory: bool,
+    pub size: u64,
+    pub created: chrono::DateTime<chrono::Utc>,
+}
+
+pub struct Trash {
+    directory: PathBuf,
+    entries: Mutex<Option<Vec<TrashEntry>>>,
+}
+
+impl Trash {
+    pub fn new(server: uuid::Uuid, config: &crate::config::Config) -> Self {
+        Self {
+            directory: Path::new(&config.system.trash_directory).join(server.to_string()),
+            entries: Mutex::new(None),
+        }
+    }
+
+    #[inline]
+    fn index_path(&self) -> PathBuf {
+        self.directory.join("index.json")
+    }
+
+    async fn filesystem(&self) -> Result<CapFilesystem, anyhow::Error> {
+        let path = self.directory.join("files");
+        tokio::fs::create_dir_all(&path).await?;
+
+        Ok(CapFilesystem::new(path).await?)
+    }
+
+    /// Locks the index, reading it from disk on first use
+    async fn lock(&self) -> tokio::sync::MappedMutexGuard<'_, Vec<TrashEntry>> {
+        let mut entries = self.entries.lock().await;
+
+        if entries.is_none() {
+            let loaded = match tokio::fs::read(self.index_path()).await {
+                Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|err| {
+                    tracing::error!(
+                        path = %self.index_path().display(),
+                        "failed to parse trash index: {:#?}",
+                        err
+                    );
+
+                    Vec::new()
+                }),
+                Err(_) => Vec::new(),
+            };
+
+            *entries = Some(loaded);
+        }
+
+        MutexGuard::map(entries, |entries| entries.get_or_insert_default())
+    }
+
+    async fn save(&self, entries: &[TrashEntry]) -> Result<(), anyhow::Error> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let tmp_path = self.directory.join("index.json.tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec(entries)?).await?;
+        tokio::fs::rename(tmp_path, self.index_path()).await?;
+
+        Ok(())
+    }
+
+    pub async fn entries(&self) -> Vec<TrashEntry> {
+        self.lock().await.clone()
+    }
+
+    pub async fn entry(&self, uuid: uuid::Uuid) -> Option<TrashEntry> {
+        self.lock()
+            .await
+            .iter()
+            .find(|entry| entry.uuid == uuid)
+            .cloned()
+    }
+
+    /// Moves a path into the trash, or deletes it permanently if the trash is disabled for this server
+    pub async fn remove_path(
+        &self,
+        server: &crate::server::Server,
+        path: impl AsRef<Path>,
+    ) -> Result<(), anyhow::Error> {
+        let policy = match server.configuration.read().await.trash.clone() {
+            Some(policy) => policy,
+            None => return server.filesystem.truncate_path(path).await,
+        };
+
+        let path = server.filesystem.relative_path(path.as_ref());
+        let metadata = server.filesystem.async_symlink_metadata(&path).await?;
+
+        let size = if metadata.is_dir() {
+            directory_size(&server.filesystem, &path).await?
+        } else {
+            metadata.len()
+        };
+
+        if policy
+            .max_size_bytes()
+            .is_some_and(|max_size| size > max_size)
+        {
+            return server.filesystem.truncate_path(&path).await;
+        }
+
+        let mut entries = self.lock().await;
+        let entry = TrashEntry {
+            uuid: uuid::Uuid::new_v4(),
+            path: path.to_string_lossy().to_string(),
+            reason: TrashReason::Deleted,
+            directory: metadata.is_dir(),
+            size,
+            created: chrono::Utc::now(),
+        };
+
+        let filesystem = self.filesystem().await?;
+        if let Err(err) = transfer(
+            &server.filesystem,
+            &path,
+            &filesystem,
+            Path::new(&entry.uuid.to_string()),
+        )
+        .await
+        {
+            tracing::warn!(
+                server = %server.uuid,
+                path = %path.display(),
+                "failed to move path into trash, deleting it instead: {:#?}",
+                err
+            );
+
+            drop(entries);
+            return server.filesystem.truncate_path(&path).await;
+        }
+
+        server
+            .filesystem
+            .async_allocate_in_path(&path, -(size as i64), false)
+            .await;
+
+        if metadata.is_dir() {
+            let mut disk_usage = server.filesystem.disk_usage.write().await;
+            disk_usage.remove_path(&path);
+        }
+
+        entries.push(entry);
+        prune_entries(&filesystem, &mut entries, &policy).await;
+        self.save(&entries).await
+    }
+
+    /// Copies a file into the trash before it gets overwritten, does nothing if the trash is disabled
+    pub async fn preserve_file(
+        &self,
+        server: &crate::server::Server,
+        path: impl AsRef<Path>,
+    ) -> Result<(), anyhow::Error> {
+        let policy = match server.configuration.read().await.trash.clone() {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+
+        let path = server.filesystem.relative_path(path.as_ref());
+        let metadata = match server.filesystem.async_symlink_metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return Ok(()),
+        };
+
+        if policy
+            .max_size_bytes()
+            .is_some_and(|max_size| metadata.len() > max_size)
+        {
+            return Ok(());
+        }
+
+        let mut entries = self.lock().await;
+        let filesystem = self.filesystem().await?;
+
+        preserve_copy(
+            &server.filesystem,
+            &path,
+            metadata.len(),
+            &filesystem,
+            &mut entries,
+        )
+        .await?;
+        prune_entries(&filesystem, &mut entries, &policy).await;
+        self.save(&entries).await
+    }
+
+    /// Moves an entry back into the server volume, replacing an existing file at the destination
+    /// (which is preserved in the trash first). Space has to be checked by the caller, the restored
+    /// data is allocated ignoring the disk limit
+    pub async fn restore(
+        &self,
+        server: &crate::server::Server,
+        uuid: uuid::Uuid,
+        destination: impl AsRef<Path>,
+    ) -> Result<(), anyhow::Error> {
+        let destination = server.filesystem.relative_path(destination.as_ref());
+        let policy = server.configuration.read().await.trash.clone();
+
+        let mut entries = self.lock().await;
+        let filesystem = self.filesystem().await?;
+
+        let result = restore_entry(
+            &filesystem,
+            &mut entries,
+            policy.as_ref(),
+            &server.filesystem,
+            uuid,
+            &destination,
+        )
+        .await;
+        self.save(&entries).await?;
+        let (entry, replaced_size) = result?;
+
+        server
+            .filesystem
+            .async_allocate_in_path(&destination, entry.size as i64 - replaced_size as i64, true)
+            .await;
+        server.filesystem.chown_path(&destination).await?;
+
+        Ok(())
+    }
+
+    /// Permanently deletes a single entry, or all of them, returns the amount of purged entries
+    pub async fn purge(&self, uuid: Option<uuid::Uuid>) -> Result<usize, anyhow::Error> {
+        let mut entries = self.lock().await;
+        let filesystem = self.filesystem().await?;
+
+        let purged: Vec<TrashEntry> = match uuid {
+            Some(uuid) => entries
+                .iter()
+                .position(|entry| entry.uuid == uuid)
+                .map(|index| entries.remove(index))
+                .into_iter()
+                .collect(),
+            None => std::mem::take(&mut *entries),
+        };
+
+        for entry in &purged {
+            if let Err(err) = remove_entry(&filesystem, entry.uuid).await {
+                tracing::error!(
+                    path = %self.directory.display(),
+                    entry = %entry.uuid,
+                    "failed to purge trash entry: {:#?}",
+                    err
+                );
+            }
+        }
+
+        self.save(&entries).await?;
+
+        Ok(purged.len())
+    }
+
+    /// Applies the retention policy and removes leftovers that are no longer in the index,
+    /// everything is purged when the trash has been disabled for this server
+    pub async fn prune(&self, server: &crate::server::Server) -> Result<(), anyhow::Error> {
+        let policy = match server.configuration.read().await.trash.clone() {
+            Some(policy) => policy,
+            None => {
+                if tokio::fs::try_exists(&self.directory).await? {
+                    self.destroy().await;
+                }
+
+                return Ok(());
+            }
+        };
+
+        let mut entries = self.lock().await;
+        let filesystem = self.filesystem().await?;
+
+        prune_entries(&filesystem, &mut entries, &policy).await;
+
+        for name in filesystem.async_read_dir_all("").await? {
+            let known = name
+                .parse::<uuid::Uuid>()
+                .is_ok_and(|uuid| entries.iter().any(|entry| entry.uuid == uuid));
+
+            if !known {
+                remove_path(&filesystem, Path::new(&name)).await?;
+            }
+        }
+
+        self.save(&entries).await
+    }
+
+    pub async fn destroy(&self) {
+        *self.entries.lock().await = None;
+
+        if let Err(err) = tokio::fs::remove_dir_all(&self.directory).await
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::error!(
+                path = %self.directory.display(),
+                "failed to delete trash: {:#?}",
+                err
+            );
+        }
+    }
+}
+
+/// Sums up the size of everything inside a directory, the cached disk usage can not be used
+/// since it is empty until the usage of the server has been calculated
+async fn directory_size(filesystem: &CapFilesystem, path: &Path) -> Result<u64, anyhow::Error> {
+    let mut walker = filesystem.async_walk_dir(path).await?;
+
+    let mut size = 0;
+    while let Some(Ok((is_dir, path))) = walker.next_entry().await {
+        if is_dir {
+            continue;
+        }
+
+        if let Ok(metadata) = filesystem.async_symlink_metadata(&path).await {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Copies a file of the server volume into the trash as an overwritten entry
+async fn preserve_copy(
+    volume: &CapFilesystem,
+    path: &Path,
+    size: u64,
+    filesystem: &CapFilesystem,
+    entries: &mut Vec<TrashEntry>,
+) -> Result<(), anyhow::Error> {
+    let entry = TrashEntry {
+        uuid: uuid::Uuid::new_v4(),
+        path: path.to_string_lossy().to_string(),
+        reason: TrashReason::Overwritten,
+        directory: false,
+        size,
+        created: chrono::Utc::now(),
+    };
+
+    volume
+        .async_copy(path, filesystem, entry.uuid.to_string())
+        .await?;
+    entries.push(entry);
+
+    Ok(())
+}
+
+/// Takes an entry out of the index and moves it back into the volume, the trash is only pruned
+/// afterwards so preserving the replaced file can not purge the entry that is being restored.
+/// Returns the restored entry and the size of the file it replaced
+async fn restore_entry(
+    filesystem: &CapFilesystem,
+    entries: &mut Vec<TrashEntry>,
+    policy: Option<&TrashPolicy>,
+    volume: &CapFilesystem,
+    uuid: uuid::Uuid,
+    destination: &Path,
+) -> Result<(TrashEntry, u64), anyhow::Error> {
+    let index = match entries.iter().position(|entry| entry.uuid == uuid) {
+        Some(index) => index,
+        None => return Err(anyhow::anyhow!("trash entry not found")),
+    };
+
+    let replaced_size = match volume.async_symlink_metadata(destination).await {
+        Ok(metadata) if metadata.is_file() && !entries[index].directory => metadata.len(),
+        Ok(_) => return Err(anyhow::anyhow!("restore destination already exists")),
+        Err(_) => 0,
+    };
+
+    if let Some(parent) = destination.parent() {
+        volume.async_create_dir_all(parent).await?;
+    }
+
+    let entry = entries.remove(index);
+
+    if replaced_size > 0
+        && let Some(policy) = policy
+        && policy
+            .max_size_bytes()
+            .is_none_or(|max_size| replaced_size <= max_size)
+        && let Err(err) =
+            preserve_copy(volume, destination, replaced_size, filesystem, entries).await
+    {
+        tracing::warn!(
+            path = %destination.display(),
+            "failed to preserve overwritten file in trash: {:#?}",
+            err
+        );
+    }
+
+    if let Err(err) = transfer(
+        filesystem,
+        Path::new(&uuid.to_string()),
+        volume,
+        destination,
+    )
+    .await
+    {
+        entries.push(entry);
+        return Err(err);
+    }
+
+    if let Some(policy) = policy {
+        prune_entries(filesystem, entries, policy).await;
+    }
+
+    Ok((entry, replaced_size))
+}
+
+/// Drops entries that are too old, then the oldest ones until the size cap is satisfied
+async fn prune_entries(
+    filesystem: &CapFilesystem,
+    entries: &mut Vec<TrashEntry>,
+    policy: &TrashPolicy,
+) {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(policy.retention_days as i64);
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created));
+
+    let mut total_size = 0;
+    let mut expired = Vec::new();
+    entries.retain(|entry| {
+        let too_old = policy.retention_days != 0 && entry.created < cutoff;
+        let too_large = policy
+            .max_size_bytes()
+            .is_some_and(|max_size| total_size + entry.size > max_size);
+
+        if too_old || too_large {
+            expired.push(entry.uuid);
+            return false;
+        }
+
+        total_size += entry.size;
+        true
+    });
+
+    for uuid in expired {
+        if let Err(err) = remove_entry(filesystem, uuid).await {
+            tracing::error!(
+                path = %filesystem.base_path.display(),
+                entry = %uuid,
+                "failed to purge expired trash entry: {:#?}",
+                err
+            );
+        }
+    }
+}
+
+#[inline]
+async fn remove_entry(filesystem: &CapFilesystem, uuid: uuid::Uuid) -> Result<(), anyhow::Error> {
+    remove_path(filesystem, Path::new(&uuid.to_string())).await
+}
+
+async fn remove_path(filesystem: &CapFilesystem, path: &Path) -> Result<(), anyhow::Error> {
+    match filesystem.async_symlink_metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => filesystem.async_remove_dir_all(path).await,
+        Ok(_) => filesystem.async_remove_file(path).await,
+        Err(_) => Ok(()),
+    }
+}
+
+/// Moves a path between two directories, copying it when they are on different filesystems
+/// (for example when the volume is its own btrfs subvolume or zfs dataset)
+async fn transfer(
+    from_dir: &CapFilesystem,
+    from: &Path,
+    to_dir: &CapFilesystem,
+    to: &Path,
+) -> Result<(), anyhow::Error> {
+    match from_dir.async_rename(from, to_dir, to).await {
+        Ok(()) => return Ok(()),
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::CrossesDevices) => {}
+        Err(err) => return Err(err),
+    }
+
+    let from = from_dir.relative_path(from);
+    let to = to_dir.relative_path(to);
+    let from_inner = from_dir.async_get_inner().await?;
+    let to_inner = to_dir.async_get_inner().await?;
+
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = copy_all(&from_inner, &from, &to_inner, &to) {
+            remove_all(&to_inner, &to).ok();
+            return Err(err);
+        }
+
+        remove_all(&from_inner, &from)
+    })
+    .await??;
+
+    Ok(())
+}
+
+fn copy_all(
+    from_dir: &cap_std::fs::Dir,
+    from: &Path,
+    to_dir: &cap_std::fs::Dir,
+    to: &Path,
+) -> Result<(), std::io::Error> {
+    let metadata = from_dir.symlink_metadata(from)?;
+
+    if metadata.is_dir() {
+        to_dir.create_dir(to)?;
+
+        for entry in from_dir.read_dir(from)? {
+            let name = entry?.file_name();
+            copy_all(from_dir, &from.join(&name), to_dir, &to.join(&name))?;
+        }
+    } else if metadata.is_symlink() {
+        to_dir.symlink(from_dir.read_link_contents(from)?, to)?;
+    } else {
+        from_dir.copy(from, to_dir, to)?;
+    }
+
+    Ok(())
+}
+
+fn remove_all(dir: &cap_std::fs::Dir, path: &Path) -> Result<(), std::io::Error> {
+    if dir.symlink_metadata(path)?.is_dir() {
+        dir.remove_dir_all(path)
+    } else {
+        dir.remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn restore_keeps_entry_while_over_size_cap() {
+        let root = std::env::temp_dir().join(format!("wings-trash-{}", uuid::Uuid::new_v4()));
+        let trash_path = root.join("trash");
+        let volume_path = root.join("volume");
+        std::fs::create_dir_all(&trash_path).unwrap();
+        std::fs::create_dir_all(&volume_path).unwrap();
+
+        let filesystem = CapFilesystem::new(trash_path.clone()).await.unwrap();
+        let volume = CapFilesystem::new(volume_path.clone()).await.unwrap();
+
+        let policy = TrashPolicy {
+            retention_days: 0,
+            max_size: 1,
+        };
+        let trashed = TrashEntry {
+            uuid: uuid::Uuid::new_v4(),
+            path: "world.dat".to_string(),
+            reason: TrashReason::Deleted,
+            directory: false,
+            size: 768 * 1024,
+            created: chrono::Utc::now() - chrono::Duration::hours(1),
+        };
+        filesystem
+            .async_write(trashed.uuid.to_string(), vec![1; 768 * 1024])
+            .await
+            .unwrap();
+        volume
+            .async_write("world.dat", vec![2; 512 * 1024])
+            .await
+            .unwrap();
+
+        // preserving the replaced file pushes the trash past its 1 MiB cap
+        let mut entries = vec![trashed.clone()];
+        let (restored, replaced_size) = restore_entry(
+            &filesystem,
+            &mut entries,
+            Some(&policy),
+            &volume,
+            trashed.uuid,
+            Path::new("world.dat"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(restored.uuid, trashed.uuid);
+        assert_eq!(replaced_size, 512 * 1024);
+        assert_eq!(
+            std::fs::read(volume_path.join("world.dat")).unwrap(),
+            vec![1; 768 * 1024]
+        );
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].reason, TrashReason::Overwritten);
+        assert_eq!(
+            std::fs::read(trash_path.join(entries[0].uuid.to_string())).unwrap(),
+            vec![2; 512 * 1024]
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
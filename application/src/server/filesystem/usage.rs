@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 #[derive(Default)]
 pub struct DiskUsage {
@@ -8,8 +11,12 @@ pub struct DiskUsage {
 
 impl DiskUsage {
     pub fn get_size(&self, path: &Path) -> Option<u64> {
+        self.get_entry(path).map(|usage| usage.size)
+    }
+
+    pub fn get_entry(&self, path: &Path) -> Option<&DiskUsage> {
         if crate::unlikely(path == Path::new("") || path == Path::new("/")) {
-            return Some(self.size);
+            return Some(self);
         }
 
         let mut current = self;
@@ -21,7 +28,21 @@ impl DiskUsage {
             }
         }
 
-        Some(current.size)
+        Some(current)
+    }
+
+    /// Collects the sizes of all directories below this one, down to `depth` levels
+    pub fn collect_directories(&self, parent: &Path, depth: usize, out: &mut Vec<(PathBuf, u64)>) {
+        if depth == 0 {
+            return;
+        }
+
+        for (name, usage) in &self.entries {
+            let path = parent.join(name);
+            usage.collect_directories(&path, depth - 1, out);
+
+            out.push((path, usage.size));
+        }
     }
 
     pub fn update_size(&mut self, path: &Path, delta: i64) {
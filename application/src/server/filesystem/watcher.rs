@@ -0,0 +1,403 @@
+use rustix::fs::inotify;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::MaybeUninit,
+    os::fd::{AsFd, AsRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+use tokio::io::unix::AsyncFd;
+
+/// Changes arriving within this window after the first one are sent together
+const DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(250);
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Serialize)]
+pub struct FileChange {
+    pub kind: FileChangeKind,
+    pub path: String,
+    pub directory: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+struct RawEvent {
+    wd: i32,
+    flags: inotify::ReadFlags,
+    cookie: u32,
+    name: Option<String>,
+}
+
+/// Watches a directory of a server recursively using inotify, every watched
+/// directory counts towards the server's `websocket_watch_limit`
+pub struct FileWatcher {
+    server: crate::server::Server,
+    root: PathBuf,
+
+    inotify: AsyncFd<OwnedFd>,
+    watches: HashMap<i32, PathBuf>,
+    limit_reached: bool,
+}
+
+impl FileWatcher {
+    pub async fn new(server: &crate::server::Server, root: PathBuf) -> Result<Self, anyhow::Error> {
+        let inotify =
+            inotify::init(inotify::CreateFlags::NONBLOCK | inotify::CreateFlags::CLOEXEC)?;
+
+        let mut watcher = Self {
+            server: server.clone(),
+            root: root.clone(),
+            inotify: AsyncFd::new(inotify)?,
+            watches: HashMap::new(),
+            limit_reached: false,
+        };
+
+        if !watcher.add_watch(&root).await? {
+            return Err(anyhow::anyhow!("file watch limit reached"));
+        }
+        watcher.watch_children(root).await;
+
+        Ok(watcher)
+    }
+
+    /// Returns whether directories were skipped because of the watch limit since the last call
+    #[inline]
+    pub fn take_limit_reached(&mut self) -> bool {
+        std::mem::take(&mut self.limit_reached)
+    }
+
+    async fn add_watch(&mut self, path: &Path) -> Result<bool, anyhow::Error> {
+        let limit = self.server.app_state.config.system.websocket_watch_limit;
+        if self
+            .server
+            .filesystem
+            .watched_directories
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |watched| {
+                (watched < limit).then_some(watched + 1)
+            })
+            .is_err()
+        {
+            self.limit_reached = true;
+            return Ok(false);
+        }
+
+        let result = async {
+            let inner = self.server.filesystem.async_get_inner().await?;
+            let path = self.server.filesystem.relative_path(path);
+
+            // the directory is opened through the sandbox first, so symlinks
+            // can never make inotify watch something outside of the server
+            let directory = tokio::task::spawn_blocking(move || {
+                if path.components().next().is_none() {
+                    inner.try_clone()
+                } else {
+                    inner.open_dir(path)
+                }
+            })
+            .await??;
+
+            Ok::<_, anyhow::Error>(inotify::add_watch(
+                self.inotify.get_ref(),
+                format!("/proc/self/fd/{}", directory.as_fd().as_raw_fd()),
+                inotify::WatchFlags::CREATE
+                    | inotify::WatchFlags::DELETE
+                    | inotify::WatchFlags::MODIFY
+                    | inotify::WatchFlags::CLOSE_WRITE
+                    | inotify::WatchFlags::MOVED_FROM
+                    | inotify::WatchFlags::MOVED_TO
+                    | inotify::WatchFlags::ONLYDIR
+                    | inotify::WatchFlags::EXCL_UNLINK,
+            )?)
+        }
+        .await;
+
+        match result {
+            Ok(wd) => {
+                if self.watches.insert(wd, path.to_path_buf()).is_some() {
+                    self.server
+                        .filesystem
+                        .watched_directories
+                        .fetch_sub(1, Ordering::SeqCst);
+                }
+
+                Ok(true)
+            }
+            Err(err) => {
+                self.server
+                    .filesystem
+                    .watched_directories
+                    .fetch_sub(1, Ordering::SeqCst);
+
+                Err(err)
+            }
+        }
+    }
+
+    async fn remove_watches(&mut self, path: &Path) {
+        let removed: Vec<i32> = self
+            .watches
+            .iter()
+            .filter(|(_, watched)| watched.starts_with(path))
+            .map(|(wd, _)| *wd)
+            .collect();
+
+        for wd in removed {
+            self.watches.remove(&wd);
+            inotify::remove_watch(self.inotify.get_ref(), wd).ok();
+            self.server
+                .filesystem
+                .watched_directories
+                .fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Watches every directory below `path`, breadth first so a limited
+    /// amount of watches covers the shallowest directories
+    async fn watch_children(&mut self, path: PathBuf) {
+        let mut queue = VecDeque::from([path]);
+
+        while let Some(directory) = queue.pop_front() {
+            let mut read_dir = match self.server.filesystem.async_read_dir(&directory).await {
+                Ok(read_dir) => read_dir,
+                Err(_) => continue,
+            };
+
+            while let Some(Ok((is_dir, name))) = read_dir.next_entry().await {
+                if !is_dir {
+                    continue;
+                }
+
+                let path = directory.join(name);
+                if self.server.filesystem.is_ignored(&path, true).await {
+                    continue;
+                }
+
+                match self.add_watch(&path).await {
+                    Ok(true) => queue.push_back(path),
+                    Ok(false) => return,
+                    Err(_) => continue,
+                }
+            }
+        }
+    }
+
+    async fn read_events(&self, events: &mut Vec<RawEvent>) -> Result<(), std::io::Error> {
+        loop {
+            let mut guard = self.inotify.readable().await?;
+
+            match guard.try_io(|inotify| {
+                let mut buffer = [MaybeUninit::uninit(); 4096];
+                let mut reader = inotify::Reader::new(inotify.get_ref(), &mut buffer);
+
+                let mut read = 0;
+                loop {
+                    match reader.next() {
+                        Ok(event) => {
+                            events.push(RawEvent {
+                                wd: event.wd(),
+                                flags: event.events(),
+                                cookie: event.cookie(),
+                                name: event
+                                    .file_name()
+                                    .map(|name| name.to_string_lossy().to_string())
+                                    .filter(|name| !name.is_empty()),
+                            });
+                            read += 1;
+                        }
+                        Err(rustix::io::Errno::AGAIN) if read > 0 => return Ok(()),
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            }) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Waits for the next batch of changes, the batch may be empty
+    /// if every change happened to ignored paths
+    pub async fn next_changes(&mut self) -> Result<Vec<FileChange>, anyhow::Error> {
+        let mut events = Vec::new();
+        self.read_events(&mut events).await?;
+
+        let deadline = tokio::time::Instant::now() + DEBOUNCE;
+        while let Ok(result) =
+            tokio::time::timeout_at(deadline, self.read_events(&mut events)).await
+        {
+            result?;
+        }
+
+        let mut changes: Vec<Option<FileChange>> = Vec::new();
+        let mut indices: HashMap<PathBuf, usize> = HashMap::new();
+        let mut moves: HashMap<u32, (PathBuf, usize, bool)> = HashMap::new();
+        let mut created_directories = Vec::new();
+
+        for event in events {
+            if event.flags.contains(inotify::ReadFlags::QUEUE_OVERFLOW) {
+                let index = changes.len();
+                changes.push(Some(FileChange {
+                    kind: FileChangeKind::Modified,
+                    path: self.root.to_string_lossy().to_string(),
+                    directory: true,
+                    from: None,
+                }));
+                indices.insert(self.root.clone(), index);
+
+                continue;
+            }
+
+            if event.flags.contains(inotify::ReadFlags::IGNORED) {
+                if self.watches.remove(&event.wd).is_some() {
+                    self.server
+                        .filesystem
+                        .watched_directories
+                        .fetch_sub(1, Ordering::SeqCst);
+                }
+
+                continue;
+            }
+
+            let (Some(parent), Some(name)) = (self.watches.get(&event.wd), event.name) else {
+                continue;
+            };
+
+            let path = parent.join(name);
+            let directory = event.flags.contains(inotify::ReadFlags::ISDIR);
+            if self.server.filesystem.is_ignored(&path, directory).await {
+                continue;
+            }
+
+            let kind = if event.flags.contains(inotify::ReadFlags::MOVED_TO) {
+                if let Some((from, index, _)) = moves.remove(&event.cookie) {
+                    changes[index] = None;
+                    indices.remove(&from);
+
+                    if directory {
+                        for watched in self.watches.values_mut() {
+                            if let Ok(relative) = watched.strip_prefix(&from) {
+                                *watched = path.join(relative);
+                            }
+                        }
+                    }
+
+                    let index = changes.len();
+                    changes.push(Some(FileChange {
+                        kind: FileChangeKind::Renamed,
+                        path: path.to_string_lossy().to_string(),
+                        directory,
+                        from: Some(from.to_string_lossy().to_string()),
+                    }));
+                    indices.insert(path, index);
+
+                    continue;
+                }
+
+                if directory {
+                    created_directories.push(path.clone());
+                }
+
+                FileChangeKind::Created
+            } else if event.flags.contains(inotify::ReadFlags::CREATE) {
+                if directory {
+                    created_directories.push(path.clone());
+                }
+
+                FileChangeKind::Created
+            } else if event
+                .flags
+                .intersects(inotify::ReadFlags::DELETE | inotify::ReadFlags::MOVED_FROM)
+            {
+                FileChangeKind::Deleted
+            } else {
+                FileChangeKind::Modified
+            };
+
+            let existing = indices
+                .get(&path)
+                .and_then(|index| changes[*index].as_ref().map(|change| (*index, change.kind)));
+
+            let index = match (existing, kind) {
+                (Some((index, FileChangeKind::Created)), FileChangeKind::Deleted) => {
+                    changes[index] = None;
+                    indices.remove(&path);
+
+                    None
+                }
+                (Some((index, FileChangeKind::Deleted)), FileChangeKind::Created) => {
+                    if let Some(change) = &mut changes[index] {
+                        change.kind = FileChangeKind::Modified;
+                    }
+
+                    Some(index)
+                }
+                (
+                    Some((index, FileChangeKind::Created | FileChangeKind::Modified)),
+                    FileChangeKind::Modified,
+                ) => Some(index),
+                (Some((index, _)), kind) => {
+                    if let Some(change) = &mut changes[index] {
+                        change.kind = kind;
+                    }
+
+                    Some(index)
+                }
+                (None, kind) => {
+                    let index = changes.len();
+                    changes.push(Some(FileChange {
+                        kind,
+                        path: path.to_string_lossy().to_string(),
+                        directory,
+                        from: None,
+                    }));
+                    indices.insert(path.clone(), index);
+
+                    Some(index)
+                }
+            };
+
+            if event.flags.contains(inotify::ReadFlags::MOVED_FROM)
+                && let Some(index) = index
+            {
+                moves.insert(event.cookie, (path, index, directory));
+            }
+        }
+
+        // directories moved out of the watched tree keep their watches until now,
+        // a matching move into the tree only has to rewrite the watched paths
+        for (path, _, directory) in moves.into_values() {
+            if directory {
+                self.remove_watches(&path).await;
+            }
+        }
+
+        for directory in created_directories {
+            match self.add_watch(&directory).await {
+                Ok(true) => self.watch_children(directory).await,
+                Ok(false) => break,
+                Err(_) => continue,
+            }
+        }
+
+        Ok(changes.into_iter().flatten().collect())
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.server
+            .filesystem
+            .watched_directories
+            .fetch_sub(self.watches.len(), Ordering::SeqCst);
+    }
+}
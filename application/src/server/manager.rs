@@ -1,4 +1,4 @@
-use super::{Server, state::ServerState};
+use super::{Server, resources::history::ResourceHistoryResolution, state::ServerState};
 use std::{
     collections::HashMap,
     path::Path,
@@ -222,6 +222,63 @@ impl Manager {
                 }
             }
         });
+
+        if app_state.config.system.resource_history.enabled {
+            self.spawn_resource_history_sampler();
+        }
+
+        self.spawn_trash_pruner();
+    }
+
+    fn spawn_resource_history_sampler(&self) {
+        tokio::spawn({
+            let servers = Arc::clone(&self.servers);
+
+            async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(
+                        ResourceHistoryResolution::TenSeconds.interval() as u64,
+                    ))
+                    .await;
+
+                    let servers = servers.read().await.clone();
+                    for server in servers {
+                        let usage = server.resource_usage().await;
+
+                        if let Err(err) = server.resource_history.record(usage).await {
+                            tracing::error!(
+                                server = %server.uuid,
+                                "failed to record resource history: {:#?}",
+                                err
+                            );
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn spawn_trash_pruner(&self) {
+        tokio::spawn({
+            let servers = Arc::clone(&self.servers);
+
+            async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(60 * 60)).await;
+
+                    let servers = servers.read().await.clone();
+                    for server in servers {
+                        if let Err(err) = server.trash.prune(&server).await {
+                            tracing::error!(
+                                server = %server.uuid,
+                                "failed to prune trash: {:#?}",
+                                err
+                            );
+                        }
+                    }
+                }
+            }
+        });
     }
 
     pub async fn get_servers(&self) -> tokio::sync::RwLockReadGuard<'_, Vec<Server>> {
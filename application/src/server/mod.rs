@@ -28,6 +28,7 @@ pub mod schedule;
 pub mod script;
 pub mod state;
 pub mod transfer;
+pub mod upload;
 pub mod websocket;
 
 pub struct InnerServer {
@@ -45,6 +46,8 @@ pub struct InnerServer {
     pub container: RwLock<Option<Arc<container::Container>>>,
     pub schedules: Arc<schedule::manager::ScheduleManager>,
     pub activity: activity::ActivityManager,
+    pub resource_history: resources::history::ResourceHistory,
+    pub trash: filesystem::trash::Trash,
 
     pub state: state::ServerStateLock,
     pub outgoing_transfer: RwLock<Option<transfer::OutgoingServerTransfer>>,
@@ -88,7 +91,15 @@ impl Server {
             &configuration.egg.file_denylist,
         );
 
+        backup::encryption::set_server_key(
+            configuration.uuid,
+            configuration.backup_encryption_key.as_deref(),
+        );
+
         let activity = activity::ActivityManager::new(configuration.uuid, &app_state.config);
+        let resource_history =
+            resources::history::ResourceHistory::new(configuration.uuid, &app_state.config);
+        let trash = filesystem::trash::Trash::new(configuration.uuid, &app_state.config);
         let schedules = Arc::new(schedule::manager::ScheduleManager::new(Arc::clone(
             &app_state.config,
         )));
@@ -107,6 +118,8 @@ impl Server {
             container: RwLock::new(None),
             schedules: Arc::clone(&schedules),
             activity,
+            resource_history,
+            trash,
 
             state: state::ServerStateLock::new(rx, schedules),
             outgoing_transfer: RwLock::new(None),
@@ -425,6 +438,10 @@ impl Server {
             .await;
         self.suspended
             .store(configuration.suspended, Ordering::SeqCst);
+        backup::encryption::set_server_key(
+            configuration.uuid,
+            configuration.backup_encryption_key.as_deref(),
+        );
         *self.configuration.write().await = configuration;
         *self.process_configuration.write().await = process_configuration;
         self.schedules.update_schedules(self.clone()).await;
@@ -1379,7 +1396,16 @@ impl Server {
         tokio::spawn({
             let server = self.clone();
 
-            async move { server.filesystem.destroy().await }
+            async move {
+                server.filesystem.destroy().await;
+                server.resource_history.destroy().await;
+                server.trash.destroy().await;
+                crate::server::backup::retention::BackupRetentionLedger::destroy(
+                    &server.app_state.config,
+                    server.uuid,
+                )
+                .await;
+            }
         });
     }
 
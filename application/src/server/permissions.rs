@@ -31,10 +31,20 @@ pub enum Permission {
     AdminWebsocketInstall,
     #[serde(rename = "admin.websocket.transfer")]
     AdminWebsocketTransfer,
+    #[serde(rename = "admin.ssh.port-forwarding")]
+    AdminSshPortForwarding,
+    #[serde(rename = "admin.ssh.container-shell")]
+    AdminSshContainerShell,
     #[serde(rename = "backup.read", alias = "backups.read")]
     BackupRead,
+    #[serde(rename = "backup.create", alias = "backups.create")]
+    BackupCreate,
+    #[serde(rename = "backup.restore", alias = "backups.restore")]
+    BackupRestore,
     #[serde(rename = "schedule.read", alias = "schedules.read")]
     ScheduleRead,
+    #[serde(rename = "schedule.update", alias = "schedules.update")]
+    ScheduleUpdate,
 
     #[serde(rename = "file.read", alias = "files.read")]
     FileRead,
@@ -57,6 +67,8 @@ impl Permission {
             Permission::AdminWebsocketErrors
                 | Permission::AdminWebsocketInstall
                 | Permission::AdminWebsocketTransfer
+                | Permission::AdminSshPortForwarding
+                | Permission::AdminSshContainerShell
         )
     }
 
@@ -0,0 +1,331 @@
+use super::{ResourceUsage, ResourceUsageNetwork};
+use crate::server::state::ServerState;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::{
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex,
+};
+use utoipa::ToSchema;
+
+const RECORD_SIZE: usize = 64;
+
+#[derive(ToSchema, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ResourceHistoryResolution {
+    #[serde(rename = "10s")]
+    #[schema(rename = "10s")]
+    TenSeconds,
+    #[serde(rename = "1m")]
+    #[schema(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "15m")]
+    #[schema(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    #[schema(rename = "1h")]
+    OneHour,
+}
+
+impl ResourceHistoryResolution {
+    pub const ALL: [Self; 4] = [
+        Self::TenSeconds,
+        Self::OneMinute,
+        Self::FifteenMinutes,
+        Self::OneHour,
+    ];
+
+    /// The width of a single bucket in seconds
+    #[inline]
+    pub fn interval(self) -> i64 {
+        match self {
+            Self::TenSeconds => 10,
+            Self::OneMinute => 60,
+            Self::FifteenMinutes => 15 * 60,
+            Self::OneHour => 60 * 60,
+        }
+    }
+
+    /// The amount of buckets kept on disk, 1 hour, 1 day, 7 days and 30 days respectively
+    #[inline]
+    pub fn capacity(self) -> i64 {
+        match self {
+            Self::TenSeconds => 360,
+            Self::OneMinute => 1440,
+            Self::FifteenMinutes => 672,
+            Self::OneHour => 720,
+        }
+    }
+
+    #[inline]
+    pub fn retention(self) -> i64 {
+        self.interval() * self.capacity()
+    }
+
+    #[inline]
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::TenSeconds => "10s.bin",
+            Self::OneMinute => "1m.bin",
+            Self::FifteenMinutes => "15m.bin",
+            Self::OneHour => "1h.bin",
+        }
+    }
+
+    /// Picks the finest resolution that still has data going back to `from`
+    pub fn for_range(from: i64, now: i64) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|resolution| now - resolution.retention() <= from)
+            .unwrap_or(Self::OneHour)
+    }
+}
+
+#[derive(ToSchema, Serialize, Clone, Copy)]
+pub struct ResourceHistoryPoint {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+
+    pub memory_bytes: u64,
+    pub memory_limit_bytes: u64,
+    pub disk_bytes: u64,
+
+    pub state: ServerState,
+
+    /// Bytes transferred during this bucket, not since container start
+    #[schema(inline)]
+    pub network: ResourceUsageNetwork,
+
+    pub cpu_absolute: f64,
+}
+
+impl ResourceHistoryPoint {
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut record = [0; RECORD_SIZE];
+
+        record[0..8].copy_from_slice(&self.timestamp.timestamp().to_le_bytes());
+        record[8..16].copy_from_slice(&self.memory_bytes.to_le_bytes());
+        record[16..24].copy_from_slice(&self.memory_limit_bytes.to_le_bytes());
+        record[24..32].copy_from_slice(&self.disk_bytes.to_le_bytes());
+        record[32..40].copy_from_slice(&self.network.rx_bytes.to_le_bytes());
+        record[40..48].copy_from_slice(&self.network.tx_bytes.to_le_bytes());
+        record[48..56].copy_from_slice(&self.cpu_absolute.to_le_bytes());
+        record[56] = self.state.into();
+
+        record
+    }
+
+    fn decode(record: &[u8]) -> Option<Self> {
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(record[offset..offset + 8].try_into().unwrap_or_default())
+        };
+
+        let timestamp = read_u64(0) as i64;
+        if timestamp == 0 || record[56] > 3 {
+            return None;
+        }
+
+        Some(Self {
+            timestamp: chrono::DateTime::from_timestamp(timestamp, 0)?,
+            memory_bytes: read_u64(8),
+            memory_limit_bytes: read_u64(16),
+            disk_bytes: read_u64(24),
+            state: ServerState::from(record[56]),
+            network: ResourceUsageNetwork {
+                rx_bytes: read_u64(32),
+                tx_bytes: read_u64(40),
+            },
+            cpu_absolute: f64::from_bits(read_u64(48)),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    start: i64,
+    samples: u64,
+
+    cpu_sum: f64,
+    memory_sum: u64,
+    network: ResourceUsageNetwork,
+    last: ResourceUsage,
+}
+
+impl Bucket {
+    fn point(&self) -> ResourceHistoryPoint {
+        let samples = self.samples.max(1);
+
+        ResourceHistoryPoint {
+            timestamp: chrono::DateTime::from_timestamp(self.start, 0).unwrap_or_default(),
+            memory_bytes: self.memory_sum / samples,
+            memory_limit_bytes: self.last.memory_limit_bytes,
+            disk_bytes: self.last.disk_bytes,
+            state: self.last.state,
+            network: self.network,
+            cpu_absolute: self.cpu_sum / samples as f64,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ResourceHistoryBuckets {
+    open: [Option<Bucket>; 4],
+    last_network: Option<ResourceUsageNetwork>,
+}
+
+pub struct ResourceHistory {
+    directory: PathBuf,
+    buckets: Mutex<ResourceHistoryBuckets>,
+}
+
+impl ResourceHistory {
+    pub fn new(server: uuid::Uuid, config: &crate::config::Config) -> Self {
+        Self {
+            directory: Path::new(&config.system.resource_history.directory)
+                .join(server.to_string()),
+            buckets: Mutex::new(ResourceHistoryBuckets::default()),
+        }
+    }
+
+    pub async fn record(&self, usage: ResourceUsage) -> Result<(), std::io::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let mut buckets = self.buckets.lock().await;
+
+        // docker reports network counters since container start, a decrease means the container was recreated
+        let network = match buckets.last_network {
+            Some(last) => ResourceUsageNetwork {
+                rx_bytes: usage
+                    .network
+                    .rx_bytes
+                    .checked_sub(last.rx_bytes)
+                    .unwrap_or(usage.network.rx_bytes),
+                tx_bytes: usage
+                    .network
+                    .tx_bytes
+                    .checked_sub(last.tx_bytes)
+                    .unwrap_or(usage.network.tx_bytes),
+            },
+            None => ResourceUsageNetwork::default(),
+        };
+        buckets.last_network = Some(usage.network);
+
+        let mut closed = Vec::new();
+
+        for (resolution, bucket) in ResourceHistoryResolution::ALL
+            .into_iter()
+            .zip(buckets.open.iter_mut())
+        {
+            let start = now - now.rem_euclid(resolution.interval());
+
+            match bucket {
+                Some(bucket) if bucket.start == start => {
+                    bucket.samples += 1;
+                    bucket.cpu_sum += usage.cpu_absolute;
+                    bucket.memory_sum += usage.memory_bytes;
+                    bucket.network.rx_bytes += network.rx_bytes;
+                    bucket.network.tx_bytes += network.tx_bytes;
+                    bucket.last = usage;
+                }
+                _ => {
+                    if let Some(old) = bucket.replace(Bucket {
+                        start,
+                        samples: 1,
+                        cpu_sum: usage.cpu_absolute,
+                        memory_sum: usage.memory_bytes,
+                        network,
+                        last: usage,
+                    }) {
+                        closed.push((resolution, old));
+                    }
+                }
+            }
+        }
+
+        drop(buckets);
+
+        for (resolution, bucket) in closed {
+            self.write_point(resolution, bucket.start, &bucket.point())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_point(
+        &self,
+        resolution: ResourceHistoryResolution,
+        start: i64,
+        point: &ResourceHistoryPoint,
+    ) -> Result<(), std::io::Error> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.directory.join(resolution.file_name()))
+            .await?;
+
+        let file_size = resolution.capacity() as u64 * RECORD_SIZE as u64;
+        if file.metadata().await?.len() != file_size {
+            file.set_len(file_size).await?;
+        }
+
+        let slot = (start / resolution.interval()).rem_euclid(resolution.capacity()) as u64;
+        file.seek(std::io::SeekFrom::Start(slot * RECORD_SIZE as u64))
+            .await?;
+        file.write_all(&point.encode()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    pub async fn points(
+        &self,
+        resolution: ResourceHistoryResolution,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<ResourceHistoryPoint>, std::io::Error> {
+        let data = match tokio::fs::read(self.directory.join(resolution.file_name())).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        let oldest = chrono::Utc::now().timestamp() - resolution.retention();
+        let in_range = |timestamp: i64| timestamp >= from && timestamp <= to && timestamp > oldest;
+
+        let mut points: Vec<_> = data
+            .chunks_exact(RECORD_SIZE)
+            .filter_map(ResourceHistoryPoint::decode)
+            .filter(|point| in_range(point.timestamp.timestamp()))
+            .collect();
+
+        let index = ResourceHistoryResolution::ALL
+            .iter()
+            .position(|r| *r == resolution)
+            .unwrap_or_default();
+        if let Some(bucket) = self.buckets.lock().await.open[index]
+            && in_range(bucket.start)
+            && !points
+                .iter()
+                .any(|point| point.timestamp.timestamp() == bucket.start)
+        {
+            points.push(bucket.point());
+        }
+
+        points.sort_by_key(|point| point.timestamp);
+
+        Ok(points)
+    }
+
+    pub async fn destroy(&self) {
+        if let Err(err) = tokio::fs::remove_dir_all(&self.directory).await
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::error!(
+                path = %self.directory.display(),
+                "failed to delete resource history: {:#?}",
+                err
+            );
+        }
+    }
+}
@@ -2,6 +2,8 @@ use super::state::ServerState;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+pub mod history;
+
 nestify::nest! {
     #[derive(ToSchema, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
     pub struct ResourceUsage {
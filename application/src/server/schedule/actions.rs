@@ -1,16 +1,73 @@
 use crate::{
     io::compression::CompressionType,
     routes::State,
-    server::activity::{Activity, ActivityEvent},
+    server::{
+        activity::{Activity, ActivityEvent},
+        filesystem::pull::{get_download_client, is_blocked_ip},
+    },
 };
 use cap_std::fs::OpenOptions;
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     path::{Path, PathBuf},
+    pin::Pin,
     sync::Arc,
 };
 use tokio::io::AsyncWriteExt;
 
+pub type ScheduleVariables = HashMap<String, String>;
+
+/// The most iterations a single `repeat` action can run
+const MAX_REPEAT_COUNT: u64 = 1000;
+
+fn deserialize_repeat_count<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let count = u64::deserialize(deserializer)?;
+    if count > MAX_REPEAT_COUNT {
+        return Err(serde::de::Error::custom(format!(
+            "repeat count {count} is larger than the maximum of {MAX_REPEAT_COUNT}"
+        )));
+    }
+
+    Ok(count)
+}
+
+/// Replaces `{{name}}` placeholders with their values, unknown placeholders are left untouched
+pub fn render_template<'a>(template: &'a str, variables: &ScheduleVariables) -> Cow<'a, str> {
+    if variables.is_empty() || !template.contains("{{") {
+        return Cow::Borrowed(template);
+    }
+
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+
+        let inner = &rest[start + 2..];
+        let Some(end) = inner.find("}}") else {
+            result.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        match variables.get(inner[..end].trim()) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + end + 4]),
+        }
+
+        rest = &inner[end + 2..];
+    }
+
+    result.push_str(rest);
+
+    Cow::Owned(result)
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct RenameFile {
     pub from: String,
@@ -52,6 +109,8 @@ pub enum ScheduleAction {
 
         name: Option<String>,
         ignored_files: Vec<String>,
+        #[serde(default)]
+        retention: Option<crate::server::backup::retention::BackupRetention>,
     },
     CreateDirectory {
         ignore_failure: bool,
@@ -113,6 +172,48 @@ pub enum ScheduleAction {
 
         image: String,
     },
+    SendHttpRequest {
+        ignore_failure: bool,
+
+        method: String,
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        body: Option<String>,
+        timeout: u64,
+    },
+    If {
+        #[serde(default)]
+        ignore_failure: bool,
+
+        condition: super::ScheduleCondition,
+        then: Vec<ScheduleAction>,
+        #[serde(default, rename = "else")]
+        otherwise: Vec<ScheduleAction>,
+    },
+    WaitFor {
+        #[serde(default)]
+        ignore_failure: bool,
+
+        condition: super::ScheduleCondition,
+        timeout: u64,
+    },
+    Repeat {
+        #[serde(default)]
+        ignore_failure: bool,
+
+        #[serde(deserialize_with = "deserialize_repeat_count")]
+        count: u64,
+        actions: Vec<ScheduleAction>,
+    },
+    ForEach {
+        #[serde(default)]
+        ignore_failure: bool,
+
+        variable: String,
+        values: Vec<String>,
+        actions: Vec<ScheduleAction>,
+    },
 }
 
 impl ScheduleAction {
@@ -133,13 +234,65 @@ impl ScheduleAction {
             ScheduleAction::UpdateStartupVariable { ignore_failure, .. } => *ignore_failure,
             ScheduleAction::UpdateStartupCommand { ignore_failure, .. } => *ignore_failure,
             ScheduleAction::UpdateStartupDockerImage { ignore_failure, .. } => *ignore_failure,
+            ScheduleAction::SendHttpRequest { ignore_failure, .. } => *ignore_failure,
+            ScheduleAction::If { ignore_failure, .. } => *ignore_failure,
+            ScheduleAction::WaitFor { ignore_failure, .. } => *ignore_failure,
+            ScheduleAction::Repeat { ignore_failure, .. } => *ignore_failure,
+            ScheduleAction::ForEach { ignore_failure, .. } => *ignore_failure,
+        }
+    }
+
+    /// Stores the result of the action in the `last_action_result` variable, blocks of
+    /// actions keep the result of the last action they ran instead
+    pub fn record_result(&self, variables: &mut ScheduleVariables, result: &Result<(), String>) {
+        if matches!(
+            self,
+            ScheduleAction::If { .. }
+                | ScheduleAction::Repeat { .. }
+                | ScheduleAction::ForEach { .. }
+        ) {
+            return;
         }
+
+        variables.insert(
+            "last_action_result".into(),
+            match result {
+                Ok(()) => "success".into(),
+                Err(err) => err.clone(),
+            },
+        );
+    }
+
+    /// Runs nested actions in order, stopping at the first failure that is not ignored
+    pub fn execute_all<'a>(
+        actions: &'a [ScheduleAction],
+        state: &'a State,
+        server: &'a crate::server::Server,
+        schedule: uuid::Uuid,
+        variables: &'a mut ScheduleVariables,
+    ) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>> {
+        Box::pin(async move {
+            for action in actions {
+                let result = action.execute(state, server, schedule, variables).await;
+                action.record_result(variables, &result);
+
+                if let Err(err) = result
+                    && !action.ignore_failure()
+                {
+                    return Err(err);
+                }
+            }
+
+            Ok(())
+        })
     }
 
     pub async fn execute(
         &self,
         state: &State,
         server: &crate::server::Server,
+        schedule: uuid::Uuid,
+        variables: &mut ScheduleVariables,
     ) -> Result<(), String> {
         if server.is_locked_state() {
             return Err("server is in a locked state.".into());
@@ -316,6 +469,8 @@ impl ScheduleAction {
                 }
             },
             ScheduleAction::SendCommand { command, .. } => {
+                let command = render_template(command, variables);
+
                 if server.state.get_state() == crate::server::state::ServerState::Offline {
                     return Err("server is not running.".into());
                 }
@@ -343,6 +498,7 @@ impl ScheduleAction {
                 foreground,
                 name,
                 ignored_files,
+                retention,
                 ..
             } => {
                 let (adapter, uuid) = match state
@@ -370,12 +526,18 @@ impl ScheduleAction {
                 let thread = tokio::spawn({
                     let state = Arc::clone(state);
                     let ignored_files = ignored_files.join("\n");
+                    let retention = retention.clone().map(|retention| {
+                        crate::server::backup::retention::ScheduleBackupRetention {
+                            schedule,
+                            retention,
+                        }
+                    });
                     let server = server.clone();
 
                     async move {
                         if let Err(err) = state
                             .backup_manager
-                            .create(adapter, &server, uuid, ignored_files)
+                            .create(adapter, &server, uuid, ignored_files, retention)
                             .await
                         {
                             tracing::error!(
@@ -450,6 +612,10 @@ impl ScheduleAction {
                 append,
                 ..
             } => {
+                let file_path = render_template(file_path, variables);
+                let file_path = file_path.as_ref();
+                let content = render_template(content, variables);
+
                 let path = match server.filesystem.async_canonicalize(file_path).await {
                     Ok(path) => path,
                     Err(_) => PathBuf::from(file_path),
@@ -1035,6 +1201,157 @@ impl ScheduleAction {
                     }
                 };
             }
+            ScheduleAction::SendHttpRequest {
+                method,
+                url,
+                headers,
+                body,
+                timeout,
+                ..
+            } => {
+                let resource_usage = server.resource_usage().await;
+
+                let mut variables = variables.clone();
+                variables.insert("server.uuid".into(), server.uuid.to_string());
+                variables.insert(
+                    "server.name".into(),
+                    server.configuration.read().await.meta.name.clone(),
+                );
+                variables.insert("server.state".into(), resource_usage.state.to_str().into());
+                variables.insert(
+                    "server.memory_bytes".into(),
+                    resource_usage.memory_bytes.to_string(),
+                );
+                variables.insert(
+                    "server.memory_limit_bytes".into(),
+                    resource_usage.memory_limit_bytes.to_string(),
+                );
+                variables.insert(
+                    "server.disk_bytes".into(),
+                    resource_usage.disk_bytes.to_string(),
+                );
+                variables.insert(
+                    "server.cpu_absolute".into(),
+                    format!("{:.2}", resource_usage.cpu_absolute),
+                );
+                variables.insert("server.uptime".into(), resource_usage.uptime.to_string());
+
+                let method = match reqwest::Method::from_bytes(method.to_uppercase().as_bytes()) {
+                    Ok(method) => method,
+                    Err(_) => return Err("invalid http method".into()),
+                };
+
+                let url = match reqwest::Url::parse(&render_template(url, &variables)) {
+                    Ok(url) => url,
+                    Err(_) => return Err("invalid url".into()),
+                };
+
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err("url must use http or https".into());
+                }
+
+                if let Some(ip) = url
+                    .host_str()
+                    .and_then(|host| is_blocked_ip(&state.config, host))
+                {
+                    tracing::warn!("blocking internal IP address in schedule request: {}", ip);
+                    return Err(format!("IP address {ip} is blocked"));
+                }
+
+                let client = match get_download_client(&state.config).await {
+                    Ok(client) => client,
+                    Err(err) => {
+                        tracing::error!("failed to build http client: {:#?}", err);
+                        return Err("failed to build http client".into());
+                    }
+                };
+
+                let mut request = client
+                    .request(method, url)
+                    .timeout(std::time::Duration::from_millis(*timeout));
+                for (name, value) in headers {
+                    request = request.header(name, &*render_template(value, &variables));
+                }
+                if let Some(body) = body {
+                    request = request.body(render_template(body, &variables).into_owned());
+                }
+
+                match request.send().await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => {
+                        return Err(format!(
+                            "request failed with status {}",
+                            response.status().as_u16()
+                        ));
+                    }
+                    Err(err) if err.is_timeout() => return Err("request timed out".into()),
+                    Err(err) => {
+                        tracing::debug!(
+                            server = %server.uuid,
+                            "failed to send schedule http request: {:#?}",
+                            err
+                        );
+
+                        return Err("failed to send request".into());
+                    }
+                }
+            }
+            ScheduleAction::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let actions = if condition.evaluate(server).await {
+                    then
+                } else {
+                    otherwise
+                };
+
+                Self::execute_all(actions, state, server, schedule, variables).await?;
+            }
+            ScheduleAction::WaitFor {
+                condition, timeout, ..
+            } => {
+                let wait = async {
+                    while !condition.evaluate(server).await {
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    }
+                };
+
+                if tokio::time::timeout(std::time::Duration::from_millis(*timeout), wait)
+                    .await
+                    .is_err()
+                {
+                    return Err("condition was not met before the timeout.".into());
+                }
+            }
+            ScheduleAction::Repeat { count, actions, .. } => {
+                for _ in 0..*count {
+                    Self::execute_all(actions, state, server, schedule, variables).await?;
+                }
+            }
+            ScheduleAction::ForEach {
+                variable,
+                values,
+                actions,
+                ..
+            } => {
+                let mut scoped_variables = variables.clone();
+
+                for value in values {
+                    scoped_variables.insert(variable.clone(), value.clone());
+                    let result =
+                        Self::execute_all(actions, state, server, schedule, &mut scoped_variables)
+                            .await;
+
+                    if let Some(last_action_result) = scoped_variables.get("last_action_result") {
+                        variables.insert("last_action_result".into(), last_action_result.clone());
+                    }
+
+                    result?;
+                }
+            }
         }
 
         Ok(())
@@ -1,7 +1,7 @@
 use crate::server::websocket::{WebsocketEvent, WebsocketMessage};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, pin::Pin, str::FromStr, sync::Arc};
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, RwLock, broadcast::error::RecvError};
 use utoipa::ToSchema;
 
 pub mod actions;
@@ -20,6 +20,54 @@ pub enum ScheduleTrigger {
         state: crate::server::state::ServerState,
     },
     Crash,
+    ConsoleLine {
+        /// `None` if the pattern sent by the panel is invalid, the trigger is then never fired
+        #[serde(with = "serde_regex")]
+        regex: Option<Box<regex::Regex>>,
+        /// Minimum time between two firings of this trigger in milliseconds
+        #[serde(default = "console_line_cooldown")]
+        cooldown: u64,
+    },
+}
+
+fn console_line_cooldown() -> u64 {
+    1000
+}
+
+mod serde_regex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        regex: &Option<Box<regex::Regex>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match regex {
+            Some(regex) => serializer.serialize_str(regex.as_str()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Box<regex::Regex>>, D::Error> {
+        let source = match Option::<String>::deserialize(deserializer)? {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+
+        match regex::Regex::new(&source) {
+            Ok(regex) => Ok(Some(Box::new(regex))),
+            Err(err) => {
+                tracing::warn!(
+                    pattern = %source,
+                    "ignoring console line trigger with invalid regex: {}",
+                    err
+                );
+
+                Ok(None)
+            }
+        }
+    }
 }
 
 impl PartialEq for ScheduleTrigger {
@@ -37,6 +85,19 @@ impl PartialEq for ScheduleTrigger {
                 ScheduleTrigger::ServerState { state: s2 },
             ) => s1 == s2,
             (ScheduleTrigger::Crash, ScheduleTrigger::Crash) => true,
+            (
+                ScheduleTrigger::ConsoleLine {
+                    regex: r1,
+                    cooldown: c1,
+                },
+                ScheduleTrigger::ConsoleLine {
+                    regex: r2,
+                    cooldown: c2,
+                },
+            ) => {
+                r1.as_ref().map(|regex| regex.as_str()) == r2.as_ref().map(|regex| regex.as_str())
+                    && c1 == c2
+            }
             _ => false,
         }
     }
@@ -206,6 +267,7 @@ pub struct Schedule {
     executor_task: tokio::task::JoinHandle<()>,
     executor_notifier: Arc<tokio::sync::Notify>,
     executor_skip_notifier: Arc<tokio::sync::Notify>,
+    executor_variables: Arc<Mutex<actions::ScheduleVariables>>,
 }
 
 impl Schedule {
@@ -215,6 +277,7 @@ impl Schedule {
     ) -> Self {
         let executor_notifier = Arc::new(tokio::sync::Notify::new());
         let executor_skip_notifier = Arc::new(tokio::sync::Notify::new());
+        let executor_variables = Arc::new(Mutex::new(actions::ScheduleVariables::new()));
 
         let condition = Arc::new(RwLock::new(raw_schedule.condition));
         let raw_actions = Arc::new(RwLock::new(Arc::new(raw_schedule.actions)));
@@ -228,6 +291,7 @@ impl Schedule {
             server.clone(),
             raw_schedule.triggers,
             Arc::clone(&executor_notifier),
+            Arc::clone(&executor_variables),
         );
 
         Self {
@@ -245,11 +309,13 @@ impl Schedule {
                 raw_actions,
                 Arc::clone(&executor_notifier),
                 Arc::clone(&executor_skip_notifier),
+                Arc::clone(&executor_variables),
                 status,
                 completion_status,
             ),
             executor_notifier,
             executor_skip_notifier,
+            executor_variables,
         }
     }
 
@@ -278,8 +344,12 @@ impl Schedule {
             task.abort();
         }
 
-        let (triggers, tasks) =
-            Self::create_trigger_tasks(server, triggers, Arc::clone(&self.executor_notifier));
+        let (triggers, tasks) = Self::create_trigger_tasks(
+            server,
+            triggers,
+            Arc::clone(&self.executor_notifier),
+            Arc::clone(&self.executor_variables),
+        );
 
         self.triggers = triggers;
         self.trigger_tasks = tasks;
@@ -296,6 +366,7 @@ impl Schedule {
             Arc::clone(&self.raw_actions),
             Arc::clone(&self.executor_notifier),
             Arc::clone(&self.executor_skip_notifier),
+            Arc::clone(&self.executor_variables),
             Arc::clone(&self.status),
             Arc::clone(&self.completion_status),
         );
@@ -309,6 +380,7 @@ impl Schedule {
         raw_actions: Arc<RwLock<Arc<Vec<super::configuration::ScheduleAction>>>>,
         executor_notifier: Arc<tokio::sync::Notify>,
         executor_skip_notifier: Arc<tokio::sync::Notify>,
+        executor_variables: Arc<Mutex<actions::ScheduleVariables>>,
         status: Arc<RwLock<ScheduleStatus>>,
         completion_status: Arc<Mutex<Option<ApiScheduleCompletionStatus>>>,
     ) -> tokio::task::JoinHandle<()> {
@@ -321,6 +393,8 @@ impl Schedule {
                     _ = executor_notifier.notified() => false,
                 };
 
+                let mut variables = std::mem::take(&mut *executor_variables.lock().await);
+
                 if !skip_condition && !condition.read().await.evaluate(&server).await {
                     continue;
                 }
@@ -350,22 +424,25 @@ impl Schedule {
                         ))
                         .ok();
 
-                    match raw_action.action.execute(&server.app_state, &server).await {
-                        Ok(()) => {}
-                        Err(err) => {
-                            errors.insert(raw_action.uuid, err.clone());
-                            server
-                                .websocket
-                                .send(WebsocketMessage::new(
-                                    WebsocketEvent::ServerScheduleStepError,
-                                    &[raw_action.uuid.to_string(), err],
-                                ))
-                                .ok();
-
-                            if !raw_action.action.ignore_failure() {
-                                successful = false;
-                                break;
-                            }
+                    let result = raw_action
+                        .action
+                        .execute(&server.app_state, &server, uuid, &mut variables)
+                        .await;
+                    raw_action.action.record_result(&mut variables, &result);
+
+                    if let Err(err) = result {
+                        errors.insert(raw_action.uuid, err.clone());
+                        server
+                            .websocket
+                            .send(WebsocketMessage::new(
+                                WebsocketEvent::ServerScheduleStepError,
+                                &[raw_action.uuid.to_string(), err],
+                            ))
+                            .ok();
+
+                        if !raw_action.action.ignore_failure() {
+                            successful = false;
+                            break;
                         }
                     }
                 }
@@ -401,15 +478,21 @@ impl Schedule {
         server: crate::server::Server,
         raw_triggers: Vec<ScheduleTrigger>,
         executor_notifier: Arc<tokio::sync::Notify>,
+        executor_variables: Arc<Mutex<actions::ScheduleVariables>>,
     ) -> (Vec<ScheduleTrigger>, Vec<tokio::task::JoinHandle<()>>) {
-        let cron_count = raw_triggers
+        let task_count = raw_triggers
             .iter()
-            .filter(|t| matches!(t, ScheduleTrigger::Cron { .. }))
+            .filter(|t| {
+                matches!(
+                    t,
+                    ScheduleTrigger::Cron { .. } | ScheduleTrigger::ConsoleLine { .. }
+                )
+            })
             .count();
         let mut triggers = Vec::new();
-        triggers.reserve_exact(raw_triggers.len() - cron_count);
+        triggers.reserve_exact(raw_triggers.len() - task_count);
         let mut tasks = Vec::new();
-        tasks.reserve_exact(cron_count);
+        tasks.reserve_exact(task_count);
 
         for trigger in raw_triggers {
             match trigger {
@@ -453,6 +536,68 @@ impl Schedule {
                         }
                     }));
                 }
+                ScheduleTrigger::ConsoleLine { regex: None, .. } => {
+                    tracing::debug!(
+                        server = %server.uuid,
+                        "skipping console line trigger with invalid regex"
+                    );
+                }
+                ScheduleTrigger::ConsoleLine {
+                    regex: Some(regex),
+                    cooldown,
+                } => {
+                    tasks.push(tokio::task::spawn({
+                        let executor_notifier = Arc::clone(&executor_notifier);
+                        let executor_variables = Arc::clone(&executor_variables);
+                        let server = server.clone();
+
+                        async move {
+                            let cooldown = std::time::Duration::from_millis(cooldown);
+                            let mut last_fired: Option<std::time::Instant> = None;
+
+                            loop {
+                                if let Some(mut stdout) = server.container_stdout().await {
+                                    loop {
+                                        let line = match stdout.recv().await {
+                                            Ok(line) => line,
+                                            Err(RecvError::Closed) => break,
+                                            Err(RecvError::Lagged(_)) => continue,
+                                        };
+
+                                        if last_fired.is_some_and(|last| last.elapsed() < cooldown)
+                                        {
+                                            continue;
+                                        }
+
+                                        let captures = match regex.captures(&line) {
+                                            Some(captures) => captures,
+                                            None => continue,
+                                        };
+
+                                        let mut variables = actions::ScheduleVariables::new();
+                                        for (i, name) in regex.capture_names().enumerate() {
+                                            let value = match captures.get(i) {
+                                                Some(value) => value.as_str().to_string(),
+                                                None => continue,
+                                            };
+
+                                            if let Some(name) = name {
+                                                variables.insert(name.to_string(), value.clone());
+                                            }
+                                            variables.insert(i.to_string(), value);
+                                        }
+
+                                        last_fired = Some(std::time::Instant::now());
+                                        *executor_variables.lock().await = variables;
+                                        executor_notifier.notify_one();
+                                    }
+                                }
+
+                                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                            }
+                        }
+                    }));
+                }
                 _ => triggers.push(trigger),
             }
         }
@@ -0,0 +1,607 @@
+use super::{OutgoingServerTransfer, TransferArchiveFormat};
+use crate::io::{
+    compression::{CompressionLevel, writer::CompressionWriter},
+    counting_reader::{AsyncCountingReader, CountingReader},
+    fixed_reader::FixedReader,
+};
+use cap_std::fs::PermissionsExt;
+use serde::Deserialize;
+use sha2::Digest;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Where the receiving node keeps partial chunks and completion markers of a transfer
+#[inline]
+pub fn staging_directory(config: &crate::config::Config, server: uuid::Uuid) -> PathBuf {
+    Path::new(&config.system.tmp_directory)
+        .join("transfers")
+        .join(server.to_string())
+}
+
+#[inline]
+pub fn is_valid_chunk_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+pub async fn file_checksum(path: &Path) -> Result<String, anyhow::Error> {
+    let mut hasher = sha2::Sha256::new();
+    let mut file = tokio::fs::File::open(path).await?;
+
+    let mut buffer = vec![0; crate::BUFFER_SIZE];
+    loop {
+        let bytes_read = file.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Deserialize)]
+struct ChunkStatus {
+    offset: u64,
+    completed: bool,
+}
+
+#[derive(Deserialize)]
+struct SessionStatus {
+    completed: HashSet<String>,
+}
+
+pub struct ChunkedTransferClient {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+    retry_limit: u64,
+}
+
+impl ChunkedTransferClient {
+    pub fn new(url: &str, token: String, retry_limit: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/chunked", url.trim_end_matches('/')),
+            token,
+            retry_limit: retry_limit.max(1),
+        }
+    }
+
+    async fn retry<T, F: Future<Output = Result<T, anyhow::Error>>>(
+        &self,
+        action: &str,
+        mut run: impl FnMut() -> F,
+    ) -> Result<T, anyhow::Error> {
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            match run().await {
+                Ok(value) => return Ok(value),
+                Err(err) if attempts < self.retry_limit => {
+                    tracing::warn!(
+                        "failed to {} (attempt {} of {}): {:#?}",
+                        action,
+                        attempts,
+                        self.retry_limit,
+                        err
+                    );
+
+                    tokio::time::sleep(std::time::Duration::from_secs(attempts.min(30))).await;
+                }
+                Err(err) => {
+                    return Err(
+                        err.context(format!("failed to {action} after {attempts} attempts"))
+                    );
+                }
+            }
+        }
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, anyhow::Error> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        Err(anyhow::anyhow!(
+            "destination responded with {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        ))
+    }
+
+    /// Starts or resumes the transfer, returning the chunks the destination already has
+    pub async fn start(&self) -> Result<HashSet<String>, anyhow::Error> {
+        self.retry("start chunked transfer", || async {
+            let response = self
+                .client
+                .post(&self.url)
+                .header("Authorization", &self.token)
+                .send()
+                .await?;
+            let status: SessionStatus = Self::check(response).await?.json().await?;
+
+            Ok(status.completed)
+        })
+        .await
+    }
+
+    async fn status(&self, name: &str) -> Result<ChunkStatus, anyhow::Error> {
+        let response = self
+            .client
+            .get(format!("{}/chunk", self.url))
+            .header("Authorization", &self.token)
+            .query(&[("name", name)])
+            .send()
+            .await?;
+
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// Uploads a file as a chunk, continuing from whatever the destination already received
+    pub async fn upload(
+        &self,
+        name: &str,
+        file_name: &str,
+        path: &Path,
+        bytes_uploaded: Option<&Arc<AtomicU64>>,
+    ) -> Result<(), anyhow::Error> {
+        let size = tokio::fs::metadata(path).await?.len();
+        let checksum = file_checksum(path).await?;
+
+        self.retry(&format!("upload chunk {name}"), || async {
+            let status = self.status(name).await?;
+            if status.completed {
+                return Ok(());
+            }
+
+            if status.offset > size {
+                return Err(anyhow::anyhow!(
+                    "destination has {} bytes of chunk {name}, but it only has {size} bytes",
+                    status.offset
+                ));
+            }
+
+            let mut file = tokio::fs::File::open(path).await?;
+            file.seek(std::io::SeekFrom::Start(status.offset)).await?;
+            let reader = file.take(size - status.offset);
+
+            let body = match bytes_uploaded {
+                Some(bytes_uploaded) => {
+                    reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::with_capacity(
+                        AsyncCountingReader::new_with_bytes_read(
+                            reader,
+                            Arc::clone(bytes_uploaded),
+                        ),
+                        crate::BUFFER_SIZE,
+                    ))
+                }
+                None => reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::with_capacity(
+                    reader,
+                    crate::BUFFER_SIZE,
+                )),
+            };
+
+            let response = self
+                .client
+                .put(format!("{}/chunk", self.url))
+                .header("Authorization", &self.token)
+                .header("Content-Length", size - status.offset)
+                .query(&[
+                    ("name", name),
+                    ("file_name", file_name),
+                    ("offset", &status.offset.to_string()),
+                    ("size", &size.to_string()),
+                    ("checksum", &checksum),
+                ])
+                .body(body)
+                .send()
+                .await?;
+            let status: ChunkStatus = Self::check(response).await?.json().await?;
+
+            if !status.completed {
+                return Err(anyhow::anyhow!(
+                    "destination only received {} of {size} bytes of chunk {name}",
+                    status.offset
+                ));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn finish(
+        &self,
+        deleted: &[PathBuf],
+        backups: &[uuid::Uuid],
+    ) -> Result<(), anyhow::Error> {
+        let deleted = deleted
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+
+        self.retry("finish chunked transfer", || async {
+            let response = self
+                .client
+                .post(format!("{}/finish", self.url))
+                .header("Authorization", &self.token)
+                .json(&serde_json::json!({
+                    "deleted": deleted,
+                    "backups": backups,
+                }))
+                .send()
+                .await?;
+            Self::check(response).await?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct PlanEntry {
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+    directory: bool,
+}
+
+type Plan = BTreeMap<PathBuf, PlanEntry>;
+
+pub struct ChunkedTransfer {
+    pub server: crate::server::Server,
+    pub backup_manager: Arc<crate::server::backup::manager::BackupManager>,
+    pub client: ChunkedTransferClient,
+    pub archive_format: TransferArchiveFormat,
+    pub compression_level: CompressionLevel,
+    pub pre_sync: bool,
+    pub bytes_archived: Arc<AtomicU64>,
+}
+
+impl ChunkedTransfer {
+    async fn build_plan(&self) -> Result<Plan, anyhow::Error> {
+        let mut plan = Plan::new();
+
+        let mut walker = self.server.filesystem.async_walk_dir(Path::new("")).await?;
+        while let Some(Ok((_, path))) = walker.next_entry().await {
+            let metadata = match self.server.filesystem.async_symlink_metadata(&path).await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            plan.insert(
+                path,
+                PlanEntry {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok().map(|t| t.into_std()),
+                    directory: metadata.is_dir(),
+                },
+            );
+        }
+
+        Ok(plan)
+    }
+
+    /// Chunks are named after their contents, so a resumed transfer skips chunks the destination
+    /// already has unless a file in them changed since
+    fn chunk_name(entries: &[(&PathBuf, &PlanEntry)]) -> String {
+        let mut hasher = sha2::Sha256::new();
+
+        for (path, entry) in entries {
+            hasher.update(path.as_os_str().as_encoded_bytes());
+            hasher.update(entry.size.to_le_bytes());
+            hasher.update(
+                entry
+                    .modified
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .unwrap_or_default()
+                    .as_nanos()
+                    .to_le_bytes(),
+            );
+            hasher.update([entry.directory as u8]);
+        }
+
+        format!("archive-{}", &format!("{:x}", hasher.finalize())[..32])
+    }
+
+    fn write_chunk(
+        filesystem: &crate::server::filesystem::cap::CapFilesystem,
+        entries: &[PathBuf],
+        destination: std::fs::File,
+        options: (TransferArchiveFormat, CompressionLevel, usize),
+        bytes_archived: &Arc<AtomicU64>,
+    ) -> Result<(), anyhow::Error> {
+        let (archive_format, compression_level, threads) = options;
+        let writer = CompressionWriter::new(
+            std::io::BufWriter::with_capacity(crate::BUFFER_SIZE, destination),
+            archive_format.compression_type(),
+            compression_level,
+            threads,
+        );
+        let mut archive = tar::Builder::new(writer);
+
+        for path in entries {
+            let metadata = match filesystem.symlink_metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_mode(metadata.permissions().mode());
+            header.set_mtime(
+                metadata
+                    .modified()
+                    .map(|t| {
+                        t.into_std()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                    })
+                    .unwrap_or_default()
+                    .as_secs(),
+            );
+
+            if metadata.is_dir() {
+                header.set_entry_type(tar::EntryType::Directory);
+
+                archive.append_data(&mut header, path, std::io::empty())?;
+            } else if metadata.is_file() {
+                let file = filesystem.open(path)?;
+                let reader: Box<dyn Read> = Box::new(CountingReader::new_with_bytes_read(
+                    file,
+                    Arc::clone(bytes_archived),
+                ));
+                let reader = FixedReader::new_with_fixed_bytes(reader, metadata.len() as usize);
+
+                header.set_size(metadata.len());
+                header.set_entry_type(tar::EntryType::Regular);
+
+                archive.append_data(&mut header, path, reader)?;
+            } else if let Ok(link_target) = filesystem.read_link_contents(path) {
+                header.set_entry_type(tar::EntryType::Symlink);
+
+                if header.set_link_name(link_target).is_ok() {
+                    archive.append_data(&mut header, path, std::io::empty())?;
+                }
+            }
+        }
+
+        archive.into_inner()?.finish()?;
+
+        Ok(())
+    }
+
+    async fn send_entries(
+        &self,
+        entries: Vec<(&PathBuf, &PlanEntry)>,
+        completed: &HashSet<String>,
+    ) -> Result<(), anyhow::Error> {
+        let config = &self.server.app_state.config;
+        let chunk_size = config.system.transfers.chunk_size.max(1) * 1024 * 1024;
+
+        let mut chunks = Vec::new();
+        let mut chunk = Vec::new();
+        let mut current_size = 0;
+        for (path, entry) in entries {
+            current_size += entry.size;
+            chunk.push((path, entry));
+
+            if current_size >= chunk_size {
+                chunks.push(std::mem::take(&mut chunk));
+                current_size = 0;
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+
+        for chunk in chunks {
+            let name = Self::chunk_name(&chunk);
+            if completed.contains(&name) {
+                let size = chunk.iter().map(|(_, entry)| entry.size).sum();
+                self.bytes_archived.fetch_add(size, Ordering::SeqCst);
+
+                continue;
+            }
+
+            let file_name = format!("{name}.{}", self.archive_format.extension());
+            let path = Path::new(&config.system.tmp_directory)
+                .join(format!("transfer-{}-{file_name}", self.server.uuid));
+
+            let entries = chunk
+                .into_iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+            let file = tokio::fs::File::create(&path).await?.into_std().await;
+            let filesystem = self.server.filesystem.clone();
+            let options = (
+                self.archive_format,
+                self.compression_level,
+                config.api.file_compression_threads,
+            );
+            let bytes_archived = Arc::clone(&self.bytes_archived);
+
+            let result = match tokio::task::spawn_blocking(move || {
+                Self::write_chunk(&filesystem, &entries, file, options, &bytes_archived)
+            })
+            .await
+            {
+                Ok(Ok(())) => self.client.upload(&name, &file_name, &path, None).await,
+                Ok(Err(err)) => Err(err),
+                Err(err) => Err(err.into()),
+            };
+
+            tokio::fs::remove_file(&path).await.ok();
+            result?;
+        }
+
+        Ok(())
+    }
+
+    async fn stop_server(&self) -> Result<(), anyhow::Error> {
+        if self.server.state.get_state() != crate::server::state::ServerState::Offline {
+            self.server
+                .stop_with_kill_timeout(std::time::Duration::from_secs(15), true)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_backups(&self, backups: &[uuid::Uuid]) -> Result<Vec<uuid::Uuid>, anyhow::Error> {
+        let mut transferred = Vec::new();
+
+        for backup in backups {
+            let backup = match self.backup_manager.find(*backup).await {
+                Ok(Some(backup)) => backup,
+                _ => {
+                    tracing::warn!(
+                        server = %self.server.uuid,
+                        "requested backup {} does not exist",
+                        backup
+                    );
+                    continue;
+                }
+            };
+
+            if backup.adapter() != crate::server::backup::adapters::BackupAdapter::Wings {
+                tracing::warn!(
+                    server = %self.server.uuid,
+                    "backup {} is not a Wings backup and cannot be transferred, skipping",
+                    backup.uuid()
+                );
+                continue;
+            }
+
+            let transfer_file = crate::server::backup::adapters::wings::WingsBackup::transfer_file(
+                &self.server,
+                backup.uuid(),
+            )
+            .await?;
+
+            self.client
+                .upload(
+                    &format!("backup-{}", backup.uuid()),
+                    &transfer_file.file_name,
+                    &transfer_file.path,
+                    Some(&self.bytes_archived),
+                )
+                .await?;
+            transferred.push(backup.uuid());
+        }
+
+        Ok(transferred)
+    }
+
+    pub async fn run(&self, backups: &[uuid::Uuid]) -> Result<(), anyhow::Error> {
+        let completed = self.client.start().await?;
+
+        let mut total_bytes = self.server.filesystem.limiter_usage().await;
+        for backup in backups {
+            if let Ok((_, path)) =
+                crate::server::backup::adapters::wings::WingsBackup::get_first_file_name(
+                    &self.server.app_state.config,
+                    *backup,
+                )
+                .await
+            {
+                total_bytes += tokio::fs::metadata(&path)
+                    .await
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+            }
+        }
+
+        self.server
+            .websocket
+            .send(crate::server::websocket::WebsocketMessage::new(
+                crate::server::websocket::WebsocketEvent::ServerTransferStatus,
+                &["processing".to_string()],
+            ))
+            .ok();
+
+        let progress_task = OutgoingServerTransfer::spawn_progress_task(
+            &self.server,
+            Arc::clone(&self.bytes_archived),
+            total_bytes,
+        );
+        let result = self.transfer(backups, &completed).await;
+        progress_task.abort();
+
+        result
+    }
+
+    async fn transfer(
+        &self,
+        backups: &[uuid::Uuid],
+        completed: &HashSet<String>,
+    ) -> Result<(), anyhow::Error> {
+        let mut deleted = Vec::new();
+
+        if self.pre_sync {
+            OutgoingServerTransfer::log(
+                &self.server,
+                "Pre-syncing server data to destination while the server is running...",
+            );
+
+            let plan = self.build_plan().await?;
+            self.send_entries(plan.iter().collect(), completed).await?;
+
+            OutgoingServerTransfer::log(
+                &self.server,
+                "Finished pre-sync, stopping server to send remaining changes...",
+            );
+            self.stop_server().await?;
+
+            let final_plan = self.build_plan().await?;
+            let changed = final_plan
+                .iter()
+                .filter(|(path, entry)| plan.get(*path) != Some(*entry))
+                .collect::<Vec<_>>();
+            deleted.extend(
+                plan.keys()
+                    .filter(|path| !final_plan.contains_key(*path))
+                    .cloned(),
+            );
+
+            OutgoingServerTransfer::log(
+                &self.server,
+                &format!(
+                    "Sending {} changed and {} deleted entries to destination...",
+                    changed.len(),
+                    deleted.len()
+                ),
+            );
+            self.send_entries(changed, completed).await?;
+        } else {
+            self.stop_server().await?;
+
+            OutgoingServerTransfer::log(
+                &self.server,
+                "Sending server data to destination in chunks...",
+            );
+            let plan = self.build_plan().await?;
+            self.send_entries(plan.iter().collect(), completed).await?;
+        }
+
+        let backups = self.send_backups(backups).await?;
+        self.client.finish(&deleted, &backups).await?;
+
+        OutgoingServerTransfer::log(&self.server, "Finished sending server data to destination.");
+
+        Ok(())
+    }
+}
@@ -2,10 +2,12 @@ use crate::io::{
     compression::{CompressionLevel, CompressionType},
     counting_reader::AsyncCountingReader,
 };
+use cap_std::fs::{Permissions, PermissionsExt};
 use human_bytes::human_bytes;
 use serde::Deserialize;
 use sha2::Digest;
 use std::{
+    io::{Read, Write},
     path::{Path, PathBuf},
     sync::{
         Arc,
@@ -15,6 +17,8 @@ use std::{
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use utoipa::ToSchema;
 
+pub mod chunked;
+
 #[derive(Clone, Copy, ToSchema, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 #[schema(rename_all = "snake_case")]
@@ -39,6 +43,17 @@ impl TransferArchiveFormat {
             TransferArchiveFormat::TarZstd => "tar.zst",
         }
     }
+
+    pub fn compression_type(&self) -> CompressionType {
+        match self {
+            TransferArchiveFormat::Tar => CompressionType::None,
+            TransferArchiveFormat::TarGz => CompressionType::Gz,
+            TransferArchiveFormat::TarXz => CompressionType::Xz,
+            TransferArchiveFormat::TarBz2 => CompressionType::Bz2,
+            TransferArchiveFormat::TarLz4 => CompressionType::Lz4,
+            TransferArchiveFormat::TarZstd => CompressionType::Zstd,
+        }
+    }
 }
 
 impl std::str::FromStr for TransferArchiveFormat {
@@ -63,12 +78,107 @@ impl std::str::FromStr for TransferArchiveFormat {
     }
 }
 
+#[derive(Clone, Copy, ToSchema, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "snake_case")]
+pub enum TransferMode {
+    /// Stops the server and streams everything as a single archive
+    #[default]
+    Stream,
+    /// Stops the server and sends checksummed chunks that can be resumed
+    Chunked,
+    /// Sends chunks while the server is running, then stops it and only sends what changed
+    PreSync,
+}
+
+/// Extracts a transfer archive into the filesystem of the receiving server
+pub fn extract_archive(server: &super::Server, reader: impl Read) -> Result<(), anyhow::Error> {
+    let mut archive = tar::Archive::new(reader);
+    let mut directory_entries = chunked_vec::ChunkedVec::new();
+    let mut entries = archive.entries()?;
+
+    while let Some(Ok(mut entry)) = entries.next() {
+        let path = entry.path()?;
+
+        if path.is_absolute() {
+            continue;
+        }
+
+        let destination_path = path.as_ref();
+        let header = entry.header();
+
+        match header.entry_type() {
+            tar::EntryType::Directory => {
+                server.filesystem.create_dir_all(destination_path)?;
+                if let Ok(permissions) = header.mode().map(Permissions::from_mode) {
+                    server
+                        .filesystem
+                        .set_permissions(destination_path, permissions)?;
+                }
+
+                if let Ok(modified_time) = header.mtime() {
+                    directory_entries.push((destination_path.to_path_buf(), modified_time));
+                }
+            }
+            tar::EntryType::Regular => {
+                if let Some(parent) = destination_path.parent() {
+                    server.filesystem.create_dir_all(parent)?;
+                }
+
+                let mut writer = super::filesystem::writer::FileSystemWriter::new(
+                    server.clone(),
+                    destination_path,
+                    header.mode().map(Permissions::from_mode).ok(),
+                    header
+                        .mtime()
+                        .map(|t| {
+                            cap_std::time::SystemTime::from_std(
+                                std::time::UNIX_EPOCH + std::time::Duration::from_secs(t),
+                            )
+                        })
+                        .ok(),
+                )?;
+
+                crate::io::copy(&mut entry, &mut writer)?;
+                writer.flush()?;
+            }
+            tar::EntryType::Symlink => {
+                let link = entry.link_name().unwrap_or_default().unwrap_or_default();
+
+                if let Err(err) = server.filesystem.symlink(link, destination_path) {
+                    tracing::debug!(
+                        path = %destination_path.display(),
+                        "failed to create symlink from archive: {:#?}",
+                        err
+                    );
+                } else if let Ok(permissions) = header.mode().map(Permissions::from_mode) {
+                    server
+                        .filesystem
+                        .set_symlink_permissions(destination_path, permissions)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (destination_path, modified_time) in directory_entries {
+        server.filesystem.set_times(
+            &destination_path,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(modified_time),
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
 pub struct OutgoingServerTransfer {
     pub bytes_archived: Arc<AtomicU64>,
 
     server: super::Server,
     archive_format: TransferArchiveFormat,
     compression_level: CompressionLevel,
+    mode: TransferMode,
     pub task: Option<tokio::task::JoinHandle<()>>,
 }
 
@@ -77,12 +187,14 @@ impl OutgoingServerTransfer {
         server: &super::Server,
         archive_format: TransferArchiveFormat,
         compression_level: CompressionLevel,
+        mode: TransferMode,
     ) -> Self {
         Self {
             bytes_archived: Arc::new(AtomicU64::new(0)),
             server: server.clone(),
             archive_format,
             compression_level,
+            mode,
             task: None,
         }
     }
@@ -122,6 +234,121 @@ impl OutgoingServerTransfer {
             .ok();
     }
 
+    fn spawn_progress_task(
+        server: &super::Server,
+        bytes_archived: Arc<AtomicU64>,
+        total_bytes: u64,
+    ) -> tokio::task::JoinHandle<()> {
+        let server = server.clone();
+
+        tokio::spawn(async move {
+            let formatted_total_bytes = human_bytes(total_bytes as f64);
+            let mut total_n_bytes_archived = 0.0;
+
+            loop {
+                if !server.transferring.load(Ordering::SeqCst) {
+                    tracing::info!(
+                        server = %server.uuid,
+                        "transfer aborted, stopping progress task"
+                    );
+                    break;
+                }
+
+                let bytes_archived = bytes_archived.load(Ordering::SeqCst);
+                total_n_bytes_archived += 1.0;
+
+                let formatted_bytes_archived = human_bytes(bytes_archived as f64);
+                let formatted_diff = human_bytes(bytes_archived as f64 / total_n_bytes_archived);
+                let formatted_percentage = format!(
+                    "{:.2}%",
+                    (bytes_archived as f64 / total_bytes as f64) * 100.0
+                );
+
+                Self::log(
+                    &server,
+                    &format!(
+                        "Transferred {formatted_bytes_archived} of {formatted_total_bytes} ({formatted_diff}/s, {formatted_percentage})"
+                    ),
+                );
+                tracing::debug!(
+                    server = %server.uuid,
+                    "transferred {} of {} ({}/s, {})",
+                    formatted_bytes_archived,
+                    formatted_total_bytes,
+                    formatted_diff,
+                    formatted_percentage
+                );
+
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        })
+    }
+
+    async fn transfer_success(
+        server: &super::Server,
+        backup_manager: &super::backup::manager::BackupManager,
+        backups: Vec<uuid::Uuid>,
+        delete_backups: bool,
+    ) {
+        for backup in backups {
+            match backup_manager.find(backup).await {
+                Ok(Some(backup)) => {
+                    if delete_backups {
+                        if let Err(err) = backup.delete(&server.app_state.config).await {
+                            tracing::error!(
+                                server = %server.uuid,
+                                "failed to delete backup {}: {}",
+                                backup.uuid(),
+                                err
+                            );
+                        } else {
+                            tracing::info!(
+                                server = %server.uuid,
+                                "deleted backup {} after transfer",
+                                backup.uuid()
+                            );
+                        }
+                    }
+                }
+                Ok(None) => {
+                    tracing::warn!(
+                        server = %server.uuid,
+                        "requested backup {} does not exist",
+                        backup
+                    );
+                }
+                Err(err) => {
+                    tracing::error!(
+                        server = %server.uuid,
+                        "failed to find backup {}: {:#?}",
+                        backup,
+                        err
+                    );
+                }
+            }
+        }
+
+        server.transferring.store(false, Ordering::SeqCst);
+
+        tracing::info!(
+            server = %server.uuid,
+            "finished outgoing server transfer"
+        );
+
+        let server = server.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+            server
+                .websocket
+                .send(super::websocket::WebsocketMessage::new(
+                    super::websocket::WebsocketEvent::ServerTransferStatus,
+                    &["completed".to_string()],
+                ))
+                .ok();
+        });
+    }
+
     pub fn start(
         &mut self,
         backup_manager: &Arc<super::backup::manager::BackupManager>,
@@ -134,6 +361,7 @@ impl OutgoingServerTransfer {
         let bytes_archived = Arc::clone(&self.bytes_archived);
         let archive_format = self.archive_format;
         let compression_level = self.compression_level;
+        let mode = self.mode;
         let server = self.server.clone();
 
         tracing::info!(
@@ -142,6 +370,41 @@ impl OutgoingServerTransfer {
         );
 
         let old_task = self.task.replace(tokio::spawn(async move {
+            if mode != TransferMode::Stream {
+                let transfer = chunked::ChunkedTransfer {
+                    server: server.clone(),
+                    backup_manager: Arc::clone(&backup_manager),
+                    client: chunked::ChunkedTransferClient::new(
+                        &url,
+                        token,
+                        server.app_state.config.system.transfers.retry_limit,
+                    ),
+                    archive_format,
+                    compression_level,
+                    pre_sync: mode == TransferMode::PreSync,
+                    bytes_archived,
+                };
+
+                match transfer.run(&backups).await {
+                    Ok(()) => {
+                        Self::transfer_success(&server, &backup_manager, backups, delete_backups)
+                            .await;
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            server = %server.uuid,
+                            "failed to complete chunked transfer: {:#?}",
+                            err
+                        );
+
+                        Self::log(&server, &format!("Transfer failed: {err}"));
+                        Self::transfer_failure(&server).await;
+                    }
+                }
+
+                return;
+            }
+
             if server.state.get_state() != super::state::ServerState::Offline
                 && let Err(err) = server
                     .stop_with_kill_timeout(std::time::Duration::from_secs(15), true)
@@ -184,14 +447,7 @@ impl OutgoingServerTransfer {
                         Some(Arc::clone(&bytes_archived)),
                         vec![],
                         crate::server::filesystem::archive::create::CreateTarOptions {
-                            compression_type: match archive_format {
-                                TransferArchiveFormat::Tar => CompressionType::None,
-                                TransferArchiveFormat::TarGz => CompressionType::Gz,
-                                TransferArchiveFormat::TarXz => CompressionType::Xz,
-                                TransferArchiveFormat::TarBz2 => CompressionType::Bz2,
-                                TransferArchiveFormat::TarLz4 => CompressionType::Lz4,
-                                TransferArchiveFormat::TarZstd => CompressionType::Zstd,
-                            },
+                            compression_type: archive_format.compression_type(),
                             compression_level,
                             threads: server.app_state.config.api.file_compression_threads,
                         }
@@ -242,18 +498,19 @@ impl OutgoingServerTransfer {
                 );
 
             let mut total_bytes = server.filesystem.limiter_usage().await;
+            let mut transfer_files = Vec::new();
 
             if !backups.is_empty() {
                 for backup in &backups {
                     if let Ok(Some(backup)) = backup_manager.find(*backup).await {
                         match backup.adapter() {
                             super::backup::adapters::BackupAdapter::Wings => {
-                                let file_name = match super::backup::adapters::wings::WingsBackup::get_first_file_name(&server.app_state.config, backup.uuid()).await {
-                                    Ok((_, file_name)) => file_name,
+                                let transfer_file = match super::backup::adapters::wings::WingsBackup::transfer_file(&server, backup.uuid()).await {
+                                    Ok(transfer_file) => transfer_file,
                                     Err(err) => {
                                         tracing::error!(
                                             server = %server.uuid,
-                                            "failed to get first file name for backup {}: {}",
+                                            "failed to prepare backup {} for transfer: {}",
                                             backup.uuid(),
                                             err
                                         );
@@ -261,13 +518,13 @@ impl OutgoingServerTransfer {
                                     }
                                 };
                                 let reader = AsyncCountingReader::new_with_bytes_read(
-                                    match tokio::fs::File::open(&file_name).await {
+                                    match tokio::fs::File::open(&transfer_file.path).await {
                                         Ok(file) => file,
                                         Err(err) => {
                                             tracing::error!(
                                                 server = %server.uuid,
                                                 "failed to open backup file {}: {}",
-                                                file_name.display(),
+                                                transfer_file.path.display(),
                                                 err
                                             );
                                             continue;
@@ -276,7 +533,7 @@ impl OutgoingServerTransfer {
                                     Arc::clone(&bytes_archived),
                                 );
 
-                                total_bytes += tokio::fs::metadata(&file_name)
+                                total_bytes += tokio::fs::metadata(&transfer_file.path)
                                     .await
                                     .map(|m| m.len())
                                     .unwrap_or(0);
@@ -286,10 +543,11 @@ impl OutgoingServerTransfer {
                                     reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(
                                         tokio_util::io::ReaderStream::with_capacity(reader, crate::BUFFER_SIZE),
                                     ))
-                                    .file_name(file_name.file_name().unwrap_or_default().to_string_lossy().to_string())
+                                    .file_name(transfer_file.file_name.clone())
                                     .mime_str("backup/wings")
                                     .unwrap(),
                                 );
+                                transfer_files.push(transfer_file);
                             }
                             _ => {
                                 tracing::warn!(
@@ -309,52 +567,8 @@ impl OutgoingServerTransfer {
                 }
             }
 
-            let progress_task = tokio::spawn({
-                let server = server.clone();
-
-                async move {
-                    let formatted_total_bytes = human_bytes(total_bytes as f64);
-                    let mut total_n_bytes_archived = 0.0;
-
-                    loop {
-                        if !server.transferring.load(Ordering::SeqCst) {
-                            tracing::info!(
-                                server = %server.uuid,
-                                "transfer aborted, stopping progress task"
-                            );
-                            break;
-                        }
-
-                        let bytes_archived = bytes_archived.load(Ordering::SeqCst);
-                        total_n_bytes_archived += 1.0;
-
-                        let formatted_bytes_archived = human_bytes(bytes_archived as f64);
-                        let formatted_diff =
-                            human_bytes(bytes_archived as f64 / total_n_bytes_archived);
-                        let formatted_percentage = format!(
-                            "{:.2}%",
-                            (bytes_archived as f64 / total_bytes as f64) * 100.0
-                        );
-
-                        Self::log(
-                            &server,
-                            &format!(
-                                "Transferred {formatted_bytes_archived} of {formatted_total_bytes} ({formatted_diff}/s, {formatted_percentage})"
-                            ),
-                        );
-                        tracing::debug!(
-                            server = %server.uuid,
-                            "transferred {} of {} ({}/s, {})",
-                            formatted_bytes_archived,
-                            formatted_total_bytes,
-                            formatted_diff,
-                            formatted_percentage
-                        );
-
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                    }
-                }
-            });
+            let progress_task =
+                Self::spawn_progress_task(&server, Arc::clone(&bytes_archived), total_bytes);
 
             let client = reqwest::Client::new();
             let response = client
@@ -367,6 +581,7 @@ impl OutgoingServerTransfer {
 
             let (archive, checksum, _) = tokio::join!(archive_task, checksum_task, response);
             progress_task.abort();
+            drop(transfer_files);
 
             if let Err(err) = archive {
                 tracing::error!(
@@ -392,62 +607,7 @@ impl OutgoingServerTransfer {
 
             Self::log(&server, "Finished streaming archive to destination.");
 
-            for backup in backups {
-                match backup_manager.find(backup).await {
-                    Ok(Some(backup)) => {
-                        if delete_backups {
-                            if let Err(err) = backup.delete(&server.app_state.config).await {
-                                tracing::error!(
-                                    server = %server.uuid,
-                                    "failed to delete backup {}: {}",
-                                    backup.uuid(),
-                                    err
-                                );
-                            } else {
-                                tracing::info!(
-                                    server = %server.uuid,
-                                    "deleted backup {} after transfer",
-                                    backup.uuid()
-                                );
-                            }
-                        }
-                    }
-                    Ok(None) => {
-                        tracing::warn!(
-                            server = %server.uuid,
-                            "requested backup {} does not exist",
-                            backup
-                        );
-                    }
-                    Err(err) => {
-                        tracing::error!(
-                            server = %server.uuid,
-                            "failed to find backup {}: {:#?}",
-                            backup,
-                            err
-                        );
-                    }
-                }
-            }
-
-            server.transferring.store(false, Ordering::SeqCst);
-
-            tracing::info!(
-                server = %server.uuid,
-                "finished outgoing server transfer"
-            );
-
-            tokio::spawn(async move {
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-                server
-                    .websocket
-                    .send(super::websocket::WebsocketMessage::new(
-                        super::websocket::WebsocketEvent::ServerTransferStatus,
-                        &["completed".to_string()],
-                    ))
-                    .ok();
-            });
+            Self::transfer_success(&server, &backup_manager, backups, delete_backups).await;
         }));
 
         if let Some(old_task) = old_task {
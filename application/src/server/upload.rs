@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+};
+use tokio::sync::RwLock;
+
+pub struct UploadSession {
+    pub id: uuid::Uuid,
+    pub server: crate::server::Server,
+    pub user_uuid: uuid::Uuid,
+    pub user_ip: Option<std::net::IpAddr>,
+
+    pub path: PathBuf,
+    pub size: u64,
+    pub staging_path: PathBuf,
+
+    /// Held while data is written to the staged file or it is moved into place
+    pub write_lock: tokio::sync::Mutex<()>,
+    offset: AtomicU64,
+    last_activity: AtomicI64,
+}
+
+impl UploadSession {
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn set_offset(&self, offset: u64) {
+        self.offset.store(offset, Ordering::SeqCst);
+        self.touch();
+    }
+
+    #[inline]
+    pub fn touch(&self) {
+        self.last_activity
+            .store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn expired(&self, timeout: u64) -> bool {
+        chrono::Utc::now().timestamp() - self.last_activity.load(Ordering::Relaxed) > timeout as i64
+    }
+
+    #[inline]
+    pub fn parent(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// Gives back the space the staged data was counted with and removes it
+    pub async fn discard(&self) {
+        self.server
+            .filesystem
+            .async_allocate_in_path(self.parent(), -(self.offset() as i64), true)
+            .await;
+
+        tokio::fs::remove_file(&self.staging_path).await.ok();
+    }
+}
+
+pub struct UploadManager {
+    config: Arc<crate::config::Config>,
+    sessions: Arc<RwLock<HashMap<uuid::Uuid, Arc<UploadSession>>>>,
+
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl UploadManager {
+    pub fn new(config: Arc<crate::config::Config>) -> Self {
+        let sessions: Arc<RwLock<HashMap<uuid::Uuid, Arc<UploadSession>>>> =
+            Arc::new(RwLock::new(HashMap::new()));
+
+        std::fs::remove_dir_all(Self::staging_directory(&config)).ok();
+
+        Self {
+            task: tokio::spawn({
+                let config = Arc::clone(&config);
+                let sessions = Arc::clone(&sessions);
+
+                async move {
+                    loop {
+                        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+
+                        let mut expired = Vec::new();
+                        sessions.write().await.retain(|_, session| {
+                            if session.expired(config.api.upload_session_timeout)
+                                && session.write_lock.try_lock().is_ok()
+                            {
+                                expired.push(Arc::clone(session));
+                                return false;
+                            }
+
+                            true
+                        });
+
+                        for session in expired {
+                            tracing::debug!(
+                                server = %session.server.uuid,
+                                session = %session.id,
+                                "discarding abandoned upload session"
+                            );
+
+                            session.discard().await;
+                        }
+                    }
+                }
+            }),
+            config,
+            sessions,
+        }
+    }
+
+    #[inline]
+    pub fn staging_directory(config: &crate::config::Config) -> PathBuf {
+        Path::new(&config.system.tmp_directory).join("uploads")
+    }
+
+    pub async fn create(
+        &self,
+        server: crate::server::Server,
+        user_uuid: uuid::Uuid,
+        user_ip: Option<std::net::IpAddr>,
+        path: PathBuf,
+        size: u64,
+    ) -> Result<Arc<UploadSession>, anyhow::Error> {
+        let id = uuid::Uuid::new_v4();
+        let staging_directory = Self::staging_directory(&self.config);
+        tokio::fs::create_dir_all(&staging_directory).await?;
+
+        let staging_path = staging_directory.join(id.to_string());
+        tokio::fs::File::create(&staging_path).await?;
+
+        let session = Arc::new(UploadSession {
+            id,
+            server,
+            user_uuid,
+            user_ip,
+            path,
+            size,
+            staging_path,
+            write_lock: tokio::sync::Mutex::new(()),
+            offset: AtomicU64::new(0),
+            last_activity: AtomicI64::new(chrono::Utc::now().timestamp()),
+        });
+
+        self.sessions.write().await.insert(id, Arc::clone(&session));
+
+        Ok(session)
+    }
+
+    #[inline]
+    pub async fn get(&self, id: uuid::Uuid) -> Option<Arc<UploadSession>> {
+        self.sessions.read().await.get(&id).cloned()
+    }
+
+    #[inline]
+    pub async fn remove(&self, id: uuid::Uuid) -> Option<Arc<UploadSession>> {
+        self.sessions.write().await.remove(&id)
+    }
+}
+
+impl Drop for UploadManager {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
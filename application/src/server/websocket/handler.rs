@@ -46,10 +46,12 @@ pub async fn handle_ws(
         let (sender, mut reciever) = socket.split();
         let sender = Arc::new(Mutex::new(sender));
         let socket_jwt = Arc::new(RwLock::new(None));
+        let file_watch = Arc::new(Mutex::new(None));
 
         let writer = {
             let state = Arc::clone(&state);
             let socket_jwt = Arc::clone(&socket_jwt);
+            let file_watch = Arc::clone(&file_watch);
             let sender = Arc::clone(&sender);
             let server = server.clone();
 
@@ -85,7 +87,14 @@ pub async fn handle_ws(
                     {
                         Ok(Some((message, jwt))) => {
                             match super::message_handler::handle_message(
-                                &state, user_ip, &server, &sender, &jwt, message,
+                                &state,
+                                user_ip,
+                                &server,
+                                &sender,
+                                &socket_jwt,
+                                &file_watch,
+                                &jwt,
+                                message,
                             )
                             .await
                             {
@@ -171,7 +180,9 @@ pub async fn handle_ws(
                                     }
                                 }
                                 websocket::WebsocketEvent::ServerBackupProgress
-                                | websocket::WebsocketEvent::ServerBackupCompleted => {
+                                | websocket::WebsocketEvent::ServerBackupCompleted
+                                | websocket::WebsocketEvent::ServerBackupVerifyProgress
+                                | websocket::WebsocketEvent::ServerBackupVerifyCompleted => {
                                     if !socket_jwt
                                         .permissions
                                         .has_permission(Permission::BackupRead)
@@ -308,5 +319,9 @@ pub async fn handle_ws(
                 );
             }
         }
+
+        if let Some(task) = file_watch.lock().await.take() {
+            task.abort();
+        }
     })
 }
@@ -1,20 +1,27 @@
 use super::{WebsocketEvent, WebsocketJwtPayload, WebsocketMessage};
 use crate::server::{
     activity::{Activity, ActivityEvent},
+    filesystem::watcher::FileWatcher,
     permissions::Permission,
 };
 use anyhow::Context;
 use axum::extract::ws::{Message, WebSocket};
 use futures_util::stream::SplitSink;
 use serde_json::json;
-use std::{net::IpAddr, str::FromStr};
-use tokio::sync::Mutex;
+use std::{net::IpAddr, str::FromStr, sync::Arc};
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::JoinHandle,
+};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_message(
     state: &crate::routes::AppState,
     user_ip: IpAddr,
     server: &crate::server::Server,
-    sender: &Mutex<SplitSink<WebSocket, Message>>,
+    sender: &Arc<Mutex<SplitSink<WebSocket, Message>>>,
+    socket_jwt_lock: &Arc<RwLock<Option<Arc<WebsocketJwtPayload>>>>,
+    file_watch: &Mutex<Option<JoinHandle<()>>>,
     socket_jwt: &WebsocketJwtPayload,
     message: super::WebsocketMessage,
 ) -> Result<(), anyhow::Error> {
@@ -339,6 +346,141 @@ pub async fn handle_message(
                 }
             }
         }
+        WebsocketEvent::WatchFiles => {
+            if !socket_jwt.permissions.has_permission(Permission::FileRead) {
+                tracing::debug!(
+                    server = %server.uuid,
+                    "jwt does not have permission to watch files: {:?}",
+                    socket_jwt.permissions
+                );
+
+                return Ok(());
+            }
+
+            let root = match server
+                .filesystem
+                .async_canonicalize(message.args.first().map_or("", |v| v.as_str()))
+                .await
+            {
+                Ok(root) => root,
+                Err(_) => {
+                    super::send_message(
+                        sender,
+                        server.get_daemon_error("Directory to watch not found."),
+                    )
+                    .await;
+
+                    return Ok(());
+                }
+            };
+
+            if !server
+                .filesystem
+                .async_metadata(&root)
+                .await
+                .is_ok_and(|metadata| metadata.is_dir())
+                || server.filesystem.is_ignored(&root, true).await
+            {
+                super::send_message(
+                    sender,
+                    server.get_daemon_error("Directory to watch not found."),
+                )
+                .await;
+
+                return Ok(());
+            }
+
+            if let Some(task) = file_watch.lock().await.take() {
+                task.abort();
+            }
+
+            let mut watcher = match FileWatcher::new(server, root).await {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    tracing::debug!(
+                        server = %server.uuid,
+                        "failed to watch files: {:#?}",
+                        err
+                    );
+
+                    super::send_message(
+                        sender,
+                        server.get_daemon_error(
+                            "Unable to watch directory, too many directories may be watched already.",
+                        ),
+                    )
+                    .await;
+
+                    return Ok(());
+                }
+            };
+
+            *file_watch.lock().await = Some(tokio::spawn({
+                let config = Arc::clone(&state.config);
+                let server = server.clone();
+                let sender = Arc::clone(sender);
+                let socket_jwt = Arc::clone(socket_jwt_lock);
+
+                async move {
+                    loop {
+                        if watcher.take_limit_reached() {
+                            super::send_message(
+                                &sender,
+                                server.get_daemon_error(
+                                    "Too many directories are watched, changes in some directories will not be reported.",
+                                ),
+                            )
+                            .await;
+                        }
+
+                        let changes = match watcher.next_changes().await {
+                            Ok(changes) => changes,
+                            Err(err) => {
+                                tracing::error!(
+                                    server = %server.uuid,
+                                    "failed to read file changes: {:#?}",
+                                    err
+                                );
+
+                                break;
+                            }
+                        };
+
+                        if changes.is_empty() {
+                            continue;
+                        }
+
+                        let allowed = match socket_jwt.read().await.as_ref() {
+                            Some(jwt) => {
+                                jwt.permissions.has_permission(Permission::FileRead)
+                                    && jwt.base.validate(&config.jwt).await
+                            }
+                            None => false,
+                        };
+
+                        if !allowed {
+                            continue;
+                        }
+
+                        let changes: Vec<String> = changes
+                            .iter()
+                            .filter_map(|change| serde_json::to_string(change).ok())
+                            .collect();
+
+                        super::send_message(
+                            &sender,
+                            WebsocketMessage::new(WebsocketEvent::ServerFileChanges, &changes),
+                        )
+                        .await;
+                    }
+                }
+            }));
+        }
+        WebsocketEvent::UnwatchFiles => {
+            if let Some(task) = file_watch.lock().await.take() {
+                task.abort();
+            }
+        }
         _ => {
             tracing::debug!(
                 "received websocket message that will not be handled: {:?}",
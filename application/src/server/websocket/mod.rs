@@ -41,6 +41,10 @@ pub enum WebsocketEvent {
     SendCommand,
     #[serde(rename = "send stats")]
     SendStats,
+    #[serde(rename = "watch files")]
+    WatchFiles,
+    #[serde(rename = "unwatch files")]
+    UnwatchFiles,
     #[serde(rename = "daemon error")]
     Error,
     #[serde(rename = "jwt error")]
@@ -72,6 +76,10 @@ pub enum WebsocketEvent {
     ServerBackupRestoreProgress,
     #[serde(rename = "backup restore completed")]
     ServerBackupRestoreCompleted,
+    #[serde(rename = "backup verify progress")]
+    ServerBackupVerifyProgress,
+    #[serde(rename = "backup verify completed")]
+    ServerBackupVerifyCompleted,
     #[serde(rename = "transfer logs")]
     ServerTransferLogs,
     #[serde(rename = "transfer status")]
@@ -86,6 +94,8 @@ pub enum WebsocketEvent {
     ServerOperationCompleted,
     #[serde(rename = "operation error")]
     ServerOperationError,
+    #[serde(rename = "file changes")]
+    ServerFileChanges,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -1,6 +1,7 @@
-use crate::{remote::AuthenticationType, routes::State};
+use crate::{remote::AuthenticationType, routes::State, server::permissions::Permission};
 use russh::{
     Channel, ChannelId, MethodSet,
+    keys::{Certificate, HashAlg, PublicKey, ssh_key::certificate::CertType},
     server::{Auth, Msg, Session},
 };
 use russh_sftp::protocol::StatusCode;
@@ -27,6 +28,48 @@ fn validate_username(username: &str) -> bool {
     true
 }
 
+/// Whether the panel rejected the credentials, as opposed to being unreachable or failing
+fn is_auth_rejection(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| {
+            matches!(
+                status,
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+            )
+        })
+}
+
+/// Restrictions placed on a session by the critical options and
+/// extensions of the certificate it authenticated with
+pub struct KeyRestrictions {
+    pub force_command: Option<String>,
+    pub pty: bool,
+    pub port_forwarding: bool,
+}
+
+impl Default for KeyRestrictions {
+    fn default() -> Self {
+        Self {
+            force_command: None,
+            pty: true,
+            port_forwarding: true,
+        }
+    }
+}
+
+fn source_address_allowed(source_address: &str, ip: Option<IpAddr>) -> bool {
+    let ip = match ip {
+        Some(ip) => ip.to_canonical(),
+        None => return false,
+    };
+
+    source_address
+        .split(',')
+        .filter_map(|cidr| cidr.trim().parse::<cidr::IpCidr>().ok())
+        .any(|cidr| cidr.contains(&ip))
+}
+
 pub struct SshSession {
     pub state: State,
     pub server: Option<crate::server::Server>,
@@ -36,6 +79,10 @@ pub struct SshSession {
 
     pub clients: HashMap<ChannelId, Channel<Msg>>,
     pub shell_clients: HashSet<ChannelId>,
+    /// The requested terminal type and size of channels with a pty
+    pub ptys: HashMap<ChannelId, (String, tokio::sync::watch::Sender<(u32, u32)>)>,
+    pub restrictions: KeyRestrictions,
+    pub limits: Arc<super::limits::SshLimits>,
 }
 
 impl SshSession {
@@ -52,6 +99,84 @@ impl SshSession {
     pub async fn get_channel(&mut self, channel_id: ChannelId) -> Option<Channel<Msg>> {
         self.clients.remove(&channel_id)
     }
+
+    /// Counts a new shell, exec or forwarding channel towards the session limit of the user
+    fn open_session(&self, user_uuid: uuid::Uuid) -> Option<super::limits::SessionGuard> {
+        let session = self.limits.open_session(&self.state.config, user_uuid);
+        if session.is_none() {
+            tracing::debug!(
+                user = %user_uuid,
+                "denied ssh session, user reached the session limit"
+            );
+        }
+
+        session
+    }
+
+    /// Checks a user certificate the way OpenSSH does for `TrustedUserCAKeys`,
+    /// the username has to be one of the principals of the certificate
+    fn validate_certificate(
+        &self,
+        username: &str,
+        certificate: &Certificate,
+    ) -> Result<KeyRestrictions, &'static str> {
+        let ca_fingerprints = self
+            .state
+            .config
+            .system
+            .sftp
+            .certificates
+            .trusted_user_ca_keys
+            .iter()
+            .filter_map(|key| PublicKey::from_openssh(key).ok())
+            .map(|key| key.fingerprint(HashAlg::Sha256))
+            .collect::<Vec<_>>();
+
+        if ca_fingerprints.is_empty() {
+            return Err("no trusted user certificate authorities configured");
+        }
+
+        if certificate.cert_type() != CertType::User {
+            return Err("not a user certificate");
+        }
+
+        if certificate
+            .validate_at(chrono::Utc::now().timestamp() as u64, &ca_fingerprints)
+            .is_err()
+        {
+            return Err("untrusted or expired certificate");
+        }
+
+        if !certificate
+            .valid_principals()
+            .iter()
+            .any(|principal| principal == username)
+        {
+            return Err("username is not a principal of the certificate");
+        }
+
+        let mut restrictions = KeyRestrictions {
+            force_command: None,
+            pty: certificate.extensions().contains_key("permit-pty"),
+            port_forwarding: certificate
+                .extensions()
+                .contains_key("permit-port-forwarding"),
+        };
+
+        for (name, value) in certificate.critical_options().iter() {
+            match name.as_str() {
+                "force-command" => restrictions.force_command = Some(value.clone()),
+                "source-address" => {
+                    if !source_address_allowed(value, self.user_ip) {
+                        return Err("source address not allowed by certificate");
+                    }
+                }
+                _ => return Err("unsupported critical option"),
+            }
+        }
+
+        Ok(restrictions)
+    }
 }
 
 impl russh::server::Handler for SshSession {
@@ -79,6 +204,16 @@ impl russh::server::Handler for SshSession {
             });
         }
 
+        if self.limits.is_locked_out(&self.state.config, self.user_ip) {
+            tracing::debug!(
+                username = username,
+                ip = ?self.user_ip,
+                "rejected password login from locked out ip"
+            );
+
+            return Ok(Auth::reject());
+        }
+
         let (user, server, permissions, ignored_files) = match self
             .state
             .config
@@ -94,10 +229,16 @@ impl russh::server::Handler for SshSession {
                     err
                 );
 
+                if is_auth_rejection(&err) {
+                    self.limits
+                        .record_failed_login(&self.state.config, self.user_ip);
+                }
+
                 return Ok(Auth::reject());
             }
         };
 
+        self.limits.clear_failed_logins(self.user_ip);
         self.user_uuid = Some(user);
 
         let server = match self
@@ -203,6 +344,99 @@ impl russh::server::Handler for SshSession {
         Ok(Auth::Accept)
     }
 
+    async fn auth_openssh_certificate(
+        &mut self,
+        username: &str,
+        certificate: &Certificate,
+    ) -> Result<Auth, Self::Error> {
+        if !validate_username(username) {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(self.get_auth_methods()),
+                partial_success: false,
+            });
+        }
+
+        let restrictions = match self.validate_certificate(username, certificate) {
+            Ok(restrictions) => restrictions,
+            Err(reason) => {
+                tracing::debug!(
+                    username = username,
+                    key_id = certificate.key_id(),
+                    "failed to authenticate (certificate): {}",
+                    reason
+                );
+
+                return Ok(Auth::Reject {
+                    proceed_with_methods: Some(self.get_auth_methods()),
+                    partial_success: false,
+                });
+            }
+        };
+
+        let (user, server, permissions, ignored_files) = match self
+            .state
+            .config
+            .client
+            .get_sftp_certificate_auth(
+                username,
+                certificate.key_id(),
+                &PublicKey::from(certificate.public_key().clone())
+                    .fingerprint(HashAlg::Sha256)
+                    .to_string(),
+            )
+            .await
+        {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::debug!(
+                    username = username,
+                    "failed to authenticate (certificate): {:#?}",
+                    err
+                );
+
+                return Ok(Auth::Reject {
+                    proceed_with_methods: Some(self.get_auth_methods()),
+                    partial_success: false,
+                });
+            }
+        };
+
+        self.user_uuid = Some(user);
+
+        let server = match self
+            .state
+            .server_manager
+            .get_servers()
+            .await
+            .iter()
+            .find(|s| s.uuid == server)
+            .cloned()
+        {
+            Some(server) => server,
+            None => return Ok(Auth::reject()),
+        };
+
+        tracing::debug!(
+            key_id = certificate.key_id(),
+            "user {} authenticated with certificate",
+            username
+        );
+
+        if server.is_locked_state() {
+            return Ok(Auth::reject());
+        }
+
+        server
+            .user_permissions
+            .set_permissions(user, permissions, &ignored_files)
+            .await;
+
+        self.server = Some(server);
+        self.restrictions = restrictions;
+
+        Ok(Auth::Accept)
+    }
+
     async fn channel_open_session(
         &mut self,
         channel: Channel<Msg>,
@@ -224,6 +458,54 @@ impl russh::server::Handler for SshSession {
 
         self.clients.remove(&channel);
         self.shell_clients.retain(|&id| id != channel);
+        self.ptys.remove(&channel);
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn pty_request(
+        &mut self,
+        channel_id: ChannelId,
+        term: &str,
+        col_width: u32,
+        row_height: u32,
+        _pix_width: u32,
+        _pix_height: u32,
+        _modes: &[(russh::Pty, u32)],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        tracing::debug!("channel pty request: {}", channel_id);
+
+        if !self.restrictions.pty {
+            session.channel_failure(channel_id)?;
+            return Ok(());
+        }
+
+        self.ptys.insert(
+            channel_id,
+            (
+                term.to_string(),
+                tokio::sync::watch::Sender::new((col_width, row_height)),
+            ),
+        );
+        session.channel_success(channel_id)?;
+
+        Ok(())
+    }
+
+    async fn window_change_request(
+        &mut self,
+        channel_id: ChannelId,
+        col_width: u32,
+        row_height: u32,
+        _pix_width: u32,
+        _pix_height: u32,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        if let Some((_, size)) = self.ptys.get(&channel_id) {
+            size.send_replace((col_width, row_height));
+        }
 
         Ok(())
     }
@@ -249,11 +531,69 @@ impl russh::server::Handler for SshSession {
             None => return Err(Box::new(StatusCode::PermissionDenied)),
         };
 
+        let Some(ssh_session) = self.open_session(user_uuid) else {
+            session.channel_failure(channel_id)?;
+            return Ok(());
+        };
+
+        if let Some(command) = self.restrictions.force_command.clone() {
+            if command == "internal-sftp" {
+                session.channel_failure(channel_id)?;
+                return Ok(());
+            }
+
+            let channel = match self.get_channel(channel_id).await {
+                Some(channel) => channel,
+                None => return Err(Box::new(StatusCode::PermissionDenied)),
+            };
+
+            tracing::debug!("running forced command instead of shell: {}", command);
+
+            session.channel_success(channel_id)?;
+            let exec = super::exec::ExecSession {
+                state: Arc::clone(&self.state),
+                server,
+
+                user_ip: self.user_ip,
+                user_uuid,
+
+                _session: ssh_session,
+            };
+            exec.run(command, channel);
+
+            return Ok(());
+        }
+
         let channel = match self.get_channel(channel_id).await {
             Some(channel) => channel,
             None => return Err(Box::new(StatusCode::PermissionDenied)),
         };
 
+        if self.state.config.system.sftp.shell.container.enabled
+            && let Some((term, size)) = self.ptys.get(&channel_id)
+            && server
+                .user_permissions
+                .has_permission(user_uuid, Permission::AdminSshContainerShell)
+                .await
+        {
+            session.channel_success(channel_id)?;
+            let terminal = super::terminal::TerminalSession {
+                state: Arc::clone(&self.state),
+                server,
+
+                user_ip: self.user_ip,
+                user_uuid,
+
+                term: term.clone(),
+                size: size.subscribe(),
+
+                _session: ssh_session,
+            };
+            terminal.run(channel);
+
+            return Ok(());
+        }
+
         self.shell_clients.insert(channel_id);
 
         session.channel_success(channel_id)?;
@@ -264,6 +604,11 @@ impl russh::server::Handler for SshSession {
             user_ip: self.user_ip,
             user_uuid,
             mode: super::shell::ShellMode::Normal,
+
+            follow: None,
+            following: Default::default(),
+
+            _session: ssh_session,
         };
         ssh.run(channel);
 
@@ -276,7 +621,21 @@ impl russh::server::Handler for SshSession {
         data: &[u8],
         session: &mut Session,
     ) -> Result<(), Self::Error> {
-        let command = String::from_utf8_lossy(data);
+        let command = match &self.restrictions.force_command {
+            Some(command) if command == "internal-sftp" => {
+                session.channel_failure(channel_id)?;
+                return Ok(());
+            }
+            Some(command) => {
+                tracing::debug!(
+                    "running forced command instead of: {}",
+                    String::from_utf8_lossy(data)
+                );
+
+                command.clone()
+            }
+            None => String::from_utf8_lossy(data).to_string(),
+        };
 
         let user_uuid = match self.user_uuid {
             Some(uuid) => uuid,
@@ -288,6 +647,11 @@ impl russh::server::Handler for SshSession {
             None => return Err(Box::new(StatusCode::PermissionDenied)),
         };
 
+        let Some(ssh_session) = self.open_session(user_uuid) else {
+            session.channel_failure(channel_id)?;
+            return Ok(());
+        };
+
         let channel = match self.get_channel(channel_id).await {
             Some(channel) => channel,
             None => return Err(Box::new(StatusCode::PermissionDenied)),
@@ -302,12 +666,74 @@ impl russh::server::Handler for SshSession {
 
             user_ip: self.user_ip,
             user_uuid,
+
+            _session: ssh_session,
         };
-        exec.run(command.to_string(), channel);
+        exec.run(command, channel);
 
         Ok(())
     }
 
+    async fn channel_open_direct_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        host_to_connect: &str,
+        port_to_connect: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        if !self.state.config.system.sftp.port_forwarding.enabled
+            || !self.restrictions.port_forwarding
+        {
+            return Ok(false);
+        }
+
+        let (Some(user_uuid), Some(server)) = (self.user_uuid, &self.server) else {
+            return Ok(false);
+        };
+
+        if !server
+            .user_permissions
+            .has_permission(user_uuid, Permission::AdminSshPortForwarding)
+            .await
+        {
+            return Ok(false);
+        }
+
+        let Some(ssh_session) = self.open_session(user_uuid) else {
+            return Ok(false);
+        };
+
+        let forward = super::forward::ForwardSession {
+            state: Arc::clone(&self.state),
+            server: server.clone(),
+
+            user_ip: self.user_ip,
+            user_uuid,
+
+            _session: ssh_session,
+        };
+
+        let address = match forward.resolve(host_to_connect, port_to_connect).await {
+            Some(address) => address,
+            None => {
+                tracing::debug!(
+                    host = host_to_connect,
+                    port = port_to_connect,
+                    "denied forwarded channel to disallowed destination"
+                );
+
+                return Ok(false);
+            }
+        };
+
+        tracing::debug!("opening forwarded channel to {}", address);
+        forward.run(channel, address);
+
+        Ok(true)
+    }
+
     async fn data(
         &mut self,
         channel_id: ChannelId,
@@ -337,11 +763,39 @@ impl russh::server::Handler for SshSession {
             None => return Err(Box::new(StatusCode::PermissionDenied)),
         };
 
-        if name == "sftp" {
+        if name == "sftp"
+            && self
+                .restrictions
+                .force_command
+                .as_ref()
+                .is_none_or(|command| command == "internal-sftp")
+        {
             let channel = match self.get_channel(channel_id).await {
                 Some(channel) => channel,
                 None => return Err(Box::new(StatusCode::PermissionDenied)),
             };
+
+            let limits =
+                match self
+                    .limits
+                    .open_sftp_session(&self.state.config, server.uuid, user_uuid)
+                {
+                    Some(limits) => limits,
+                    None => {
+                        tracing::debug!(
+                            user = %user_uuid,
+                            "denied sftp session, user reached the session limit"
+                        );
+
+                        session.channel_failure(channel_id)?;
+                        return Ok(());
+                    }
+                };
+
+            self.state
+                .metrics
+                .sftp_sessions
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             let sftp = super::sftp::SftpSession {
                 state: Arc::clone(&self.state),
                 server,
@@ -351,6 +805,7 @@ impl russh::server::Handler for SshSession {
 
                 handle_id: 0,
                 handles: HashMap::new(),
+                limits,
             };
 
             session.channel_success(channel_id)?;
@@ -20,6 +20,8 @@ pub struct ExecSession {
 
     pub user_ip: Option<IpAddr>,
     pub user_uuid: uuid::Uuid,
+
+    pub _session: super::limits::SessionGuard,
 }
 
 impl ExecSession {
@@ -0,0 +1,128 @@
+use crate::routes::State;
+use russh::{Channel, server::Msg};
+use std::net::{IpAddr, SocketAddr};
+
+pub struct ForwardSession {
+    pub state: State,
+    pub server: crate::server::Server,
+
+    pub user_ip: Option<IpAddr>,
+    pub user_uuid: uuid::Uuid,
+
+    pub _session: super::limits::SessionGuard,
+}
+
+impl ForwardSession {
+    async fn container_ips(&self) -> Vec<IpAddr> {
+        let docker_id = match self.server.container.read().await.as_ref() {
+            Some(container) => container.docker_id.clone(),
+            None => return Vec::new(),
+        };
+
+        let container = match self.state.docker.inspect_container(&docker_id, None).await {
+            Ok(container) => container,
+            Err(_) => return Vec::new(),
+        };
+
+        container
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .map(|networks| {
+                networks
+                    .into_values()
+                    .filter_map(|network| network.ip_address?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Resolves where a forwarded connection may go, only allocation ports
+    /// on the server's own container are reachable, `localhost` refers to the container
+    pub async fn resolve(&self, host: &str, port: u32) -> Option<SocketAddr> {
+        let port = u16::try_from(port).ok()?;
+
+        {
+            let configuration = self.server.configuration.read().await;
+            let allocations = &configuration.allocations;
+
+            if allocations.default.as_ref().map(|default| default.port) != Some(port)
+                && !allocations
+                    .mappings
+                    .values()
+                    .any(|ports| ports.contains(&port))
+            {
+                return None;
+            }
+        }
+
+        let container_ips = self.container_ips().await;
+
+        let ip = if host == "localhost" {
+            *container_ips.first()?
+        } else {
+            let ip: IpAddr = host.parse().ok()?;
+
+            if ip.is_loopback() {
+                *container_ips.first()?
+            } else if container_ips.contains(&ip) {
+                ip
+            } else {
+                return None;
+            }
+        };
+
+        Some(SocketAddr::new(ip, port))
+    }
+
+    pub fn run(self, channel: Channel<Msg>, address: SocketAddr) {
+        tokio::spawn(async move {
+            let mut stream = match tokio::time::timeout(
+                std::time::Duration::from_secs(10),
+                tokio::net::TcpStream::connect(address),
+            )
+            .await
+            {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(err)) => {
+                    tracing::debug!(
+                        server = %self.server.uuid,
+                        address = %address,
+                        "failed to connect forwarded channel: {:#?}",
+                        err
+                    );
+
+                    channel.close().await.ok();
+                    return;
+                }
+                Err(_) => {
+                    tracing::debug!(
+                        server = %self.server.uuid,
+                        address = %address,
+                        "timed out connecting forwarded channel"
+                    );
+
+                    channel.close().await.ok();
+                    return;
+                }
+            };
+
+            tracing::info!(
+                server = %self.server.uuid,
+                user = %self.user_uuid,
+                ip = ?self.user_ip,
+                address = %address,
+                "opened forwarded ssh channel"
+            );
+
+            let mut channel = channel.into_stream();
+            if let Err(err) = tokio::io::copy_bidirectional(&mut channel, &mut stream).await {
+                tracing::debug!(
+                    server = %self.server.uuid,
+                    address = %address,
+                    "forwarded channel closed with error: {:#?}",
+                    err
+                );
+            }
+        });
+    }
+}
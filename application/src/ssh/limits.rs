@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+/// A token bucket shared by all sessions a bandwidth limit applies to,
+/// bursts of up to a second of transfer are allowed
+struct BandwidthLimiter {
+    bytes_per_second: u64,
+    next_free: Mutex<Instant>,
+}
+
+impl BandwidthLimiter {
+    fn new_with_bytes_per_second(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until `bytes` may be transferred
+    async fn consume(&self, bytes: usize) {
+        if crate::unlikely(bytes == 0) {
+            return;
+        }
+
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+        let delay = {
+            let now = Instant::now();
+            let mut next_free = self.next_free.lock().unwrap();
+            *next_free = (*next_free).max(now) + cost;
+
+            next_free
+                .saturating_duration_since(now)
+                .saturating_sub(Duration::from_secs(1))
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum BandwidthKey {
+    ServerRead(uuid::Uuid),
+    ServerWrite(uuid::Uuid),
+    UserRead(uuid::Uuid),
+    UserWrite(uuid::Uuid),
+}
+
+/// Limits that apply across all ssh connections of the node
+#[derive(Default)]
+pub struct SshLimits {
+    bandwidth: Mutex<HashMap<BandwidthKey, Weak<BandwidthLimiter>>>,
+    sessions: Mutex<HashMap<uuid::Uuid, usize>>,
+    failed_logins: Mutex<HashMap<IpAddr, (u32, Instant)>>,
+}
+
+impl SshLimits {
+    fn bandwidth_limiter(&self, key: BandwidthKey, limit: u64) -> Option<Arc<BandwidthLimiter>> {
+        if limit == 0 {
+            return None;
+        }
+
+        let mut bandwidth = self.bandwidth.lock().unwrap();
+        if let Some(limiter) = bandwidth.get(&key).and_then(Weak::upgrade) {
+            return Some(limiter);
+        }
+
+        bandwidth.retain(|_, limiter| limiter.strong_count() > 0);
+
+        let limiter = Arc::new(BandwidthLimiter::new_with_bytes_per_second(
+            limit * 1024 * 1024,
+        ));
+        bandwidth.insert(key, Arc::downgrade(&limiter));
+
+        Some(limiter)
+    }
+
+    /// Opens a new sftp, shell, exec or forwarding session of a user, `None` if the
+    /// user already has the maximum amount of concurrent sessions open
+    pub fn open_session(
+        self: &Arc<Self>,
+        config: &crate::config::Config,
+        user: uuid::Uuid,
+    ) -> Option<SessionGuard> {
+        let user_sessions = config.system.sftp.limits.user_sessions;
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let count = sessions.entry(user).or_default();
+
+        if user_sessions != 0 && *count >= user_sessions {
+            return None;
+        }
+
+        *count += 1;
+
+        Some(SessionGuard {
+            limits: Arc::clone(self),
+            user,
+        })
+    }
+
+    /// Returns the limits of a new sftp session, `None` if the user
+    /// already has the maximum amount of concurrent sessions open
+    pub fn open_sftp_session(
+        self: &Arc<Self>,
+        config: &crate::config::Config,
+        server: uuid::Uuid,
+        user: uuid::Uuid,
+    ) -> Option<SftpLimits> {
+        let limits = &config.system.sftp.limits;
+
+        Some(SftpLimits {
+            _session: self.open_session(config, user)?,
+            read: [
+                self.bandwidth_limiter(BandwidthKey::ServerRead(server), limits.server_read_limit),
+                self.bandwidth_limiter(BandwidthKey::UserRead(user), limits.user_read_limit),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            write: [
+                self.bandwidth_limiter(
+                    BandwidthKey::ServerWrite(server),
+                    limits.server_write_limit,
+                ),
+                self.bandwidth_limiter(BandwidthKey::UserWrite(user), limits.user_write_limit),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        })
+    }
+
+    /// Whether password logins from this ip are blocked because of too many failed attempts
+    pub fn is_locked_out(&self, config: &crate::config::Config, ip: Option<IpAddr>) -> bool {
+        let lockout = &config.system.sftp.lockout;
+        let Some(ip) = ip else {
+            return false;
+        };
+
+        if lockout.max_attempts == 0 {
+            return false;
+        }
+
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        match failed_logins.get(&ip) {
+            Some((attempts, last_attempt))
+                if last_attempt.elapsed() < Duration::from_secs(lockout.duration) =>
+            {
+                *attempts >= lockout.max_attempts
+            }
+            Some(_) => {
+                failed_logins.remove(&ip);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn record_failed_login(&self, config: &crate::config::Config, ip: Option<IpAddr>) {
+        let lockout = &config.system.sftp.lockout;
+        let Some(ip) = ip else {
+            return;
+        };
+
+        if lockout.max_attempts == 0 {
+            return;
+        }
+
+        let duration = Duration::from_secs(lockout.duration);
+
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        failed_logins.retain(|_, (_, last_attempt)| last_attempt.elapsed() < duration);
+
+        let (attempts, last_attempt) = failed_logins.entry(ip).or_insert((0, Instant::now()));
+        *attempts += 1;
+        *last_attempt = Instant::now();
+
+        if *attempts == lockout.max_attempts {
+            tracing::warn!(
+                ip = %ip,
+                "locking out ip for {}s after {} failed ssh logins",
+                lockout.duration,
+                attempts
+            );
+        }
+    }
+
+    pub fn clear_failed_logins(&self, ip: Option<IpAddr>) {
+        if let Some(ip) = ip {
+            self.failed_logins.lock().unwrap().remove(&ip);
+        }
+    }
+}
+
+/// Counts towards the concurrent sessions of its user until dropped
+pub struct SessionGuard {
+    limits: Arc<SshLimits>,
+    user: uuid::Uuid,
+}
+
+/// Bandwidth limits of a single sftp session
+pub struct SftpLimits {
+    _session: SessionGuard,
+
+    read: Vec<Arc<BandwidthLimiter>>,
+    write: Vec<Arc<BandwidthLimiter>>,
+}
+
+impl SftpLimits {
+    pub async fn read(&self, bytes: usize) {
+        for limiter in &self.read {
+            limiter.consume(bytes).await;
+        }
+    }
+
+    pub async fn write(&self, bytes: usize) {
+        for limiter in &self.write {
+            limiter.consume(bytes).await;
+        }
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let mut sessions = self.limits.sessions.lock().unwrap();
+        if let Some(count) = sessions.get_mut(&self.user) {
+            *count = count.saturating_sub(1);
+
+            if *count == 0 {
+                sessions.remove(&self.user);
+            }
+        }
+    }
+}
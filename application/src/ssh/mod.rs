@@ -7,11 +7,25 @@ use std::{
 
 mod auth;
 mod exec;
+mod forward;
+mod limits;
 mod sftp;
 mod shell;
+mod terminal;
 
 pub struct Server {
     pub state: State,
+
+    limits: Arc<limits::SshLimits>,
+}
+
+impl Server {
+    pub fn new(state: State) -> Self {
+        Self {
+            state,
+            limits: Arc::new(limits::SshLimits::default()),
+        }
+    }
 }
 
 impl russh::server::Server for Server {
@@ -27,6 +41,9 @@ impl russh::server::Server for Server {
 
             clients: HashMap::new(),
             shell_clients: HashSet::new(),
+            ptys: HashMap::new(),
+            restrictions: auth::KeyRestrictions::default(),
+            limits: Arc::clone(&self.limits),
         }
     }
 }
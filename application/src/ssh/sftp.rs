@@ -63,6 +63,7 @@ pub struct SftpSession {
 
     pub handle_id: u64,
     pub handles: HashMap<String, ServerHandle>,
+    pub limits: super::limits::SftpLimits,
 }
 
 impl SftpSession {
@@ -160,6 +161,15 @@ impl SftpSession {
     }
 }
 
+impl Drop for SftpSession {
+    fn drop(&mut self) {
+        self.state
+            .metrics
+            .sftp_sessions
+            .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 impl russh_sftp::server::Handler for SftpSession {
     type Error = StatusCode;
 
@@ -348,7 +358,13 @@ impl russh_sftp::server::Handler for SftpSession {
                 return Err(StatusCode::NoSuchFile);
             }
 
-            if self.server.filesystem.truncate_path(&path).await.is_err() {
+            if self
+                .server
+                .trash
+                .remove_path(&self.server, &path)
+                .await
+                .is_err()
+            {
                 return Err(StatusCode::NoSuchFile);
             }
 
@@ -402,7 +418,12 @@ impl russh_sftp::server::Handler for SftpSession {
             }
 
             if path != self.server.filesystem.base_path
-                && self.server.filesystem.truncate_path(&path).await.is_err()
+                && self
+                    .server
+                    .trash
+                    .remove_path(&self.server, &path)
+                    .await
+                    .is_err()
             {
                 return Err(StatusCode::NoSuchFile);
             }
@@ -928,6 +949,17 @@ impl russh_sftp::server::Handler for SftpSession {
             return Err(StatusCode::NoSuchFile);
         }
 
+        if pflags.contains(OpenFlags::TRUNCATE)
+            && let Err(err) = self.server.trash.preserve_file(&self.server, &path).await
+        {
+            tracing::warn!(
+                server = %self.server.uuid,
+                path = %path.display(),
+                "failed to preserve overwritten file in trash: {:#?}",
+                err
+            );
+        }
+
         let mut activity_event = None;
         if pflags.contains(OpenFlags::TRUNCATE) || pflags.contains(OpenFlags::CREATE) {
             activity_event = Some(ActivityEvent::SftpCreate);
@@ -1020,6 +1052,8 @@ impl russh_sftp::server::Handler for SftpSession {
             return Err(StatusCode::Eof);
         }
 
+        self.limits.read(data.len()).await;
+
         Ok(Data { id, data })
     }
 
@@ -1056,6 +1090,8 @@ impl russh_sftp::server::Handler for SftpSession {
             return Err(StatusCode::Failure);
         }
 
+        self.limits.write(data.len()).await;
+
         tokio::task::spawn_blocking({
             let file = Arc::clone(&handle.file);
 
@@ -1,14 +1,26 @@
 use crate::{
+    io::compression::CompressionType,
     routes::State,
     server::{
         activity::{Activity, ActivityEvent},
+        backup::adapters::BackupAdapter,
+        filesystem::operations::FilesystemOperation,
         permissions::Permission,
+        schedule::actions::ArchiveFormat,
         websocket::WebsocketEvent,
     },
 };
 use russh::{Channel, ChannelWriteHalf, server::Msg};
 use serde_json::json;
-use std::{net::IpAddr, pin::Pin, sync::Arc};
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     sync::broadcast::error::RecvError,
@@ -27,6 +39,78 @@ pub struct ShellSession {
     pub user_ip: Option<IpAddr>,
     pub user_uuid: uuid::Uuid,
     pub mode: ShellMode,
+
+    /// The task streaming the console output of `logs -f`, stopped by any key press
+    pub follow: Option<tokio::task::AbortHandle>,
+    /// Whether the console output is currently printed by `logs -f` instead of the shell itself
+    pub following: Arc<AtomicBool>,
+
+    pub _session: super::limits::SessionGuard,
+}
+
+fn archive_format(name: &str) -> Option<ArchiveFormat> {
+    let name = name.to_lowercase();
+
+    Some(if name.ends_with(".tar") {
+        ArchiveFormat::Tar
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        ArchiveFormat::TarGz
+    } else if name.ends_with(".tar.xz") {
+        ArchiveFormat::TarXz
+    } else if name.ends_with(".tar.bz2") {
+        ArchiveFormat::TarBz2
+    } else if name.ends_with(".tar.lz4") {
+        ArchiveFormat::TarLz4
+    } else if name.ends_with(".tar.zst") {
+        ArchiveFormat::TarZstd
+    } else if name.ends_with(".zip") {
+        ArchiveFormat::Zip
+    } else if name.ends_with(".7z") {
+        ArchiveFormat::SevenZip
+    } else {
+        return None;
+    })
+}
+
+fn describe_operation(operation: &FilesystemOperation) -> String {
+    let (kind, path, progress, total) = match operation {
+        FilesystemOperation::Compress {
+            path,
+            progress,
+            total,
+        } => ("compress", path, progress, total),
+        FilesystemOperation::Decompress {
+            path,
+            progress,
+            total,
+            ..
+        } => ("decompress", path, progress, total),
+        FilesystemOperation::Pull {
+            path,
+            progress,
+            total,
+        } => ("pull", path, progress, total),
+        FilesystemOperation::Replace {
+            path,
+            progress,
+            total,
+        } => ("replace", path, progress, total),
+    };
+
+    let progress = progress.load(std::sync::atomic::Ordering::Relaxed);
+    let total = total.load(std::sync::atomic::Ordering::Relaxed);
+
+    format!(
+        "{kind} {} - {} / {} ({:.2}%)",
+        path.display(),
+        human_bytes::human_bytes(progress as f64),
+        human_bytes::human_bytes(total as f64),
+        if total == 0 {
+            0.0
+        } else {
+            (progress as f64 / total as f64 * 100.0).min(100.0)
+        }
+    )
 }
 
 impl ShellSession {
@@ -38,10 +122,75 @@ impl ShellSession {
             .await
     }
 
+    #[inline]
+    async fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.server.filesystem.is_ignored(path, is_dir).await
+            || self
+                .server
+                .user_permissions
+                .is_ignored(self.user_uuid, path, is_dir)
+                .await
+    }
+
+    /// Stops a running `logs -f`, returns whether one was running
+    async fn stop_following(&mut self, writer: &mut Pin<Box<impl tokio::io::AsyncWrite>>) -> bool {
+        let Some(follow) = self.follow.take() else {
+            return false;
+        };
+
+        follow.abort();
+        self.following.store(false, Ordering::Relaxed);
+
+        writer.write_all(b"\r\x1b[2K").await.unwrap_or_default();
+        writer.flush().await.unwrap_or_default();
+
+        true
+    }
+
+    fn start_following(&mut self, channel_writer: &ChannelWriteHalf<Msg>, json: bool) {
+        let server = self.server.clone();
+        let mut writer = channel_writer.make_writer();
+
+        self.following.store(true, Ordering::Relaxed);
+        self.follow = Some(
+            tokio::spawn(async move {
+                loop {
+                    if let Some(mut stdout) = server.container_stdout().await {
+                        loop {
+                            match stdout.recv().await {
+                                Ok(line) => {
+                                    let line = if json {
+                                        json!({ "line": line }).to_string()
+                                    } else {
+                                        line
+                                    };
+
+                                    if writer
+                                        .write_all(format!("{line}\r\n\x1b[2K").as_bytes())
+                                        .await
+                                        .is_err()
+                                    {
+                                        return;
+                                    }
+                                }
+                                Err(RecvError::Closed) => break,
+                                Err(RecvError::Lagged(_)) => {}
+                            }
+                        }
+                    }
+
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                }
+            })
+            .abort_handle(),
+        );
+    }
+
     async fn handle_cli_command(
         &mut self,
         line: &str,
         writer: &mut Pin<Box<impl tokio::io::AsyncWrite>>,
+        channel_writer: &ChannelWriteHalf<Msg>,
     ) {
         let prefix = &self.state.config.system.sftp.shell.cli.name;
         writer.write_all(b"\r\n").await.unwrap_or_default();
@@ -50,26 +199,52 @@ impl ShellSession {
             .bold()
             .paint(format!("[{} Daemon]:", self.state.config.app_name));
 
+        // with --json every message becomes a json object and command results
+        // are printed as a single json line after the command finishes
+        let json = line.split_whitespace().any(|segment| segment == "--json");
+        let mut output: Option<String> = None;
+        let mut follow = false;
+
         let mut writeln = async |line: &str| {
+            let line = if json {
+                json!({ "message": line }).to_string()
+            } else {
+                format!("{prelude} {line}")
+            };
+
             writer
-                .write_all(format!("{prelude} {line}\r\n\x1b[2K").as_bytes())
+                .write_all(format!("{line}\r\n\x1b[2K").as_bytes())
                 .await
                 .unwrap_or_default();
         };
 
-        let mut segments = line.split_whitespace();
-        segments.next();
+        let mut segments = line
+            .split_whitespace()
+            .skip(1)
+            .filter(|segment| *segment != "--json");
 
         match segments.next() {
             Some("help") => {
                 writeln("Available commands:").await;
-                writeln("  help    - Show this help message").await;
-                writeln("  version - Show the current version").await;
-                writeln("  power   - Send a power action to the server").await;
-                writeln("  stats   - Show server statistics").await;
+                writeln("  help       - Show this help message").await;
+                writeln("  version    - Show the current version").await;
+                writeln("  power      - Send a power action to the server").await;
+                writeln("  stats      - Show server statistics").await;
+                writeln("  logs       - Show the last lines of the server log, -f to follow it")
+                    .await;
+                writeln("  backups    - List, create or restore backups").await;
+                writeln("  schedules  - List, trigger or abort schedules").await;
+                writeln("  operations - List running file operations and pulls").await;
+                writeln("  compress   - Compress files into an archive").await;
+                writeln("  decompress - Decompress an archive").await;
+                writeln("Append --json to any command for machine readable output.").await;
             }
             Some("version") => {
-                writeln(&format!("Current version: {}", crate::VERSION)).await;
+                if json {
+                    output = Some(json!({ "version": crate::VERSION }).to_string());
+                } else {
+                    writeln(&format!("Current version: {}", crate::VERSION)).await;
+                }
             }
             Some("power") => match segments.next() {
                 Some("start") => {
@@ -255,66 +430,745 @@ impl ShellSession {
             Some("stats") => {
                 let resource_usage = self.server.resource_usage().await;
 
-                writeln("Server Statistics:").await;
-                writeln(&format!(
-                    "  CPU Usage: {:.2}% / {}",
-                    resource_usage.cpu_absolute,
-                    if self.server.configuration.read().await.build.cpu_limit == 0 {
-                        "Unlimited".to_string()
+                if json {
+                    output = Some(json!(resource_usage).to_string());
+                } else {
+                    writeln("Server Statistics:").await;
+                    writeln(&format!(
+                        "  CPU Usage: {:.2}% / {}",
+                        resource_usage.cpu_absolute,
+                        if self.server.configuration.read().await.build.cpu_limit == 0 {
+                            "Unlimited".to_string()
+                        } else {
+                            format!(
+                                "{}%",
+                                self.server.configuration.read().await.build.cpu_limit
+                            )
+                        }
+                    ))
+                    .await;
+                    writeln(&format!(
+                        "  Memory Usage: {} / {} ({:.2}%)",
+                        human_bytes::human_bytes(resource_usage.memory_bytes as f64),
+                        if self.server.configuration.read().await.build.memory_limit == 0 {
+                            "Unlimited".to_string()
+                        } else {
+                            human_bytes::human_bytes(
+                                (self.server.configuration.read().await.build.memory_limit
+                                    * 1024
+                                    * 1024) as f64,
+                            )
+                        },
+                        (resource_usage.memory_bytes as f64
+                            / resource_usage.memory_limit_bytes as f64
+                            * 100.0)
+                            .min(100.0)
+                    ))
+                    .await;
+                    writeln(&format!(
+                        "  Disk Usage: {} / {} ({:.2}%)",
+                        human_bytes::human_bytes(resource_usage.disk_bytes as f64),
+                        if self.server.filesystem.disk_limit() == 0 {
+                            "Unlimited".to_string()
+                        } else {
+                            human_bytes::human_bytes(self.server.filesystem.disk_limit() as f64)
+                        },
+                        (resource_usage.disk_bytes as f64
+                            / self.server.filesystem.disk_limit() as f64
+                            * 100.0)
+                            .min(100.0)
+                    ))
+                    .await;
+                    writeln("  Network Usage:").await;
+                    writeln(&format!(
+                        "    Received: {}",
+                        human_bytes::human_bytes(resource_usage.network.rx_bytes as f64)
+                    ))
+                    .await;
+                    writeln(&format!(
+                        "    Sent: {}",
+                        human_bytes::human_bytes(resource_usage.network.tx_bytes as f64)
+                    ))
+                    .await;
+                }
+            }
+            Some("logs") => {
+                if !self.has_permission(Permission::ControlConsole).await {
+                    writeln("You are missing the `control.console` permission to do this.").await;
+                    return;
+                }
+
+                let mut lines = None;
+                for segment in segments.by_ref() {
+                    match segment {
+                        "-f" | "--follow" => follow = true,
+                        segment => match segment.parse::<usize>() {
+                            Ok(count) if lines.is_none() => lines = Some(count.clamp(1, 1000)),
+                            _ => {
+                                writeln(&format!("Usage: {prefix} logs [lines] [-f]")).await;
+                                return;
+                            }
+                        },
+                    }
+                }
+                let lines = lines.unwrap_or(self.state.config.system.websocket_log_count);
+
+                let logs = match self.server.read_log(&self.state.docker, lines).await {
+                    Ok(logs) => logs,
+                    Err(err) => {
+                        tracing::error!(
+                            server = %self.server.uuid,
+                            "failed to read server logs: {:#?}",
+                            err,
+                        );
+
+                        writeln("An unexpected error occurred while reading the server logs. Please contact an Administrator.")
+                            .await;
+                        return;
+                    }
+                };
+
+                output = Some(if json {
+                    json!({ "lines": logs.lines().collect::<Vec<_>>() }).to_string()
+                } else {
+                    logs.lines().collect::<Vec<_>>().join("\r\n\x1b[2K")
+                });
+
+                if follow && !json {
+                    writeln("Following the server log, press any key to stop.").await;
+                }
+            }
+            Some("backups") => match segments.next() {
+                Some("list") => {
+                    if !self.has_permission(Permission::BackupRead).await {
+                        writeln("You are missing the `backup.read` permission to do this.").await;
+                        return;
+                    }
+
+                    let backups = self.server.configuration.read().await.backups.clone();
+
+                    if json {
+                        output = Some(json!({ "backups": backups }).to_string());
+                    } else if backups.is_empty() {
+                        writeln("This server has no backups.").await;
                     } else {
-                        format!(
-                            "{}%",
-                            self.server.configuration.read().await.build.cpu_limit
-                        )
+                        writeln("Backups:").await;
+                        for backup in backups {
+                            writeln(&format!("  {backup}")).await;
+                        }
                     }
-                ))
-                .await;
-                writeln(&format!(
-                    "  Memory Usage: {} / {} ({:.2}%)",
-                    human_bytes::human_bytes(resource_usage.memory_bytes as f64),
-                    if self.server.configuration.read().await.build.memory_limit == 0 {
-                        "Unlimited".to_string()
+                }
+                Some("create") => {
+                    if !self.has_permission(Permission::BackupCreate).await {
+                        writeln("You are missing the `backup.create` permission to do this.").await;
+                        return;
+                    }
+
+                    let name = segments.collect::<Vec<_>>().join(" ");
+                    let name = (!name.is_empty()).then_some(name);
+
+                    let (adapter, uuid) = match self
+                        .state
+                        .config
+                        .client
+                        .create_backup(self.server.uuid, name.as_deref(), &[])
+                        .await
+                    {
+                        Ok(result) => result,
+                        Err(err) => {
+                            tracing::error!(
+                                server = %self.server.uuid,
+                                "failed to create backup: {:#?}",
+                                err,
+                            );
+
+                            writeln("An unexpected error occurred while creating the backup. Please contact an Administrator.")
+                                .await;
+                            return;
+                        }
+                    };
+
+                    if self
+                        .state
+                        .backup_manager
+                        .fast_contains(&self.server, uuid)
+                        .await
+                    {
+                        writeln("Backup already exists.").await;
+                        return;
+                    }
+
+                    tokio::spawn({
+                        let state = Arc::clone(&self.state);
+                        let server = self.server.clone();
+
+                        async move {
+                            if let Err(err) = state
+                                .backup_manager
+                                .create(adapter, &server, uuid, String::new(), None)
+                                .await
+                            {
+                                tracing::error!(
+                                    "failed to create backup {} (adapter = {:?}) for {}: {}",
+                                    uuid,
+                                    adapter,
+                                    server.uuid,
+                                    err
+                                );
+                            }
+                        }
+                    });
+
+                    if json {
+                        output = Some(json!({ "uuid": uuid, "adapter": adapter }).to_string());
                     } else {
-                        human_bytes::human_bytes(
-                            (self.server.configuration.read().await.build.memory_limit
-                                * 1024
-                                * 1024) as f64,
-                        )
-                    },
-                    (resource_usage.memory_bytes as f64 / resource_usage.memory_limit_bytes as f64
-                        * 100.0)
-                        .min(100.0)
-                ))
-                .await;
-                writeln(&format!(
-                    "  Disk Usage: {} / {} ({:.2}%)",
-                    human_bytes::human_bytes(resource_usage.disk_bytes as f64),
-                    if self.server.filesystem.disk_limit() == 0 {
-                        "Unlimited".to_string()
+                        writeln(&format!("Started creating backup {uuid}.")).await;
+                    }
+                }
+                Some("restore") => {
+                    if !self.has_permission(Permission::BackupRestore).await {
+                        writeln("You are missing the `backup.restore` permission to do this.")
+                            .await;
+                        return;
+                    }
+
+                    let uuid = match segments.next().map(str::parse::<uuid::Uuid>) {
+                        Some(Ok(uuid)) => uuid,
+                        _ => {
+                            writeln(&format!(
+                                "Usage: {prefix} backups restore <uuid> [--truncate]"
+                            ))
+                            .await;
+                            return;
+                        }
+                    };
+                    let truncate_directory = segments.any(|segment| segment == "--truncate");
+
+                    if !self
+                        .server
+                        .configuration
+                        .read()
+                        .await
+                        .backups
+                        .contains(&uuid)
+                    {
+                        writeln("Backup not found.").await;
+                        return;
+                    }
+
+                    let backup = match self.state.backup_manager.find(uuid).await {
+                        Ok(Some(backup)) => backup,
+                        Ok(None) => {
+                            writeln("Backup not found.").await;
+                            return;
+                        }
+                        Err(err) => {
+                            tracing::error!(
+                                server = %self.server.uuid,
+                                backup = %uuid,
+                                "failed to find backup: {:#?}",
+                                err,
+                            );
+
+                            writeln("An unexpected error occurred while finding the backup. Please contact an Administrator.")
+                                .await;
+                            return;
+                        }
+                    };
+
+                    if backup.adapter() == BackupAdapter::S3 {
+                        writeln("S3 backups can only be restored from the panel.").await;
+                        return;
+                    }
+
+                    tokio::spawn({
+                        let state = Arc::clone(&self.state);
+                        let server = self.server.clone();
+
+                        async move {
+                            if let Err(err) = state
+                                .backup_manager
+                                .restore(&backup, &server, truncate_directory, None)
+                                .await
+                            {
+                                tracing::error!(
+                                    server = %server.uuid,
+                                    backup = %backup.uuid(),
+                                    adapter = ?backup.adapter(),
+                                    "failed to restore backup: {:#?}",
+                                    err
+                                );
+                            }
+                        }
+                    });
+
+                    if json {
+                        output = Some(json!({ "uuid": uuid }).to_string());
+                    } else {
+                        writeln(&format!("Started restoring backup {uuid}.")).await;
+                    }
+                }
+                _ => {
+                    writeln(&format!(
+                        "Usage: {prefix} backups <list|create [name]|restore <uuid> [--truncate]>"
+                    ))
+                    .await;
+                }
+            },
+            Some("schedules") => match segments.next() {
+                Some("list") => {
+                    if !self.has_permission(Permission::ScheduleRead).await {
+                        writeln("You are missing the `schedule.read` permission to do this.").await;
+                        return;
+                    }
+
+                    let mut schedules = Vec::new();
+                    for schedule in self.server.schedules.get_schedules().await.iter() {
+                        let status = schedule.status.read().await;
+                        schedules.push((schedule.uuid, status.running, status.step));
+                    }
+
+                    if json {
+                        output = Some(
+                            json!({
+                                "schedules": schedules
+                                    .iter()
+                                    .map(|(uuid, running, step)| json!({
+                                        "uuid": uuid,
+                                        "running": running,
+                                        "step": step,
+                                    }))
+                                    .collect::<Vec<_>>(),
+                            })
+                            .to_string(),
+                        );
+                    } else if schedules.is_empty() {
+                        writeln("This server has no schedules.").await;
+                    } else {
+                        writeln("Schedules:").await;
+                        for (uuid, running, step) in schedules {
+                            writeln(&match (running, step) {
+                                (true, Some(step)) => format!("  {uuid} - running (step {step})"),
+                                (true, None) => format!("  {uuid} - running"),
+                                (false, _) => format!("  {uuid} - idle"),
+                            })
+                            .await;
+                        }
+                    }
+                }
+                Some(action @ ("trigger" | "abort")) => {
+                    if !self.has_permission(Permission::ScheduleUpdate).await {
+                        writeln("You are missing the `schedule.update` permission to do this.")
+                            .await;
+                        return;
+                    }
+
+                    let uuid = match segments.next().map(str::parse::<uuid::Uuid>) {
+                        Some(Ok(uuid)) => uuid,
+                        _ => {
+                            writeln(&format!(
+                                "Usage: {prefix} schedules <trigger <uuid> [--skip-condition]|abort <uuid>>"
+                            ))
+                            .await;
+                            return;
+                        }
+                    };
+
+                    let found = if action == "trigger" {
+                        let skip_condition = segments.any(|segment| segment == "--skip-condition");
+
+                        match self
+                            .server
+                            .schedules
+                            .get_schedules()
+                            .await
+                            .iter()
+                            .find(|s| s.uuid == uuid)
+                        {
+                            Some(schedule) => {
+                                schedule.trigger(skip_condition);
+                                true
+                            }
+                            None => false,
+                        }
+                    } else {
+                        match self
+                            .server
+                            .schedules
+                            .get_mut_schedules()
+                            .await
+                            .iter_mut()
+                            .find(|s| s.uuid == uuid)
+                        {
+                            Some(schedule) => {
+                                schedule.recreate_executor(self.server.clone()).await;
+                                true
+                            }
+                            None => false,
+                        }
+                    };
+
+                    if !found {
+                        writeln("Schedule not found.").await;
+                        return;
+                    }
+
+                    if json {
+                        output = Some(json!({ "uuid": uuid }).to_string());
+                    } else if action == "trigger" {
+                        writeln(&format!("Triggered schedule {uuid}.")).await;
                     } else {
-                        human_bytes::human_bytes(self.server.filesystem.disk_limit() as f64)
-                    },
-                    (resource_usage.disk_bytes as f64 / self.server.filesystem.disk_limit() as f64
-                        * 100.0)
-                        .min(100.0)
-                ))
-                .await;
-                writeln("  Network Usage:").await;
-                writeln(&format!(
-                    "    Received: {}",
-                    human_bytes::human_bytes(resource_usage.network.rx_bytes as f64)
-                ))
-                .await;
-                writeln(&format!(
-                    "    Sent: {}",
-                    human_bytes::human_bytes(resource_usage.network.tx_bytes as f64)
-                ))
-                .await;
+                        writeln(&format!("Aborted schedule {uuid}.")).await;
+                    }
+                }
+                _ => {
+                    writeln(&format!(
+                        "Usage: {prefix} schedules <list|trigger <uuid> [--skip-condition]|abort <uuid>>"
+                    ))
+                    .await;
+                }
+            },
+            Some("operations") => {
+                if !self.has_permission(Permission::FileRead).await {
+                    writeln("You are missing the `file.read` permission to do this.").await;
+                    return;
+                }
+
+                let operations: Vec<(uuid::Uuid, FilesystemOperation)> = self
+                    .server
+                    .filesystem
+                    .operations
+                    .operations()
+                    .await
+                    .iter()
+                    .map(|(uuid, (operation, _))| (*uuid, operation.clone()))
+                    .collect();
+
+                if json {
+                    output = Some(
+                        json!({
+                            "operations": operations
+                                .iter()
+                                .map(|(uuid, operation)| json!({
+                                    "uuid": uuid,
+                                    "operation": operation,
+                                }))
+                                .collect::<Vec<_>>(),
+                        })
+                        .to_string(),
+                    );
+                } else if operations.is_empty() {
+                    writeln("There are no running operations.").await;
+                } else {
+                    writeln("Operations:").await;
+                    for (uuid, operation) in operations {
+                        writeln(&format!("  {uuid} - {}", describe_operation(&operation))).await;
+                    }
+                }
+            }
+            Some("compress") => {
+                if !self.has_permission(Permission::FileArchive).await {
+                    writeln("You are missing the `file.archive` permission to do this.").await;
+                    return;
+                }
+
+                let (name, files) = match segments.next() {
+                    Some(name) => (name, segments.map(PathBuf::from).collect::<Vec<_>>()),
+                    None => (Default::default(), Vec::new()),
+                };
+
+                if files.is_empty() {
+                    writeln(&format!("Usage: {prefix} compress <archive> <files...>")).await;
+                    return;
+                }
+
+                let format = match archive_format(name) {
+                    Some(format) => format,
+                    None => {
+                        writeln("Unsupported archive format, use one of .tar, .tar.gz, .tar.xz, .tar.bz2, .tar.lz4, .tar.zst, .zip or .7z.")
+                            .await;
+                        return;
+                    }
+                };
+
+                let root = match self.server.filesystem.async_canonicalize("").await {
+                    Ok(root) => root,
+                    Err(_) => {
+                        writeln("Root not found.").await;
+                        return;
+                    }
+                };
+
+                let file_name = root.join(name);
+                if self.is_ignored(&file_name, false).await {
+                    writeln("Archive not found.").await;
+                    return;
+                }
+
+                let mut total_size = 0;
+                for file in &files {
+                    let metadata = match self.server.filesystem.async_metadata(file).await {
+                        Ok(metadata) => metadata,
+                        Err(_) => {
+                            writeln(&format!("File {} not found.", file.display())).await;
+                            return;
+                        }
+                    };
+
+                    if self.is_ignored(file, metadata.is_dir()).await {
+                        writeln(&format!("File {} not found.", file.display())).await;
+                        return;
+                    }
+
+                    if metadata.is_dir() {
+                        total_size += self
+                            .server
+                            .filesystem
+                            .disk_usage
+                            .read()
+                            .await
+                            .get_size(file)
+                            .unwrap_or(0);
+                    } else {
+                        total_size += metadata.len();
+                    }
+                }
+
+                self.server
+                    .activity
+                    .log_activity(Activity {
+                        event: ActivityEvent::FileCompress,
+                        user: Some(self.user_uuid),
+                        ip: self.user_ip,
+                        metadata: Some(json!({
+                            "directory": root.display().to_string(),
+                            "name": name,
+                            "files": files.iter().map(|file| file.to_string_lossy()).collect::<Vec<_>>(),
+                        })),
+                        timestamp: chrono::Utc::now(),
+                    })
+                    .await;
+
+                let progress = Arc::new(AtomicU64::new(0));
+                let total = Arc::new(AtomicU64::new(total_size));
+
+                let (identifier, _) = self
+                    .server
+                    .filesystem
+                    .operations
+                    .add_operation(
+                        FilesystemOperation::Compress {
+                            path: file_name.clone(),
+                            progress: progress.clone(),
+                            total,
+                        },
+                        {
+                            let server = self.server.clone();
+                            let compression_level =
+                                self.state.config.system.backups.compression_level;
+                            let threads = self.state.config.api.file_compression_threads;
+
+                            async move {
+                                let ignored = server.filesystem.get_ignored().await;
+                                let writer = tokio::task::spawn_blocking({
+                                    let server = server.clone();
+
+                                    move || {
+                                        crate::server::filesystem::writer::FileSystemWriter::new(
+                                            server, &file_name, None, None,
+                                        )
+                                    }
+                                })
+                                .await??;
+
+                                match format {
+                                    ArchiveFormat::Zip => {
+                                        crate::server::filesystem::archive::create::create_zip(
+                                            server.filesystem.clone(),
+                                            writer,
+                                            &root,
+                                            files,
+                                            Some(progress),
+                                            vec![ignored],
+                                            crate::server::filesystem::archive::create::CreateZipOptions {
+                                                compression_level,
+                                            },
+                                        )
+                                        .await
+                                    }
+                                    ArchiveFormat::SevenZip => {
+                                        crate::server::filesystem::archive::create::create_7z(
+                                            server.filesystem.clone(),
+                                            writer,
+                                            &root,
+                                            files,
+                                            Some(progress),
+                                            vec![ignored],
+                                            crate::server::filesystem::archive::create::Create7zOptions {
+                                                compression_level,
+                                                threads,
+                                            },
+                                        )
+                                        .await
+                                    }
+                                    format => {
+                                        crate::server::filesystem::archive::create::create_tar(
+                                            server.filesystem.clone(),
+                                            writer,
+                                            &root,
+                                            files,
+                                            Some(progress),
+                                            vec![ignored],
+                                            crate::server::filesystem::archive::create::CreateTarOptions {
+                                                compression_type: match format {
+                                                    ArchiveFormat::TarGz => CompressionType::Gz,
+                                                    ArchiveFormat::TarXz => CompressionType::Xz,
+                                                    ArchiveFormat::TarBz2 => CompressionType::Bz2,
+                                                    ArchiveFormat::TarLz4 => CompressionType::Lz4,
+                                                    ArchiveFormat::TarZstd => CompressionType::Zstd,
+                                                    _ => CompressionType::None,
+                                                },
+                                                compression_level,
+                                                threads,
+                                            },
+                                        )
+                                        .await
+                                    }
+                                }
+                            }
+                        },
+                    )
+                    .await;
+
+                if json {
+                    output = Some(json!({ "identifier": identifier }).to_string());
+                } else {
+                    writeln(&format!(
+                        "Started compressing into {name} (operation {identifier})."
+                    ))
+                    .await;
+                }
+            }
+            Some("decompress") => {
+                if !self.has_permission(Permission::FileArchive).await {
+                    writeln("You are missing the `file.archive` permission to do this.").await;
+                    return;
+                }
+
+                let source = match segments.next() {
+                    Some(file) => PathBuf::from(file),
+                    None => {
+                        writeln(&format!("Usage: {prefix} decompress <archive> [directory]")).await;
+                        return;
+                    }
+                };
+
+                let destination = match segments.next() {
+                    Some(directory) => Path::new(directory),
+                    None => source.parent().unwrap_or(Path::new("")),
+                };
+                let destination = match self.server.filesystem.async_canonicalize(destination).await
+                {
+                    Ok(destination) => destination,
+                    Err(_) => {
+                        writeln("Directory not found.").await;
+                        return;
+                    }
+                };
+
+                let metadata = self.server.filesystem.async_metadata(&destination).await;
+                if !metadata.map(|m| m.is_dir()).unwrap_or(true)
+                    || self.is_ignored(&destination, true).await
+                {
+                    writeln("Directory not found.").await;
+                    return;
+                }
+
+                if self.is_ignored(&source, false).await {
+                    writeln("Archive not found.").await;
+                    return;
+                }
+
+                let archive = match crate::server::filesystem::archive::Archive::open(
+                    self.server.clone(),
+                    source.clone(),
+                )
+                .await
+                {
+                    Some(archive) => archive,
+                    None => {
+                        writeln("Failed to open archive.").await;
+                        return;
+                    }
+                };
+
+                self.server
+                    .activity
+                    .log_activity(Activity {
+                        event: ActivityEvent::FileDecompress,
+                        user: Some(self.user_uuid),
+                        ip: self.user_ip,
+                        metadata: Some(json!({
+                            "directory": destination.display().to_string(),
+                            "file": source.display().to_string(),
+                        })),
+                        timestamp: chrono::Utc::now(),
+                    })
+                    .await;
+
+                let progress = Arc::new(AtomicU64::new(0));
+                let total = Arc::new(AtomicU64::new(0));
+
+                let (identifier, _) = self
+                    .server
+                    .filesystem
+                    .operations
+                    .add_operation(
+                        FilesystemOperation::Decompress {
+                            path: source.clone(),
+                            destination: destination.clone(),
+                            progress: progress.clone(),
+                            total: total.clone(),
+                        },
+                        {
+                            let server = self.server.clone();
+
+                            async move {
+                                archive
+                                    .extract(destination.clone(), Some(progress), Some(total))
+                                    .await?;
+
+                                server.filesystem.chown_path(&destination).await
+                            }
+                        },
+                    )
+                    .await;
+
+                if json {
+                    output = Some(json!({ "identifier": identifier }).to_string());
+                } else {
+                    writeln(&format!(
+                        "Started decompressing {} (operation {identifier}).",
+                        source.display()
+                    ))
+                    .await;
+                }
             }
             _ => {
                 writeln("Unknown command. Type '.wings help' for a list of commands.").await;
             }
         }
+
+        if let Some(output) = output {
+            writer
+                .write_all(format!("{output}\r\n\x1b[2K").as_bytes())
+                .await
+                .unwrap_or_default();
+        }
+
+        if follow {
+            self.start_following(channel_writer, json);
+        }
     }
 
     async fn handle_special_keys(
@@ -462,7 +1316,7 @@ impl ShellSession {
                     match self.mode {
                         ShellMode::Normal => {
                             if line.starts_with(&self.state.config.system.sftp.shell.cli.name) {
-                                self.handle_cli_command(&line, data_writer).await;
+                                self.handle_cli_command(&line, data_writer, writer).await;
                             } else if self.has_permission(Permission::ControlConsole).await {
                                 if self.server.state.get_state()
                                     != crate::server::state::ServerState::Offline
@@ -758,6 +1612,7 @@ impl ShellSession {
 
             futures.push({
                 let server = self.server.clone();
+                let following = Arc::clone(&self.following);
                 let mut writer = writer.make_writer();
 
                 Box::pin(async move {
@@ -765,6 +1620,7 @@ impl ShellSession {
                         if let Some(mut stdout) = server.container_stdout().await {
                             loop {
                                 match stdout.recv().await {
+                                    Ok(_) if following.load(Ordering::Relaxed) => {}
                                     Ok(stdout) => {
                                         if let Err(err) = writer
                                             .write_all(format!("{stdout}\r\n\x1b[2K").as_bytes())
@@ -808,6 +1664,10 @@ impl ShellSession {
                         match reader.read(&mut buffer).await {
                             Ok(0) => break,
                             Ok(n) => {
+                                if self.stop_following(&mut Box::pin(&mut data_writer)).await {
+                                    continue;
+                                }
+
                                 for &byte in &buffer[..n] {
                                     if escape_sequence {
                                         sequence_buffer.push(byte);
@@ -867,6 +1727,10 @@ impl ShellSession {
                             }
                         }
                     }
+
+                    if let Some(follow) = self.follow.take() {
+                        follow.abort();
+                    }
                 })
             };
 
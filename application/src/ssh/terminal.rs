@@ -0,0 +1,269 @@
+use crate::routes::State;
+use futures_util::StreamExt;
+use russh::{Channel, server::Msg};
+use std::net::IpAddr;
+use tokio::io::AsyncWriteExt;
+
+/// A real shell inside of the server container, ran through `docker exec` with a pty
+pub struct TerminalSession {
+    pub state: State,
+    pub server: crate::server::Server,
+
+    pub user_ip: Option<IpAddr>,
+    pub user_uuid: uuid::Uuid,
+
+    pub term: String,
+    /// The terminal size as (columns, rows), updated on window change requests
+    pub size: tokio::sync::watch::Receiver<(u32, u32)>,
+
+    pub _session: super::limits::SessionGuard,
+}
+
+impl TerminalSession {
+    fn container_user(&self) -> String {
+        let user = &self.state.config.system.user;
+
+        if user.rootless.enabled {
+            format!(
+                "{}:{}",
+                user.rootless.container_uid, user.rootless.container_gid
+            )
+        } else {
+            format!("{}:{}", user.uid, user.gid)
+        }
+    }
+
+    async fn resize(&self, exec_id: &str, (columns, rows): (u32, u32)) {
+        if let Err(err) = self
+            .state
+            .docker
+            .resize_exec(
+                exec_id,
+                bollard::exec::ResizeExecOptions {
+                    height: rows.min(u16::MAX as u32) as u16,
+                    width: columns.min(u16::MAX as u32) as u16,
+                },
+            )
+            .await
+        {
+            tracing::debug!(
+                server = %self.server.uuid,
+                "failed to resize container terminal: {:#?}",
+                err
+            );
+        }
+    }
+
+    /// Hangs up the exec'd process if it is still running, killing it if it does not exit in time.
+    /// The pid is only signaled if it belongs to the server container
+    async fn kill(&self, exec_id: &str, docker_id: &str) {
+        let exec = match self.state.docker.inspect_exec(exec_id).await {
+            Ok(exec) if exec.running == Some(true) => exec,
+            _ => return,
+        };
+
+        let Some(pid) = exec
+            .pid
+            .and_then(|pid| i32::try_from(pid).ok())
+            .and_then(rustix::process::Pid::from_raw)
+        else {
+            return;
+        };
+
+        match tokio::fs::read_to_string(format!("/proc/{}/cgroup", pid.as_raw_nonzero())).await {
+            Ok(cgroup) if cgroup.contains(docker_id) => {}
+            _ => {
+                tracing::warn!(
+                    server = %self.server.uuid,
+                    "unable to kill container shell process {}, it is not part of the container",
+                    pid.as_raw_nonzero()
+                );
+                return;
+            }
+        }
+
+        if rustix::process::kill_process(pid, rustix::process::Signal::HUP).is_err() {
+            return;
+        }
+
+        for _ in 0..20 {
+            tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+
+            match self.state.docker.inspect_exec(exec_id).await {
+                Ok(exec) if exec.running == Some(true) => {}
+                _ => return,
+            }
+        }
+
+        tracing::debug!(
+            server = %self.server.uuid,
+            "container shell did not exit after hangup, killing it"
+        );
+
+        rustix::process::kill_process(pid, rustix::process::Signal::KILL).ok();
+    }
+
+    pub fn run(mut self, channel: Channel<Msg>) {
+        tokio::spawn(async move {
+            let (mut reader, writer) = channel.split();
+
+            let docker_id = self
+                .server
+                .container
+                .read()
+                .await
+                .as_ref()
+                .map(|container| container.docker_id.clone());
+
+            let docker_id = match docker_id {
+                Some(docker_id)
+                    if self.server.state.get_state()
+                        != crate::server::state::ServerState::Offline =>
+                {
+                    docker_id
+                }
+                _ => {
+                    writer
+                        .make_writer()
+                        .write_all(b"Server is not running.\r\n")
+                        .await
+                        .unwrap_or_default();
+                    writer.exit_status(1).await.unwrap_or_default();
+                    writer.close().await.unwrap_or_default();
+
+                    return;
+                }
+            };
+
+            let result: Result<i64, anyhow::Error> = async {
+                let exec = self
+                    .state
+                    .docker
+                    .create_exec(
+                        &docker_id,
+                        bollard::exec::CreateExecOptions {
+                            attach_stdin: Some(true),
+                            attach_stdout: Some(true),
+                            attach_stderr: Some(true),
+                            tty: Some(true),
+                            env: Some(vec![format!("TERM={}", self.term)]),
+                            cmd: Some(
+                                self.state
+                                    .config
+                                    .system
+                                    .sftp
+                                    .shell
+                                    .container
+                                    .command
+                                    .clone(),
+                            ),
+                            user: Some(self.container_user()),
+                            working_dir: Some("/home/container".to_string()),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+
+                let (mut output, mut input) = match self
+                    .state
+                    .docker
+                    .start_exec(
+                        &exec.id,
+                        Some(bollard::exec::StartExecOptions {
+                            detach: false,
+                            tty: true,
+                            output_capacity: None,
+                        }),
+                    )
+                    .await?
+                {
+                    bollard::exec::StartExecResults::Attached { output, input } => (output, input),
+                    bollard::exec::StartExecResults::Detached => {
+                        return Err(anyhow::anyhow!("exec unexpectedly detached"));
+                    }
+                };
+
+                tracing::info!(
+                    server = %self.server.uuid,
+                    user = %self.user_uuid,
+                    ip = ?self.user_ip,
+                    "opened container shell over ssh"
+                );
+
+                let size = *self.size.borrow_and_update();
+                self.resize(&exec.id, size).await;
+
+                let output_task = async {
+                    let mut writer = writer.make_writer();
+
+                    while let Some(Ok(output)) = output.next().await {
+                        if writer.write_all(&output.into_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                };
+
+                let input_task = async {
+                    let mut reader = reader.make_reader();
+
+                    tokio::io::copy(&mut reader, &mut input).await.ok();
+                };
+
+                let resize_task = async {
+                    let mut size = self.size.clone();
+                    while size.changed().await.is_ok() {
+                        let new_size = *size.borrow_and_update();
+                        self.resize(&exec.id, new_size).await;
+                    }
+
+                    std::future::pending::<()>().await;
+                };
+
+                tokio::select! {
+                    _ = output_task => {}
+                    _ = input_task => {}
+                    _ = resize_task => {}
+                }
+
+                // the client disconnected or closed stdin, the shell would otherwise keep running
+                self.kill(&exec.id, &docker_id).await;
+
+                Ok(self
+                    .state
+                    .docker
+                    .inspect_exec(&exec.id)
+                    .await
+                    .ok()
+                    .and_then(|exec| exec.exit_code)
+                    .unwrap_or_default())
+            }
+            .await;
+
+            let exit_code = match result {
+                Ok(exit_code) => exit_code,
+                Err(err) => {
+                    tracing::error!(
+                        server = %self.server.uuid,
+                        "failed to run container shell: {:#?}",
+                        err
+                    );
+
+                    writer
+                        .make_writer()
+                        .write_all(b"Failed to start container shell.\r\n")
+                        .await
+                        .unwrap_or_default();
+
+                    1
+                }
+            };
+
+            writer
+                .exit_status(exit_code.clamp(0, 255) as u32)
+                .await
+                .unwrap_or_default();
+            writer.eof().await.unwrap_or_default();
+            writer.close().await.unwrap_or_default();
+        });
+    }
+}